@@ -8,11 +8,8 @@ use keystone_common::errors::CommonError;
 use keystone_common::events::TreasuryMovement;
 use keystone_common::events::{LiquidityAdded, LiquidityRemoved, SwapExecuted};
 use keystone_common::fees::FeeConfig;
-#[cfg(not(target_arch = "bpf"))]
-use solana_security_txt::security_txt;
-
-#[cfg(not(target_arch = "bpf"))]
-security_txt! {
+#[cfg(all(not(target_arch = "bpf"), not(feature = "no-entrypoint")))]
+solana_security_txt::security_txt! {
     name: "Keystone AMM",
     project_url: "https://github.com/keystone-labs/keystone-vertex",
     contacts: "email:security@keystonelabs.xyz",
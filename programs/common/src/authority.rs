@@ -14,6 +14,8 @@ pub enum Role {
     Ops,
     /// Treasury operator for fund movements.
     Treasury,
+    /// Emergency role that may pause and unpause a program.
+    Guardian,
 }
 
 impl Role {
@@ -23,6 +25,7 @@ impl Role {
             Role::Admin => b"admin",
             Role::Ops => b"ops",
             Role::Treasury => b"treasury",
+            Role::Guardian => b"guardian",
         }
     }
 }
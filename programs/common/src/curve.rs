@@ -79,6 +79,7 @@ pub trait PricingCurve {
 }
 
 /// Linear bonding curve implementation with integer math.
+#[derive(Clone, Copy)]
 pub struct LinearCurve<'a> {
     config: &'a CurveConfig,
 }
@@ -141,6 +142,193 @@ impl<'a> PricingCurve for LinearCurve<'a> {
     }
 }
 
+/// Largest `|k * (s - x0)|` the sigmoid evaluates; beyond it the share is already 0 or 1 at
+/// `SCALE` precision.
+const SIGMOID_Z_LIMIT: u64 = 40;
+
+/// Buy quote for an exact curve area: area plus fee, rounded up.
+fn buy_quote(config: &CurveConfig, base_amount: u64, area: Decimal) -> Result<CurveQuote> {
+    let fee_amount = config.fee_config.apply(area)?;
+    let total = area.checked_add(fee_amount)?;
+    Ok(CurveQuote {
+        base_amount,
+        quote_amount: total.to_u64_ceil()?,
+        fee_amount: fee_amount.to_u64_ceil()?,
+    })
+}
+
+/// Sell quote for an exact curve area: area minus fee, rounded down.
+fn sell_quote(config: &CurveConfig, base_amount: u64, area: Decimal) -> Result<CurveQuote> {
+    let fee_amount = config.fee_config.apply(area)?;
+    let net = area
+        .checked_sub(fee_amount)
+        .map_err(|_| CurveError::MathOverflow)?;
+    Ok(CurveQuote {
+        base_amount,
+        quote_amount: net.to_u64()?,
+        fee_amount: fee_amount.to_u64_ceil()?,
+    })
+}
+
+/// Checks that every trade inside `[0, max_supply]` can be quoted.
+///
+/// A buy of the whole supply and a buy of the last unit bound every intermediate value any
+/// trade computes, so a curve that quotes both never overflows mid-sale. Run it once when a
+/// curve is configured; the per-trade constructors stay cheap.
+pub fn assert_quotable(curve: &dyn PricingCurve, max_supply: u64) -> Result<()> {
+    require!(max_supply > 0, CurveError::InvalidConfig);
+    curve
+        .quote_buy(0, max_supply)
+        .and_then(|_| curve.quote_buy(max_supply - 1, 1))
+        .map_err(|_| CurveError::InvalidConfig)?;
+    Ok(())
+}
+
+/// Exponential bonding curve `price(s) = base_price * e^(k * s)`.
+///
+/// Trades are charged the exact integral of the price over the traded supply range. Buys round
+/// up and sells round down, so a buy followed by a sell of the same amount never pays out more
+/// than it took in.
+#[derive(Clone, Copy)]
+pub struct ExponentialCurve<'a> {
+    config: &'a CurveConfig,
+}
+
+impl<'a> ExponentialCurve<'a> {
+    /// Create new exponential curve instance.
+    pub fn new(config: &'a CurveConfig) -> Result<Self> {
+        config.assert_valid()?;
+        Ok(Self { config })
+    }
+
+    /// Area under the curve over `[start, start + amount]`:
+    /// `base_price * e^(k * start) * (e^(k * amount) - 1) / k`.
+    fn area(&self, start: u64, amount: u64) -> Result<Decimal> {
+        let base = Decimal::from_scaled(self.config.base_price);
+        if self.config.k == 0 {
+            return base.mul_int(amount);
+        }
+        let k = Decimal::from_scaled(self.config.k);
+        let start_price = base.checked_mul(k.mul_int(start)?.exp()?)?;
+        let growth = k.mul_int(amount)?.exp_m1()?.checked_div(k)?;
+        start_price.checked_mul(growth)
+    }
+}
+
+impl<'a> PricingCurve for ExponentialCurve<'a> {
+    fn quote_buy(&self, supply: u64, base_amount: u64) -> Result<CurveQuote> {
+        require!(base_amount > 0, CurveError::InvalidInput);
+        require!(
+            supply.saturating_add(base_amount) <= self.config.max_supply,
+            CurveError::SupplyExceeded
+        );
+        buy_quote(self.config, base_amount, self.area(supply, base_amount)?)
+    }
+
+    fn quote_sell(&self, supply: u64, base_amount: u64) -> Result<CurveQuote> {
+        require!(base_amount > 0, CurveError::InvalidInput);
+        require!(base_amount <= supply, CurveError::InsufficientSupply);
+        sell_quote(
+            self.config,
+            base_amount,
+            self.area(supply - base_amount, base_amount)?,
+        )
+    }
+}
+
+/// Sigmoid bonding curve `price(s) = base_price / (1 + e^(-k * (s - x0)))`.
+///
+/// The price ramps from zero towards the `base_price` ceiling and is half of it at `x0`. Trades
+/// are charged the exact integral over the traded range with the same rounding as
+/// [`ExponentialCurve`].
+#[derive(Clone, Copy)]
+pub struct SigmoidCurve<'a> {
+    config: &'a CurveConfig,
+}
+
+impl<'a> SigmoidCurve<'a> {
+    /// Create new sigmoid curve instance.
+    pub fn new(config: &'a CurveConfig) -> Result<Self> {
+        config.assert_valid()?;
+        Ok(Self { config })
+    }
+
+    /// Area under the curve over `[start, start + amount]`, `ceiling * (softplus(z2) -
+    /// softplus(z1)) / k` with `softplus(z) = ln(1 + e^z)`.
+    ///
+    /// While `z2 - z1` is within the limit this uses
+    /// `ln(1 + sigma(z1) * (e^(z2 - z1) - 1))`, which avoids subtracting two nearly equal
+    /// logarithms. Wider ranges use `(z2 - z1) + softplus(-z2) - softplus(-z1)`, where every
+    /// exponent is clamped to the limit, so no trade inside the supply overflows.
+    fn area(&self, start: u64, amount: u64) -> Result<Decimal> {
+        let ceiling = Decimal::from_scaled(self.config.base_price);
+        if self.config.k == 0 {
+            return ceiling.mul_int(amount)?.div_int(2);
+        }
+        let k = Decimal::from_scaled(self.config.k);
+        let width = k.mul_int(amount)?;
+        let limit = Decimal::from_integer(SIGMOID_Z_LIMIT);
+        let log = if width <= limit {
+            let one = Decimal::from_integer(1);
+            // e^(-z) for z = k * (start - x0), with |z| clamped to the limit.
+            let exp_neg_z = if start >= self.config.x0 {
+                let z = k.mul_int(start - self.config.x0)?;
+                one.checked_div(z.min(limit).exp()?)?
+            } else {
+                let z = k.mul_int(self.config.x0 - start)?;
+                z.min(limit).exp()?
+            };
+            let start_share = one.checked_div(one.checked_add(exp_neg_z)?)?;
+            start_share.checked_mul(width.exp_m1()?)?.ln_1p()?
+        } else {
+            let end = start
+                .checked_add(amount)
+                .ok_or(CurveError::SupplyExceeded)?;
+            let grown = width.checked_add(self.softplus_neg(end)?)?;
+            let shed = self.softplus_neg(start)?;
+            grown.max(shed).checked_sub(shed)?
+        };
+        ceiling.checked_mul(log)?.checked_div(k)
+    }
+
+    /// `softplus(-z) = ln(1 + e^(-z))` for `z = k * (supply - x0)`, with `|z|` clamped to the
+    /// limit inside the exponential.
+    fn softplus_neg(&self, supply: u64) -> Result<Decimal> {
+        let k = Decimal::from_scaled(self.config.k);
+        let limit = Decimal::from_integer(SIGMOID_Z_LIMIT);
+        let one = Decimal::from_integer(1);
+        if supply >= self.config.x0 {
+            let z = k.mul_int(supply - self.config.x0)?;
+            one.checked_div(z.min(limit).exp()?)?.ln_1p()
+        } else {
+            // ln(1 + e^w) = w + ln(1 + e^(-w)) for w = -z > 0.
+            let w = k.mul_int(self.config.x0 - supply)?;
+            w.checked_add(one.checked_div(w.min(limit).exp()?)?.ln_1p()?)
+        }
+    }
+}
+
+impl<'a> PricingCurve for SigmoidCurve<'a> {
+    fn quote_buy(&self, supply: u64, base_amount: u64) -> Result<CurveQuote> {
+        require!(base_amount > 0, CurveError::InvalidInput);
+        require!(
+            supply.saturating_add(base_amount) <= self.config.max_supply,
+            CurveError::SupplyExceeded
+        );
+        buy_quote(self.config, base_amount, self.area(supply, base_amount)?)
+    }
+
+    fn quote_sell(&self, supply: u64, base_amount: u64) -> Result<CurveQuote> {
+        require!(base_amount > 0, CurveError::InvalidInput);
+        require!(base_amount <= supply, CurveError::InsufficientSupply);
+        sell_quote(
+            self.config,
+            base_amount,
+            self.area(supply - base_amount, base_amount)?,
+        )
+    }
+}
+
 /// Error codes used by curve helpers.
 #[error_code]
 pub enum CurveError {
@@ -173,15 +361,26 @@ mod tests {
     use proptest::prelude::*;
 
     fn cfg(base_price: u64, k: u64, max_supply: u64) -> CurveConfig {
+        curve_cfg(CurveKind::Linear, base_price, k, 0, max_supply, 0)
+    }
+
+    fn curve_cfg(
+        kind: CurveKind,
+        base_price: u64,
+        k: u64,
+        x0: u64,
+        max_supply: u64,
+        fee_bps: u64,
+    ) -> CurveConfig {
         CurveConfig {
-            kind: CurveKind::Linear,
+            kind,
             base_price,
             k,
-            x0: 0,
+            x0,
             max_supply,
             fee_config: crate::fees::FeeConfig {
-                numerator: 0,
-                denominator: 1,
+                numerator: fee_bps,
+                denominator: 10_000,
                 fee_vault: Pubkey::default(),
             },
         }
@@ -196,5 +395,146 @@ mod tests {
             let q2 = lin.quote_buy(supply + amount, amount).unwrap();
             prop_assert!(q2.quote_amount >= q1.quote_amount);
         }
+
+        #[test]
+        fn exponential_price_monotonic(supply in 0u64..1_000_000, amount in 1u64..1_000_000, step in 1u64..1_000_000, base in 1u64..1_000_000_000, k in 0u64..10_000, fee in 0u64..500) {
+            let c = curve_cfg(CurveKind::Exponential, base, k, 0, 3_000_000, fee);
+            let exp = ExponentialCurve::new(&c).unwrap();
+            prop_assume!(assert_quotable(&exp, c.max_supply).is_ok());
+            let q1 = exp.quote_buy(supply, amount).unwrap();
+            let q2 = exp.quote_buy(supply + step, amount).unwrap();
+            prop_assert!(q2.quote_amount >= q1.quote_amount);
+        }
+
+        #[test]
+        fn exponential_round_trip_no_arbitrage(supply in 0u64..2_000_000, amount in 1u64..1_000_000, base in 1u64..1_000_000_000, k in 0u64..10_000, fee in 0u64..500) {
+            let c = curve_cfg(CurveKind::Exponential, base, k, 0, 3_000_000, fee);
+            let exp = ExponentialCurve::new(&c).unwrap();
+            prop_assume!(assert_quotable(&exp, c.max_supply).is_ok());
+            let paid = exp.quote_buy(supply, amount).unwrap();
+            let returned = exp.quote_sell(supply + amount, amount).unwrap();
+            prop_assert!(returned.quote_amount <= paid.quote_amount);
+        }
+
+        #[test]
+        fn sigmoid_price_monotonic(supply in 0u64..1_000_000, amount in 1u64..1_000_000, step in 1u64..1_000_000, base in 1u64..1_000_000_000_000, k in 0u64..10_000_000, x0 in 0u64..1_000_000, fee in 0u64..500) {
+            let c = curve_cfg(CurveKind::Sigmoid, base, k, x0, 3_000_000, fee);
+            let sig = SigmoidCurve::new(&c).unwrap();
+            assert_quotable(&sig, c.max_supply).unwrap();
+            let q1 = sig.quote_buy(supply, amount).unwrap();
+            let q2 = sig.quote_buy(supply + step, amount).unwrap();
+            prop_assert!(q2.quote_amount >= q1.quote_amount);
+        }
+
+        #[test]
+        fn sigmoid_round_trip_no_arbitrage(supply in 0u64..2_000_000, amount in 1u64..1_000_000, base in 1u64..1_000_000_000_000, k in 0u64..10_000_000, x0 in 0u64..1_000_000, fee in 0u64..500) {
+            let c = curve_cfg(CurveKind::Sigmoid, base, k, x0, 3_000_000, fee);
+            let sig = SigmoidCurve::new(&c).unwrap();
+            let paid = sig.quote_buy(supply, amount).unwrap();
+            let returned = sig.quote_sell(supply + amount, amount).unwrap();
+            prop_assert!(returned.quote_amount <= paid.quote_amount);
+        }
+    }
+
+    #[test]
+    fn exponential_matches_closed_form() {
+        // base 1.0, k = 0.001: integral over [1_000, 2_000] is (e^2 - e) / 0.001.
+        let c = curve_cfg(
+            CurveKind::Exponential,
+            1_000_000_000,
+            1_000_000,
+            0,
+            10_000,
+            0,
+        );
+        let quote = ExponentialCurve::new(&c)
+            .unwrap()
+            .quote_buy(1_000, 1_000)
+            .unwrap();
+        let exact = (2f64.exp() - 1f64.exp()) / 0.001;
+        assert!((quote.quote_amount as f64 - exact).abs() <= 1.0);
+    }
+
+    #[test]
+    fn sigmoid_is_symmetric_around_midpoint() {
+        // Ceiling 2.0: the range [x0 - 500, x0 + 500] averages exactly half the ceiling.
+        let c = curve_cfg(
+            CurveKind::Sigmoid,
+            2_000_000_000,
+            5_000_000,
+            10_000,
+            100_000,
+            0,
+        );
+        let sig = SigmoidCurve::new(&c).unwrap();
+        let quote = sig.quote_buy(9_500, 1_000).unwrap();
+        assert!(quote.quote_amount.abs_diff(1_000) <= 1);
+        let below = sig.quote_buy(0, 1_000).unwrap();
+        let above = sig.quote_buy(20_000, 1_000).unwrap();
+        assert!(below.quote_amount < 10 && above.quote_amount >= 1_999);
+    }
+
+    #[test]
+    fn curves_reject_supply_overflow() {
+        let c = curve_cfg(CurveKind::Exponential, 1_000_000_000, 1_000, 0, 100, 0);
+        assert!(ExponentialCurve::new(&c)
+            .unwrap()
+            .quote_buy(90, 11)
+            .is_err());
+        assert!(SigmoidCurve::new(&c).unwrap().quote_sell(5, 6).is_err());
+    }
+
+    #[test]
+    fn sigmoid_quotes_ranges_wider_than_the_exponent_limit() {
+        // k * amount = 50 exceeds the limit on both sides of the midpoint.
+        let c = curve_cfg(
+            CurveKind::Sigmoid,
+            2_000_000_000,
+            5_000_000,
+            10_000,
+            100_000,
+            0,
+        );
+        let sig = SigmoidCurve::new(&c).unwrap();
+        assert_quotable(&sig, c.max_supply).unwrap();
+        let below = sig.quote_buy(0, 10_000).unwrap();
+        let above = sig.quote_buy(10_000, 10_000).unwrap();
+        // Integral of 2 / (1 + e^(-0.005 * (s - 10_000))) over each half.
+        let softplus = |z: f64| (1.0 + z.exp()).ln();
+        let exact_below = 2.0 * (softplus(0.0) - softplus(-50.0)) / 0.005;
+        let exact_above = 2.0 * (softplus(50.0) - softplus(0.0)) / 0.005;
+        assert!((below.quote_amount as f64 - exact_below).abs() <= 1.0);
+        assert!((above.quote_amount as f64 - exact_above).abs() <= 1.0);
+        let whole = sig.quote_buy(0, 100_000).unwrap();
+        let exact_whole = 2.0 * (softplus(450.0) - softplus(-50.0)) / 0.005;
+        assert!((whole.quote_amount as f64 - exact_whole).abs() <= 1.0);
+    }
+
+    #[test]
+    fn exponential_rejects_unquotable_supply() {
+        // k = 1e-9 over 1e12 units needs e^1000.
+        let c = curve_cfg(
+            CurveKind::Exponential,
+            1_000_000_000,
+            1,
+            0,
+            1_000_000_000_000,
+            0,
+        );
+        let exp = ExponentialCurve::new(&c).unwrap();
+        assert!(exp.quote_buy(100_000_000_000, 1).is_err());
+        assert_eq!(
+            assert_quotable(&exp, c.max_supply),
+            Err(CurveError::InvalidConfig.into())
+        );
+        let c = curve_cfg(
+            CurveKind::Exponential,
+            1_000_000_000,
+            1,
+            0,
+            10_000_000_000,
+            0,
+        );
+        assert_quotable(&ExponentialCurve::new(&c).unwrap(), c.max_supply).unwrap();
     }
 }
@@ -6,13 +6,19 @@ use num_traits::{FromPrimitive, ToPrimitive};
 /// Scaling factor for fixed-point math (1e9 precision).
 pub const SCALE: u128 = 1_000_000_000u128;
 
+/// Euler's number scaled by `SCALE` (floored).
+const E_SCALED: u128 = 2_718_281_828;
+
+/// `ln(2)` scaled by `SCALE` (floored).
+const LN2_SCALED: u128 = 693_147_180;
+
 /// Error emitted when decimal math fails.
 #[derive(Debug, Clone, Copy, thiserror::Error)]
 #[error("decimal overflow")]
 pub struct DecimalError;
 
 /// Fixed-point decimal backed by u128 with 1e9 precision.
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Decimal(u128);
 
 impl Decimal {
@@ -72,6 +78,87 @@ impl Decimal {
             .ok_or_else(|| DecimalError.into())
     }
 
+    /// Divides decimals returning scaled result (floored).
+    pub fn checked_div(self, other: Self) -> Result<Self> {
+        self.0
+            .checked_mul(SCALE)
+            .and_then(|v| v.checked_div(other.0))
+            .map(Self)
+            .ok_or_else(|| DecimalError.into())
+    }
+
+    /// Computes `e^self`.
+    ///
+    /// The integer part is applied as repeated multiplication by `e` and the fractional part as a
+    /// Taylor series. Every step floors, so the result never exceeds the true value and is
+    /// non-decreasing in `self`.
+    pub fn exp(self) -> Result<Self> {
+        let whole = self.0 / SCALE;
+        let mut result = Self(self.0 % SCALE).exp_series(true)?;
+        for _ in 0..whole {
+            result = result.checked_mul(Self(E_SCALED))?;
+        }
+        Ok(result)
+    }
+
+    /// Computes `e^self - 1` without cancellation for small arguments.
+    pub fn exp_m1(self) -> Result<Self> {
+        if self.0 < SCALE {
+            self.exp_series(false)
+        } else {
+            self.exp()?.checked_sub(Self(SCALE))
+        }
+    }
+
+    /// Computes `ln(1 + self)`, floored and non-decreasing in `self`.
+    pub fn ln_1p(self) -> Result<Self> {
+        let mut y = self
+            .0
+            .checked_add(SCALE)
+            .ok_or_else(|| anchor_lang::error::Error::from(DecimalError))?;
+        let mut halvings = 0u128;
+        while y >= 2 * SCALE {
+            y /= 2;
+            halvings += 1;
+        }
+        // ln(y) = 2 * atanh(t) with t = (y - 1) / (y + 1) <= 1/3 for y in [1, 2).
+        let t = (y - SCALE) * SCALE / (y + SCALE);
+        let t2 = t * t / SCALE;
+        let mut power = t;
+        let mut divisor = 1u128;
+        let mut sum = 0u128;
+        while power > 0 {
+            sum += power / divisor;
+            power = power * t2 / SCALE;
+            divisor += 2;
+        }
+        Ok(Self(halvings * LN2_SCALED + 2 * sum))
+    }
+
+    /// Converts to u64 rounding any fractional portion up.
+    pub fn to_u64_ceil(self) -> Result<u64> {
+        self.0
+            .checked_add(SCALE - 1)
+            .map(|v| v / SCALE)
+            .and_then(|v| u64::try_from(v).ok())
+            .ok_or_else(|| DecimalError.into())
+    }
+
+    /// Taylor series of `e^x` for `x < 1`, optionally skipping the leading `1`.
+    fn exp_series(self, include_one: bool) -> Result<Self> {
+        let mut term = SCALE;
+        let mut sum = if include_one { SCALE } else { 0 };
+        let mut n = 1u128;
+        loop {
+            term = term * self.0 / SCALE / n;
+            if term == 0 {
+                return Ok(Self(sum));
+            }
+            sum += term;
+            n += 1;
+        }
+    }
+
     /// Converts to u64 by truncating fractional portion.
     pub fn to_u64(self) -> Result<u64> {
         self.0
@@ -137,6 +224,46 @@ mod tests {
             let back = scaled.div_int(k).unwrap();
             prop_assert!(back.to_u64().unwrap() <= n);
         }
+
+        #[test]
+        fn exp_tracks_float(raw in 0u128..30 * SCALE) {
+            let exact = (raw as f64 / SCALE as f64).exp();
+            let got = Decimal::from_scaled_raw(raw).exp().unwrap().as_scaled() as f64 / SCALE as f64;
+            prop_assert!(got <= exact * (1.0 + 1e-12));
+            prop_assert!((exact - got) / exact < 1e-7);
+        }
+
+        #[test]
+        fn exp_is_monotonic(raw in 0u128..30 * SCALE, step in 1u128..SCALE) {
+            let lo = Decimal::from_scaled_raw(raw).exp().unwrap();
+            let hi = Decimal::from_scaled_raw(raw + step).exp().unwrap();
+            prop_assert!(hi >= lo);
+        }
+
+        #[test]
+        fn ln_1p_tracks_float(raw in 0u128..1_000_000 * SCALE) {
+            let x = raw as f64 / SCALE as f64;
+            let got = Decimal::from_scaled_raw(raw).ln_1p().unwrap().as_scaled() as f64 / SCALE as f64;
+            prop_assert!((x.ln_1p() - got).abs() < 1e-7);
+        }
+
+        #[test]
+        fn ln_1p_is_monotonic(raw in 0u128..1_000 * SCALE, step in 1u128..SCALE) {
+            let lo = Decimal::from_scaled_raw(raw).ln_1p().unwrap();
+            let hi = Decimal::from_scaled_raw(raw + step).ln_1p().unwrap();
+            prop_assert!(hi >= lo);
+        }
+    }
+
+    #[test]
+    fn exp_m1_keeps_small_arguments() {
+        let x = Decimal::from_scaled(3);
+        assert_eq!(x.exp_m1().unwrap(), x);
+        assert_eq!(Decimal::default().exp().unwrap(), Decimal::from_integer(1));
+        assert_eq!(
+            Decimal::from_integer(1).ln_1p().unwrap().as_scaled(),
+            LN2_SCALED
+        );
     }
 }
 
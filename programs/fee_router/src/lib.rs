@@ -7,12 +7,19 @@
 
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::keccak;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use anchor_lang::system_program;
+use anchor_spl::token_interface::spl_token_2022::extension::{
+    transfer_fee::TransferFeeConfig, BaseStateWithExtensions, StateWithExtensions,
+};
+use anchor_spl::token_interface::{
+    self, spl_token_2022, HarvestWithheldTokensToMint, Mint, TokenAccount, TokenInterface,
+    TransferChecked,
+};
 use core::str::FromStr;
+use keystone_common::{authority::Role, merkle};
 
-mod meteora_cpi;
-mod stream_adapter;
-use stream_adapter::StreamLockedReader;
+pub mod meteora_cpi;
+pub mod stream_adapter;
 
 // Program ID is set by Anchor.toml; this will be patched during setup.
 declare_id!("B4yaCkpGZB9Xnm2ZRcj9k1stdkXzkCJdbU26EWj8h7Dc");
@@ -27,6 +34,40 @@ pub const PROGRESS_SEED: &[u8] = b"progress";
 pub const FEE_POS_OWNER_SEED: &[u8] = b"investor_fee_pos_owner";
 /// PDA seed: position registry.
 pub const POSITION_SEED: &[u8] = b"position";
+/// PDA seed: per-day investor payout bitmap.
+pub const PAYOUT_BITMAP_SEED: &[u8] = b"payout_bitmap";
+/// PDA seed: per-investor dust ledger.
+pub const DUST_LEDGER_SEED: &[u8] = b"dust";
+/// PDA seed: per-investor pull-claim checkpoint.
+pub const CHECKPOINT_SEED: &[u8] = b"checkpoint";
+/// PDA seed: distribution history ring buffer.
+pub const HISTORY_SEED: &[u8] = b"history";
+
+/// Closed days kept by `DistributionHistory`.
+pub const DISTRIBUTION_HISTORY_LEN: usize = 30;
+
+/// Max honorary positions registered under one policy.
+pub const MAX_HONORARY_POSITIONS: u8 = 4;
+
+/// Index seed of honorary position `index` in its `POSITION_SEED` registry and
+/// `FEE_POS_OWNER_SEED` owner PDA seeds. Position 0 keeps the single-position seeds
+/// (`[POSITION_SEED, policy]`, `[VAULT_SEED, policy, FEE_POS_OWNER_SEED]`): an empty
+/// seed derives the same address as none.
+pub fn position_index_seed(index: &u8) -> &[u8] {
+    match index {
+        0 => &[],
+        _ => core::slice::from_ref(index),
+    }
+}
+
+/// Max recipients in a policy's day-end remainder split.
+pub const MAX_REMAINDER_RECIPIENTS: usize = 4;
+
+/// Fixed-point scale of the pull-mode quote-per-locked-unit accumulator.
+pub const ACC_SCALE: u128 = 1_000_000_000_000;
+
+/// Minimum delay between proposing and applying a policy update (seconds).
+pub const POLICY_UPDATE_MIN_DELAY_SECS: i64 = 86_400;
 
 /// Errors used by fee router.
 #[error_code]
@@ -52,6 +93,27 @@ pub enum FeeRouterError {
     /// Unauthorized access.
     #[msg("Unauthorized")]
     Unauthorized,
+    /// Stream account is not a decodable stream of a supported program.
+    #[msg("Invalid stream account")]
+    InvalidStream,
+    /// Page entry is not in the policy investor set.
+    #[msg("Invalid investor proof")]
+    InvalidInvestorProof,
+    /// No pending policy update, or its delay has not elapsed.
+    #[msg("Policy update not ready")]
+    PolicyUpdateNotReady,
+    /// Crank page targets a different phase than the day is in.
+    #[msg("Crank page does not match the distribution phase")]
+    WrongPhase,
+    /// Instruction is not available in the policy claim mode.
+    #[msg("Claim mode mismatch")]
+    ClaimModeMismatch,
+    /// Snapshot closed before every honorary position was collected today.
+    #[msg("Honorary position not collected today")]
+    PositionNotCollected,
+    /// Policy is paused by its guardian or authority.
+    #[msg("Policy is paused")]
+    Paused,
 }
 
 /// Global policy per pool (immutable except by authority).
@@ -59,12 +121,18 @@ pub enum FeeRouterError {
 pub struct Policy {
     /// Program authority that may update policy parameters.
     pub authority: Pubkey,
-    /// Meteora cp-amm pool bound to this policy.
+    /// Emergency key that may pause and unpause the policy (distinct from `authority`).
+    pub guardian: Pubkey,
+    /// Primary Meteora cp-amm pool (PDA seed); honorary position 0 lives in it.
     pub cp_pool: Pubkey,
     /// Quote mint; must match pool quote side.
     pub quote_mint: Pubkey,
-    /// Creator quote ATA to receive day-end remainder.
+    /// Token program of `quote_mint`: SPL Token or Token-2022 (fixed at init).
+    pub quote_token_program: Pubkey,
+    /// Creator quote ATA to receive day-end remainder (unless `remainder_split` is set).
     pub creator_quote_ata: Pubkey,
+    /// Optional weighted split of the day-end remainder.
+    pub remainder_split: RemainderSplit,
     /// Program treasury ATA (quote) owned by vault PDA.
     pub treasury_quote_ata: Pubkey,
     /// Max investor fee share in basis points (<= 10_000).
@@ -75,6 +143,33 @@ pub struct Policy {
     pub daily_cap_quote: u64,
     /// Minimum per-investor payout; smaller amounts are carried.
     pub min_payout_lamports: u64,
+    /// Crank payer's cut of each day's claimed quote in bps (<= 10_000), taken first.
+    pub crank_reward_bps: u16,
+    /// Per-day cap on the crank reward in quote lamports; 0 disables cap.
+    pub crank_reward_cap_quote: u64,
+    /// Merkle commitment to the investors eligible for distribution.
+    pub investor_set: InvestorSet,
+    /// Seconds after a day ends before anyone may force-close it.
+    pub force_close_grace_secs: i64,
+    /// On forced close, roll the carry into the next day instead of paying the creator.
+    pub roll_forced_carry: bool,
+    /// How investors receive their share (fixed at init).
+    pub claim_mode: ClaimMode,
+    /// Program whose accounts the investor set's `stream` entries are (fixed at init).
+    pub lock_source: LockSource,
+    /// Distribution period length in seconds (fixed at init; 86_400 for UTC days).
+    pub epoch_seconds: i64,
+    /// Shift of period boundaries from the unix epoch, `|offset| < epoch_seconds`.
+    pub epoch_offset_seconds: i64,
+    /// Most investor entries per `crank_distribute` page (> 0, fixed at init).
+    pub max_page_size: u8,
+    /// Honorary positions registered so far (indices `0..position_count`).
+    pub position_count: u8,
+    /// Proposed parameter change awaiting `apply_policy_update`.
+    pub pending_update: Option<PolicyUpdate>,
+    /// While set, cranks, claims and policy changes fail with `Paused`; collected
+    /// fees stay in the treasury.
+    pub paused: bool,
     /// Bump for PDA derivation.
     pub bump: u8,
     /// Whether initialized (sticky true after init).
@@ -84,23 +179,263 @@ pub struct Policy {
 impl Policy {
     /// Size of the Policy account including discriminator.
     pub const SPACE: usize = 8 + core::mem::size_of::<Policy>();
+
+    /// Keccak hash of the distribution config, emitted for audit.
+    pub fn config_hash(&self) -> [u8; 32] {
+        let remainder_split: Vec<u8> = self
+            .remainder_split
+            .recipients()
+            .iter()
+            .flat_map(|recipient| {
+                recipient
+                    .quote_ata
+                    .to_bytes()
+                    .into_iter()
+                    .chain(recipient.bps.to_le_bytes())
+            })
+            .collect();
+        keccak::hashv(&[
+            &self.cp_pool.to_bytes(),
+            &self.quote_mint.to_bytes(),
+            &self.quote_token_program.to_bytes(),
+            &self.creator_quote_ata.to_bytes(),
+            &remainder_split,
+            &self.treasury_quote_ata.to_bytes(),
+            &self.investor_fee_share_bps.to_le_bytes(),
+            &self.y0_total.to_le_bytes(),
+            &self.daily_cap_quote.to_le_bytes(),
+            &self.min_payout_lamports.to_le_bytes(),
+            &self.crank_reward_bps.to_le_bytes(),
+            &self.crank_reward_cap_quote.to_le_bytes(),
+            &self.investor_set.root,
+            &self.investor_set.count.to_le_bytes(),
+            &self.force_close_grace_secs.to_le_bytes(),
+            &[self.roll_forced_carry as u8],
+            &[self.claim_mode as u8],
+            &[self.lock_source as u8],
+            &self.epoch_seconds.to_le_bytes(),
+            &self.epoch_offset_seconds.to_le_bytes(),
+            &[self.max_page_size],
+        ])
+        .0
+    }
+
+    /// Distribution period containing `ts`: `floor((ts - epoch_offset_seconds) / epoch_seconds)`.
+    pub fn epoch_index(&self, ts: i64) -> Result<i64> {
+        require!(self.epoch_seconds > 0, FeeRouterError::ConstraintViolation);
+        Ok(ts
+            .checked_sub(self.epoch_offset_seconds)
+            .ok_or(FeeRouterError::ArithmeticOverflow)?
+            .div_euclid(self.epoch_seconds))
+    }
+
+    /// First unix timestamp of distribution period `epoch`.
+    pub fn epoch_start(&self, epoch: i64) -> Result<i64> {
+        epoch
+            .checked_mul(self.epoch_seconds)
+            .and_then(|ts| ts.checked_add(self.epoch_offset_seconds))
+            .ok_or(error!(FeeRouterError::ArithmeticOverflow))
+    }
+
+    /// Role `key` holds on this policy: `Guardian` for `guardian`, `Admin` for
+    /// `authority`.
+    pub fn role_of(&self, key: &Pubkey) -> Option<Role> {
+        if *key == self.guardian {
+            Some(Role::Guardian)
+        } else if *key == self.authority {
+            Some(Role::Admin)
+        } else {
+            None
+        }
+    }
+
+    /// Bit mask with one bit per registered honorary position.
+    pub fn positions_mask(&self) -> u8 {
+        ((1u16 << self.position_count) - 1) as u8
+    }
+}
+
+/// One destination of the day-end remainder.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RemainderRecipient {
+    /// Quote token account to pay.
+    pub quote_ata: Pubkey,
+    /// Share of the remainder in bps.
+    pub bps: u16,
+}
+
+/// Weighted split of the day-end remainder; empty routes it all to `creator_quote_ata`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RemainderSplit {
+    /// Recipients; only the first `count` are used.
+    pub recipients: [RemainderRecipient; MAX_REMAINDER_RECIPIENTS],
+    /// Number of recipients in use.
+    pub count: u8,
+}
+
+impl RemainderSplit {
+    /// Validates `recipients` (at most `MAX_REMAINDER_RECIPIENTS`, each `bps > 0`,
+    /// summing to 10_000 unless empty).
+    pub fn new(recipients: &[RemainderRecipient]) -> Result<Self> {
+        require!(
+            recipients.len() <= MAX_REMAINDER_RECIPIENTS,
+            FeeRouterError::ConstraintViolation
+        );
+        require!(
+            recipients.iter().all(|recipient| recipient.bps > 0),
+            FeeRouterError::ConstraintViolation
+        );
+        let total_bps: u32 = recipients
+            .iter()
+            .map(|recipient| recipient.bps as u32)
+            .sum();
+        require!(
+            recipients.is_empty() || total_bps == 10_000,
+            FeeRouterError::ConstraintViolation
+        );
+        let mut split = RemainderSplit {
+            count: recipients.len() as u8,
+            ..Default::default()
+        };
+        split.recipients[..recipients.len()].copy_from_slice(recipients);
+        Ok(split)
+    }
+
+    /// Recipients in use.
+    pub fn recipients(&self) -> &[RemainderRecipient] {
+        &self.recipients[..self.count as usize]
+    }
+}
+
+/// How investors receive their share of the daily investor pool.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ClaimMode {
+    /// The crank pays every investor in a payout pass.
+    #[default]
+    Push,
+    /// The crank records a per-locked-unit accumulator; investors pull with `claim_fees`.
+    Pull,
+}
+
+/// Where investors' still-locked amounts are read from.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LockSource {
+    /// Streamflow `Contract` accounts: deposited minus unlocked.
+    #[default]
+    Streamflow,
+    /// `keystone_vesting::VestingSchedule` accounts: `total - vested_amount(now)`.
+    KeystoneVesting,
+}
+
+/// Timelocked policy parameter change.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PolicyUpdate {
+    /// New max investor fee share in bps (<= 10_000).
+    pub investor_fee_share_bps: u16,
+    /// New Y0 total allocation (> 0).
+    pub y0_total: u64,
+    /// New daily cap; 0 disables cap.
+    pub daily_cap_quote: u64,
+    /// New dust threshold.
+    pub min_payout_lamports: u64,
+    /// New crank reward in bps.
+    pub crank_reward_bps: u16,
+    /// New per-day crank reward cap.
+    pub crank_reward_cap_quote: u64,
+    /// New creator quote ATA.
+    pub creator_quote_ata: Pubkey,
+    /// New remainder split.
+    pub remainder_split: RemainderSplit,
+    /// New force-close grace period (>= 0).
+    pub force_close_grace_secs: i64,
+    /// New forced-close carry mode.
+    pub roll_forced_carry: bool,
+    /// Earliest unix timestamp the update may be applied.
+    pub eligible_ts: i64,
+}
+
+impl PolicyUpdate {
+    /// True if the update changes nothing but the remainder recipients: the split and
+    /// `creator_quote_ata`, which takes the remainder of an empty split.
+    ///
+    /// Such an update may apply mid-day. This deliberately reverses, for reroutes
+    /// only, the rule that an update never applies to an open day: a recipient ATA
+    /// closed since it was validated otherwise fails every last page and
+    /// `force_close_day`, and the day could never close.
+    pub fn reroutes_only(&self, policy: &Policy) -> bool {
+        self.investor_fee_share_bps == policy.investor_fee_share_bps
+            && self.y0_total == policy.y0_total
+            && self.daily_cap_quote == policy.daily_cap_quote
+            && self.min_payout_lamports == policy.min_payout_lamports
+            && self.crank_reward_bps == policy.crank_reward_bps
+            && self.crank_reward_cap_quote == policy.crank_reward_cap_quote
+            && self.force_close_grace_secs == policy.force_close_grace_secs
+            && self.roll_forced_carry == policy.roll_forced_carry
+    }
+}
+
+/// Merkle commitment to the investor set, built with `keystone_common::merkle`.
+///
+/// Leaves are `keccak(index u32 LE || investor_quote_ata || stream)` for indices
+/// `0..count`; see [`investor_leaf`].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InvestorSet {
+    /// Merkle root over all investor leaves.
+    pub root: [u8; 32],
+    /// Number of investors (leaves) in the set.
+    pub count: u32,
 }
 
 /// Tracks idempotent, paginated daily distribution.
 #[account]
+#[derive(Default)]
 pub struct Progress {
-    /// Unix day (UTC) we’re currently distributing (floor(ts/86400)).
+    /// Distribution period we’re currently distributing (`Policy::epoch_index`).
     pub current_day: i64,
     /// Last distribution unix timestamp.
     pub last_distribution_ts: i64,
     /// Total claimed quote this day (from cp-amm).
     pub claimed_quote_today: u64,
-    /// Total distributed to investors today.
+    /// Total distributed to investors today, net of quote mint transfer fees.
     pub distributed_quote_today: u64,
+    /// Quote withheld by the quote mint's transfer fee on today's investor payouts.
+    pub transfer_fees_today: u64,
     /// Remainder carry within the same day across pages.
     pub carry_quote_today: u64,
-    /// Pagination cursor (opaque, provided by caller).
+    /// Sum of still-locked amounts recorded by snapshot pages today.
+    pub locked_total_today: u64,
+    /// Investor pool fixed when the snapshot pass closes.
+    pub investor_pool_today: u64,
+    /// Timestamp locked amounts are evaluated at for the whole day.
+    pub snapshot_ts: i64,
+    /// Current pass of the daily distribution.
+    pub phase: DistributionPhase,
+    /// Carry rolled over by a forced close; seeds the next day's claimed quote.
+    pub rolled_over_quote: u64,
+    /// True if the current day was closed by `force_close_day`.
+    pub force_closed: bool,
+    /// Pull mode: cumulative quote per locked unit, scaled by `ACC_SCALE`.
+    pub fee_per_locked_unit_acc: u128,
+    /// Pull mode: investor entitlement accrued but not yet settled into checkpoints,
+    /// scaled by `ACC_SCALE`.
+    pub pull_unsettled_scaled: u128,
+    /// Pull mode: entitlement settled into checkpoints, awaiting `claim_fees`.
+    pub pull_owed_quote: u64,
+    /// Crank reward reserved from today's claimed quote (fixed by the first page).
+    pub crank_reward_today: u64,
+    /// Crank reward paid out to page payers today.
+    pub crank_reward_paid_today: u64,
+    /// Crank reward each page of the day earns (fixed by the first page).
+    pub crank_reward_per_page: u64,
+    /// Honorary positions (bit per index) collected since the day started.
+    pub collected_positions: u8,
+    /// Quote held in the treasury for investor dust ledgers.
+    pub dust_reserve_quote: u64,
+    /// Next investor index expected in the current pass.
     pub page_cursor: u64,
+    /// True once the first day was opened; period index 0 is a real day, so
+    /// `current_day` alone cannot tell.
+    pub day_opened: bool,
     /// True once day’s final page is settled (creator remainder routed).
     pub day_closed: bool,
     /// Bump for PDA derivation.
@@ -110,11 +445,245 @@ pub struct Progress {
 impl Progress {
     /// Size of the Progress account including discriminator.
     pub const SPACE: usize = 8 + core::mem::size_of::<Progress>();
+
+    /// Investor pool not yet allocated today; transfer fees withheld on payouts
+    /// count as allocated.
+    pub fn investor_pool_remaining(&self) -> u64 {
+        self.investor_pool_today
+            .saturating_sub(self.distributed_quote_today)
+            .saturating_sub(self.transfer_fees_today)
+    }
+
+    /// Pull mode: investor quote held in the treasury awaiting `claim_fees`
+    /// (unsettled entitlement rounded up).
+    pub fn pull_reserve_quote(&self) -> u64 {
+        let unsettled =
+            u64::try_from(self.pull_unsettled_scaled.div_ceil(ACC_SCALE)).unwrap_or(u64::MAX);
+        self.pull_owed_quote.saturating_add(unsettled)
+    }
+
+    /// Starts day `day` at `now`: the snapshot pass begins and carry rolled over by a
+    /// forced close becomes today's claimed quote.
+    pub fn open_day(&mut self, day: i64, now: i64) {
+        self.current_day = day;
+        self.day_opened = true;
+        self.last_distribution_ts = now;
+        self.snapshot_ts = now;
+        self.claimed_quote_today = core::mem::take(&mut self.rolled_over_quote);
+        self.distributed_quote_today = 0;
+        self.transfer_fees_today = 0;
+        self.carry_quote_today = 0;
+        self.locked_total_today = 0;
+        self.investor_pool_today = 0;
+        self.crank_reward_today = 0;
+        self.crank_reward_paid_today = 0;
+        self.crank_reward_per_page = 0;
+        self.collected_positions = 0;
+        self.phase = DistributionPhase::Snapshot;
+        self.page_cursor = 0;
+        self.day_closed = false;
+        self.force_closed = false;
+    }
+
+    /// Opens `today` at `now` unless it is already open; rolling over requires the
+    /// previous day closed. Fails on a closed day.
+    pub fn enter_day(&mut self, today: i64, now: i64) -> Result<()> {
+        if !self.day_opened || today > self.current_day {
+            require!(
+                !self.day_opened || self.day_closed,
+                FeeRouterError::DailyWindowNotReady
+            );
+            self.open_day(today, now);
+        }
+        require!(!self.day_closed, FeeRouterError::DailyWindowNotReady);
+        Ok(())
+    }
+
+    /// Quote claimed today that went neither to investors (nor their transfer fees)
+    /// nor to crank payers.
+    pub fn undistributed_quote(&self) -> u64 {
+        self.claimed_quote_today
+            .saturating_sub(self.distributed_quote_today)
+            .saturating_sub(self.transfer_fees_today)
+            .saturating_sub(self.crank_reward_paid_today)
+    }
+}
+
+/// Pass of the two-phase daily distribution.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DistributionPhase {
+    /// Pages sum still-locked amounts; the last page fixes the investor pool.
+    #[default]
+    Snapshot,
+    /// Pages pay investors pro rata; the last page closes the day.
+    Payout,
+}
+
+/// Records which investors (by set index) were settled in the current day's payout pass.
+#[account]
+pub struct PayoutBitmap {
+    /// Day key the bits belong to.
+    pub day: i64,
+    /// One bit per investor index; set once the investor is settled for `day`.
+    pub bits: Vec<u8>,
+    /// Bump for PDA derivation.
+    pub bump: u8,
+}
+
+impl PayoutBitmap {
+    /// Size of the PayoutBitmap account for `investor_count` investors, including discriminator.
+    pub fn space(investor_count: u32) -> usize {
+        8 + 8 + 4 + Self::bitmap_len(investor_count) + 1
+    }
+
+    /// Bytes needed to hold one bit per investor.
+    pub fn bitmap_len(investor_count: u32) -> usize {
+        (investor_count as usize).div_ceil(8)
+    }
+
+    /// Clears all bits when `day` differs from the recorded day.
+    pub fn roll_to(&mut self, day: i64, investor_count: u32) {
+        let len = Self::bitmap_len(investor_count);
+        if self.day != day || self.bits.len() != len {
+            self.day = day;
+            self.bits = vec![0; len];
+        }
+    }
+
+    /// Whether investor `index` is already settled for the recorded day.
+    pub fn is_set(&self, index: u32) -> bool {
+        self.bits
+            .get(index as usize / 8)
+            .is_some_and(|byte| byte & (1 << (index % 8)) != 0)
+    }
+
+    /// Marks investor `index` as settled. Returns `false` if it already was.
+    pub fn mark(&mut self, index: u32) -> Result<bool> {
+        let byte = self
+            .bits
+            .get_mut(index as usize / 8)
+            .ok_or(FeeRouterError::InvalidInvestorPage)?;
+        let mask = 1u8 << (index % 8);
+        if *byte & mask != 0 {
+            return Ok(false);
+        }
+        *byte |= mask;
+        Ok(true)
+    }
+}
+
+/// Summary of one closed distribution day.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DayRecord {
+    /// Day key (`Policy::epoch_index`).
+    pub day: i64,
+    /// Quote claimed for the day.
+    pub claimed_quote: u64,
+    /// Quote paid or reserved for investors.
+    pub investor_quote: u64,
+    /// Quote routed to the remainder recipients; 0 when a forced close rolled it over.
+    pub creator_remainder: u64,
+    /// Investors in the set when the day closed.
+    pub investor_count: u32,
+    /// True if the day was closed by `force_close_day`.
+    pub force_closed: bool,
+    /// Slot the day closed at.
+    pub closed_slot: u64,
+}
+
+/// Ring buffer of the last `DISTRIBUTION_HISTORY_LEN` closed days of a policy.
+#[account]
+pub struct DistributionHistory {
+    /// Policy the history belongs to.
+    pub policy: Pubkey,
+    /// Slot the next record is written to.
+    pub head: u16,
+    /// Number of valid records.
+    pub len: u16,
+    /// Records in slot order; see `records` for chronological order.
+    pub records: [DayRecord; DISTRIBUTION_HISTORY_LEN],
+    /// Bump for PDA derivation.
+    pub bump: u8,
+}
+
+impl DistributionHistory {
+    /// Size of the DistributionHistory account including discriminator.
+    pub const SPACE: usize = 8 + core::mem::size_of::<DistributionHistory>();
+
+    /// Appends `record`, overwriting the oldest one once the buffer is full.
+    pub fn push(&mut self, record: DayRecord) {
+        self.records[self.head as usize] = record;
+        self.head = ((self.head as usize + 1) % DISTRIBUTION_HISTORY_LEN) as u16;
+        self.len = (self.len + 1).min(DISTRIBUTION_HISTORY_LEN as u16);
+    }
+
+    /// Valid records, oldest first.
+    pub fn records(&self) -> Vec<DayRecord> {
+        let start = (self.head as usize + DISTRIBUTION_HISTORY_LEN - self.len as usize)
+            % DISTRIBUTION_HISTORY_LEN;
+        (0..self.len as usize)
+            .map(|i| self.records[(start + i) % DISTRIBUTION_HISTORY_LEN])
+            .collect()
+    }
+
+    /// Most recently closed day, if any.
+    pub fn latest(&self) -> Option<DayRecord> {
+        (self.len > 0).then(|| {
+            self.records
+                [(self.head as usize + DISTRIBUTION_HISTORY_LEN - 1) % DISTRIBUTION_HISTORY_LEN]
+        })
+    }
+}
+
+/// Pull-mode claim checkpoint of one investor.
+#[account]
+pub struct InvestorCheckpoint {
+    /// Policy the checkpoint belongs to.
+    pub policy: Pubkey,
+    /// Investor quote ATA (investor set entry).
+    pub investor_quote_ata: Pubkey,
+    /// Accumulator value the checkpoint was last settled at.
+    pub fee_per_locked_unit_acc: u128,
+    /// Locked amount recorded by the investor's latest snapshot page.
+    pub locked_snapshot: u64,
+    /// Settled entitlement not yet claimed.
+    pub owed_quote: u64,
+    /// Total quote claimed so far.
+    pub claimed_total: u64,
+    /// Bump for PDA derivation.
+    pub bump: u8,
+}
+
+impl InvestorCheckpoint {
+    /// Size of the InvestorCheckpoint account including discriminator.
+    pub const SPACE: usize = 8 + core::mem::size_of::<InvestorCheckpoint>();
+}
+
+/// Sub-threshold payouts owed to one investor, carried across days.
+#[account]
+pub struct DustLedger {
+    /// Policy the ledger belongs to.
+    pub policy: Pubkey,
+    /// Investor quote ATA (investor set entry).
+    pub investor_quote_ata: Pubkey,
+    /// Quote owed but not yet paid (below `min_payout_lamports`).
+    pub accrued_quote: u64,
+    /// Locked amount recorded by today's snapshot page; the payout pass pays on it.
+    pub locked_snapshot: u64,
+    /// Bump for PDA derivation.
+    pub bump: u8,
+}
+
+impl DustLedger {
+    /// Size of the DustLedger account including discriminator.
+    pub const SPACE: usize = 8 + core::mem::size_of::<DustLedger>();
 }
 
-/// The empty ‘honorary’ DAMM v2 fee position (quote-only).
+/// One empty ‘honorary’ DAMM v2 fee position (quote-only) of a policy.
 #[account]
 pub struct HonoraryPosition {
+    /// Index in the policy's position list (PDA seed).
+    pub index: u8,
     /// Owner PDA that holds the cp-amm position.
     pub owner_pda: Pubkey,
     /// Meteora cp-amm position account pubkey.
@@ -143,7 +712,30 @@ pub mod keystone_fee_router {
             args.investor_fee_share_bps <= 10_000,
             FeeRouterError::ConstraintViolation
         );
+        require!(
+            args.crank_reward_bps <= 10_000,
+            FeeRouterError::ConstraintViolation
+        );
         require!(args.y0_total > 0, FeeRouterError::ConstraintViolation);
+        require!(
+            args.investor_set.count > 0,
+            FeeRouterError::ConstraintViolation
+        );
+        require!(
+            args.force_close_grace_secs >= 0,
+            FeeRouterError::ConstraintViolation
+        );
+        require!(args.epoch_seconds > 0, FeeRouterError::ConstraintViolation);
+        require!(
+            args.epoch_offset_seconds.unsigned_abs() < args.epoch_seconds.unsigned_abs(),
+            FeeRouterError::ConstraintViolation
+        );
+        require!(args.max_page_size > 0, FeeRouterError::ConstraintViolation);
+        require_keys_neq!(
+            args.guardian,
+            ctx.accounts.authority.key(),
+            FeeRouterError::ConstraintViolation
+        );
 
         // Defend: treasury ATA must be owned by vault PDA and both ATAs must be for quote mint.
         let policy_key = ctx.accounts.policy.key();
@@ -170,14 +762,35 @@ pub mod keystone_fee_router {
 
         let policy = &mut ctx.accounts.policy;
         policy.authority = ctx.accounts.authority.key();
+        policy.guardian = args.guardian;
+        policy.paused = false;
         policy.cp_pool = ctx.accounts.cp_pool.key();
         policy.quote_mint = ctx.accounts.quote_mint.key();
+        policy.quote_token_program = ctx.accounts.token_program.key();
         policy.creator_quote_ata = ctx.accounts.creator_quote_ata.key();
         policy.treasury_quote_ata = ctx.accounts.treasury_quote_ata.key();
         policy.investor_fee_share_bps = args.investor_fee_share_bps;
         policy.y0_total = args.y0_total;
         policy.daily_cap_quote = args.daily_cap_quote;
         policy.min_payout_lamports = args.min_payout_lamports;
+        policy.crank_reward_bps = args.crank_reward_bps;
+        policy.crank_reward_cap_quote = args.crank_reward_cap_quote;
+        policy.investor_set = args.investor_set;
+        policy.force_close_grace_secs = args.force_close_grace_secs;
+        policy.roll_forced_carry = args.roll_forced_carry;
+        policy.claim_mode = args.claim_mode;
+        policy.lock_source = args.lock_source;
+        policy.epoch_seconds = args.epoch_seconds;
+        policy.epoch_offset_seconds = args.epoch_offset_seconds;
+        policy.max_page_size = args.max_page_size;
+        policy.position_count = 0;
+        policy.remainder_split = RemainderSplit::new(&args.remainder_recipients)?;
+        assert_remainder_accounts(
+            &policy.remainder_split,
+            ctx.remaining_accounts,
+            &policy.quote_mint,
+            &policy.quote_token_program,
+        )?;
         let (_, pb) = Pubkey::find_program_address(
             &[POLICY_SEED, ctx.accounts.cp_pool.key().as_ref()],
             ctx.program_id,
@@ -185,32 +798,31 @@ pub mod keystone_fee_router {
         policy.bump = pb;
         policy.initialized = true;
 
+        policy.pending_update = None;
+
         // Emit config hash for audit.
-        let config_hash = keccak::hashv(&[
-            &policy.cp_pool.to_bytes(),
-            &policy.quote_mint.to_bytes(),
-            &policy.creator_quote_ata.to_bytes(),
-            &policy.treasury_quote_ata.to_bytes(),
-            &policy.investor_fee_share_bps.to_le_bytes(),
-            &policy.y0_total.to_le_bytes(),
-            &policy.daily_cap_quote.to_le_bytes(),
-            &policy.min_payout_lamports.to_le_bytes(),
-        ]);
         emit!(PolicyInitialized {
             policy: policy_key,
-            config_hash: config_hash.0
+            config_hash: policy.config_hash()
         });
         Ok(())
     }
 
     /// Initialize an empty honorary position owned by a PDA.
     pub fn init_honorary_position(ctx: Context<InitHonoraryPosition>) -> Result<()> {
-        // Validate binding and pool constraints.
-        require_keys_eq!(
-            ctx.accounts.policy.cp_pool,
-            ctx.accounts.cp_pool.key(),
+        // Validate binding and pool constraints; position 0 lives in the policy's pool.
+        let index = ctx.accounts.policy.position_count;
+        require!(
+            index < MAX_HONORARY_POSITIONS,
             FeeRouterError::ConstraintViolation
         );
+        if index == 0 {
+            require_keys_eq!(
+                ctx.accounts.policy.cp_pool,
+                ctx.accounts.cp_pool.key(),
+                FeeRouterError::ConstraintViolation
+            );
+        }
         require_keys_eq!(
             ctx.accounts.policy.quote_mint,
             ctx.accounts.quote_mint.key(),
@@ -219,17 +831,16 @@ pub mod keystone_fee_router {
         assert_cp_pool_quote_only(&ctx.accounts.cp_pool, &ctx.accounts.quote_mint)?;
 
         let hp = &mut ctx.accounts.honorary_position;
+        hp.index = index;
         hp.owner_pda = ctx.accounts.owner_pda.key();
         hp.position = ctx.accounts.cp_position.key();
         hp.cp_pool = ctx.accounts.cp_pool.key();
         hp.quote_mint = ctx.accounts.quote_mint.key();
-        let (.., pos_bump) = Pubkey::find_program_address(
-            &[POSITION_SEED, ctx.accounts.policy.key().as_ref()],
-            ctx.program_id,
-        );
-        hp.bump = pos_bump;
+        hp.bump = ctx.bumps.honorary_position;
+        ctx.accounts.policy.position_count = index + 1;
 
         emit!(HonoraryPositionInitialized {
+            index,
             pool: hp.cp_pool,
             position: hp.position,
             owner_pda: hp.owner_pda
@@ -237,65 +848,74 @@ pub mod keystone_fee_router {
         Ok(())
     }
 
-    /// Permissionless daily crank to claim quote fees and distribute to investors.
-    pub fn crank_distribute(ctx: Context<CrankDistribute>, args: CrankArgs) -> Result<()> {
+    /// Permissionless crank, run in two passes per distribution period ("day", see
+    /// `Policy::epoch_index`).
+    ///
+    /// Snapshot pages claim quote fees and sum still-locked amounts into `Progress`;
+    /// the last snapshot page fixes the investor pool. Payout pages then pay each
+    /// investor `investor_pool * locked_i / locked_total`, so payouts do not depend on
+    /// page order. The last payout page routes the remainder to the creator.
+    pub fn crank_distribute<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CrankDistribute<'info>>,
+        args: CrankArgs,
+    ) -> Result<()> {
         let clock = Clock::get()?;
-        let today = clock.unix_timestamp.div_euclid(86_400);
         let policy = &ctx.accounts.policy;
+        let today = policy.epoch_index(clock.unix_timestamp)?;
         let progress = &mut ctx.accounts.progress;
+        progress.bump = ctx.bumps.progress;
+        let history = &mut ctx.accounts.history;
+        history.policy = policy.key();
+        history.bump = ctx.bumps.history;
 
         // Day window & idempotency.
-        if progress.current_day == 0 {
-            progress.current_day = today;
-            progress.last_distribution_ts = clock.unix_timestamp;
-            progress.claimed_quote_today = 0;
-            progress.distributed_quote_today = 0;
-            progress.carry_quote_today = 0;
-            progress.page_cursor = 0;
-            progress.day_closed = false;
-        } else if today > progress.current_day {
-            // Require previous day closed before rollover.
-            require!(progress.day_closed, FeeRouterError::DailyWindowNotReady);
-            progress.current_day = today;
-            progress.last_distribution_ts = clock.unix_timestamp;
-            progress.claimed_quote_today = 0;
-            progress.distributed_quote_today = 0;
-            progress.carry_quote_today = 0;
-            progress.page_cursor = 0;
-            progress.day_closed = false;
-        }
-
-        // Track treasury balance before fee claim.
-        let pre_treasury_balance = ctx.accounts.treasury_quote_ata.amount;
+        progress.enter_day(today, clock.unix_timestamp)?;
+        require!(args.phase == progress.phase, FeeRouterError::WrongPhase);
 
-        meteora_cpi::collect_quote_fees(
-            &ctx.accounts.cp_pool,
-            &ctx.accounts.vault_authority,
-            &ctx.accounts.treasury_quote_ata,
-            &ctx.accounts.token_program,
-            &ctx.accounts.cp_program,
-        )?;
-
-        // Reload treasury account to observe any accrued fees and compute delta.
-        ctx.accounts.treasury_quote_ata.reload()?;
-        let post_treasury_balance = ctx.accounts.treasury_quote_ata.amount;
-        let _collected_quote = post_treasury_balance.saturating_sub(pre_treasury_balance);
-
-        // Compute distributable for this call.
-        let cap_remaining = if policy.daily_cap_quote > 0 {
-            policy
-                .daily_cap_quote
-                .saturating_sub(progress.distributed_quote_today)
+        // The last page of a pass appends the remainder split recipients' quote ATAs
+        // to `remaining_accounts`, in policy order.
+        let split_len = if args.is_last_page {
+            policy.remainder_split.count as usize
         } else {
-            u64::MAX
+            0
         };
-        // Use current treasury balance as pool_remaining to allow tests to pre-mint fees.
-        let pool_remaining = post_treasury_balance;
-        // Track claimed_quote_today as distributed + current treasury to reflect total accrued.
-        progress.claimed_quote_today = progress
-            .distributed_quote_today
-            .saturating_add(pool_remaining);
-        let mut distributable = core::cmp::min(cap_remaining, pool_remaining);
+        let (entry_accounts, recipient_accounts) = ctx.remaining_accounts.split_at(
+            ctx.remaining_accounts
+                .len()
+                .checked_sub(split_len)
+                .ok_or(FeeRouterError::InvalidInvestorPage)?,
+        );
+
+        // Page entries: (investor quote ATA, stream) pairs, up to `max_page_size`.
+        // Extra entries come as `(quote ATA, stream, entry PDA)` triples, the PDA being
+        // the dust ledger in push mode and the checkpoint in pull mode; the first
+        // entry's are `ctx.accounts.dust_ledger` and `ctx.accounts.checkpoint`.
+        require!(
+            entry_accounts.len() % 3 == 0
+                && entry_accounts.len() / 3 < policy.max_page_size as usize,
+            FeeRouterError::InvalidInvestorPage
+        );
+        let mut page: Vec<(AccountInfo<'info>, AccountInfo<'info>)> = vec![(
+            ctx.accounts.investor_quote_ata.to_account_info(),
+            ctx.accounts.stream.to_account_info(),
+        )];
+        let mut extra_entry_pdas: Vec<&'info AccountInfo<'info>> = Vec::new();
+        for chunk in entry_accounts.chunks(3) {
+            page.push((chunk[0].clone(), chunk[1].clone()));
+            extra_entry_pdas.push(&chunk[2]);
+        }
+
+        // Pages walk the committed investor set in index order, without gaps or repeats.
+        require!(
+            args.page_cursor == progress.page_cursor,
+            FeeRouterError::InvalidInvestorPage
+        );
+        let next_cursor =
+            verify_investor_page(&policy.investor_set, args.page_cursor, &page, &args.proofs)?;
+        require!(
+            args.is_last_page == (next_cursor == policy.investor_set.count as u64),
+            FeeRouterError::InvalidInvestorPage
+        );
 
         let policy_key = policy.key();
         let (_, v_bump) =
@@ -303,169 +923,820 @@ pub mod keystone_fee_router {
         let signer_seeds: &[&[u8]] = &[VAULT_SEED, policy_key.as_ref(), &[v_bump]];
         let signer: &[&[&[u8]]] = &[signer_seeds];
 
-        #[cfg(feature = "multi_page_n")]
-        let (paid_total, investors_in_page) = {
-            const N_MAX: usize = 16;
-            let remaining = ctx.remaining_accounts;
-            require!(
-                remaining.len() % 2 == 0,
-                FeeRouterError::InvalidInvestorPage
-            );
-            let total_pairs = 1 + remaining.len() / 2;
-            require!(total_pairs <= N_MAX, FeeRouterError::InvalidInvestorPage);
-
-            let mut paid_total_local: u64 = 0;
-
-            let base_stream = ctx.accounts.stream.to_account_info();
-            let base_locked = <() as StreamLockedReader>::locked_amount(&base_stream)?;
-            if base_locked > 0 && distributable > 0 {
-                let investor_quote = compute_investor_quote(policy, base_locked, distributable)?;
-                if investor_quote >= policy.min_payout_lamports && investor_quote > 0 {
-                    let cpi_ctx = CpiContext::new_with_signer(
-                        ctx.accounts.token_program.to_account_info(),
-                        Transfer {
-                            from: ctx.accounts.treasury_quote_ata.to_account_info(),
-                            to: ctx.accounts.investor_quote_ata.to_account_info(),
-                            authority: ctx.accounts.vault_authority.to_account_info(),
-                        },
-                        signer,
+        match progress.phase {
+            DistributionPhase::Snapshot => {
+                // Claim accrued fees of the page's honorary position (quote only).
+                collect_position(
+                    progress,
+                    &policy_key,
+                    &ctx.accounts.honorary_position,
+                    ctx.bumps.owner_pda,
+                    meteora_cpi::CollectQuoteFeesAccounts {
+                        meteora_program: &ctx.accounts.cp_program.to_account_info(),
+                        pool_authority: &ctx.accounts.cp_pool_authority.to_account_info(),
+                        pool: &ctx.accounts.cp_pool.to_account_info(),
+                        position: &ctx.accounts.cp_position.to_account_info(),
+                        position_nft_account: &ctx.accounts.position_nft_account.to_account_info(),
+                        owner_pda: &ctx.accounts.owner_pda.to_account_info(),
+                        token_a_vault: &ctx.accounts.token_a_vault.to_account_info(),
+                        token_b_vault: &ctx.accounts.token_b_vault.to_account_info(),
+                        token_a_mint: &ctx.accounts.token_a_mint.to_account_info(),
+                        token_b_mint: &ctx.accounts.token_b_mint.to_account_info(),
+                        treasury_quote_ata: &mut ctx.accounts.treasury_quote_ata,
+                        treasury_base_ata: &mut ctx.accounts.treasury_base_ata,
+                        event_authority: &ctx.accounts.event_authority.to_account_info(),
+                        quote_token_program: &ctx.accounts.token_program.to_account_info(),
+                        base_token_program: &ctx.accounts.base_token_program.to_account_info(),
+                    },
+                    &policy.quote_mint,
+                )?;
+                // The day's first page fixes the crank reward every page earns, from
+                // the quote of every position.
+                if args.page_cursor == 0 {
+                    require!(
+                        progress.collected_positions == policy.positions_mask(),
+                        FeeRouterError::PositionNotCollected
                     );
-                    token::transfer(cpi_ctx, investor_quote)?;
-                    distributable = distributable.saturating_sub(investor_quote);
-                    paid_total_local = paid_total_local
-                        .checked_add(investor_quote)
-                        .ok_or(FeeRouterError::ArithmeticOverflow)?;
-                    progress.distributed_quote_today = progress
-                        .distributed_quote_today
-                        .checked_add(investor_quote)
+                    fix_crank_reward(progress, policy, ctx.accounts.treasury_quote_ata.amount)?;
+                }
+
+                // Each entry's PDA records today's locked amount: the dust ledger in
+                // push mode, for the payout pass; the checkpoint in pull mode, after
+                // settling what its previous snapshot accrued.
+                let first_entry_pda = match policy.claim_mode {
+                    ClaimMode::Push => ctx.accounts.dust_ledger.as_ref(),
+                    ClaimMode::Pull => ctx.accounts.checkpoint.as_ref(),
+                }
+                .ok_or(FeeRouterError::InvalidInvestorPage)?
+                .to_account_info();
+                for (offset, (investor_ai, stream_ai)) in page.iter().enumerate() {
+                    let locked = read_locked(policy, investor_ai, stream_ai, progress.snapshot_ts)?;
+                    progress.locked_total_today = progress
+                        .locked_total_today
+                        .checked_add(locked)
                         .ok_or(FeeRouterError::ArithmeticOverflow)?;
+                    let entry_pda = match offset {
+                        0 => &first_entry_pda,
+                        _ => extra_entry_pdas[offset - 1],
+                    };
+                    match policy.claim_mode {
+                        ClaimMode::Push => {
+                            let mut ledger = load_dust_ledger(
+                                ctx.program_id,
+                                &policy_key,
+                                investor_ai,
+                                entry_pda,
+                                &ctx.accounts.payer.to_account_info(),
+                                &ctx.accounts.system_program.to_account_info(),
+                            )?;
+                            ledger.locked_snapshot = locked;
+                            store_entry_pda(entry_pda, &ledger)?;
+                        }
+                        ClaimMode::Pull => {
+                            let mut checkpoint = load_checkpoint(
+                                ctx.program_id,
+                                &policy_key,
+                                investor_ai,
+                                entry_pda,
+                                &ctx.accounts.payer.to_account_info(),
+                                &ctx.accounts.system_program.to_account_info(),
+                            )?;
+                            settle_pull_checkpoint(progress, &mut checkpoint)?;
+                            checkpoint.locked_snapshot = locked;
+                            store_entry_pda(entry_pda, &checkpoint)?;
+                        }
+                    }
+                }
+                progress.page_cursor = next_cursor;
+                progress.last_distribution_ts = clock.unix_timestamp;
+                pay_page_crank_reward(progress, |reward| {
+                    pay_crank_reward(
+                        reward,
+                        ctx.accounts.payer_quote_ata.as_deref(),
+                        &ctx.accounts.treasury_quote_ata.to_account_info(),
+                        &ctx.accounts.quote_mint,
+                        &ctx.accounts.vault_authority.to_account_info(),
+                        &ctx.accounts.token_program.to_account_info(),
+                        signer,
+                    )
+                })?;
+
+                // Snapshot close: fix the investor pool for the day.
+                if args.is_last_page {
+                    close_snapshot(progress, policy, ctx.accounts.treasury_quote_ata.amount)?;
+                    emit!(InvestorSnapshotClosed {
+                        day: progress.current_day,
+                        locked_total: progress.locked_total_today,
+                        investor_pool: progress.investor_pool_today,
+                        crank_reward: progress.crank_reward_today
+                    });
+
+                    // Pull mode: book the pool into the per-unit accumulator for
+                    // `claim_fees` and close the day without a payout pass.
+                    if policy.claim_mode == ClaimMode::Pull {
+                        accrue_pull_pool(progress)?;
+                        close_day(
+                            progress,
+                            history,
+                            policy,
+                            &mut ctx.accounts.treasury_quote_ata,
+                            &ctx.accounts.quote_mint,
+                            &ctx.accounts.creator_quote_ata.to_account_info(),
+                            recipient_accounts,
+                            &ctx.accounts.vault_authority.to_account_info(),
+                            &ctx.accounts.token_program.to_account_info(),
+                            signer,
+                        )?;
+                    }
                 }
             }
+            DistributionPhase::Payout => {
+                let bitmap = ctx
+                    .accounts
+                    .payout_bitmap
+                    .as_mut()
+                    .ok_or(FeeRouterError::InvalidInvestorPage)?;
+                bitmap.roll_to(progress.current_day, policy.investor_set.count);
+                bitmap.bump = ctx
+                    .bumps
+                    .payout_bitmap
+                    .ok_or(FeeRouterError::InvalidInvestorPage)?;
 
-            for chunk in remaining.chunks(2) {
-                let investor_ai = chunk[0].clone();
-                let stream_ai = chunk[1].clone();
-                let locked_total = <() as StreamLockedReader>::locked_amount(&stream_ai)?;
-                if locked_total == 0 || distributable == 0 {
-                    continue;
+                // Dust ledgers of the page entries, the first one's from the accounts.
+                let first_ledger = ctx
+                    .accounts
+                    .dust_ledger
+                    .as_ref()
+                    .ok_or(FeeRouterError::InvalidInvestorPage)?
+                    .to_account_info();
+                let mut ledger_ais = vec![&first_ledger];
+                ledger_ais.extend(extra_entry_pdas.iter().copied());
+                let mut ledgers = Vec::with_capacity(page.len());
+                for (ledger_ai, (investor_ai, _)) in ledger_ais.iter().zip(&page) {
+                    ledgers.push(load_dust_ledger(
+                        ctx.program_id,
+                        &policy.key(),
+                        investor_ai,
+                        ledger_ai,
+                        &ctx.accounts.payer.to_account_info(),
+                        &ctx.accounts.system_program.to_account_info(),
+                    )?);
                 }
-                let investor_quote = compute_investor_quote(policy, locked_total, distributable)?;
-                if investor_quote < policy.min_payout_lamports || investor_quote == 0 {
-                    continue;
+
+                // Each investor is settled at most once per day; repeats are no-ops.
+                // Shares use the locked amounts the snapshot pass recorded, so they
+                // add up to `locked_total_today` whatever the streams did since.
+                let mut entries = Vec::with_capacity(page.len());
+                for (offset, ledger) in ledgers.iter().enumerate() {
+                    let index = (args.page_cursor + offset as u64) as u32;
+                    let settled = !bitmap.mark(index)?;
+                    entries.push(PayoutEntry {
+                        settled,
+                        locked: if settled { 0 } else { ledger.locked_snapshot },
+                        dust: ledger.accrued_quote,
+                        ..Default::default()
+                    });
                 }
-                let ata_account: Account<TokenAccount> = Account::try_from(&investor_ai)?;
-                require_keys_eq!(
-                    ata_account.mint,
-                    policy.quote_mint,
-                    FeeRouterError::ConstraintViolation
-                );
-                drop(ata_account);
-                let cpi_ctx = CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.treasury_quote_ata.to_account_info(),
-                        to: investor_ai.clone(),
-                        authority: ctx.accounts.vault_authority.to_account_info(),
+
+                let token_program = ctx.accounts.token_program.to_account_info();
+                let treasury_quote_ata = ctx.accounts.treasury_quote_ata.to_account_info();
+                let vault_authority = ctx.accounts.vault_authority.to_account_info();
+                let quote_mint = &ctx.accounts.quote_mint;
+                let totals = run_payout_page(
+                    progress,
+                    policy,
+                    &mut entries,
+                    |offset, amount| {
+                        transfer_quote(
+                            &token_program,
+                            &treasury_quote_ata,
+                            quote_mint,
+                            &page[offset].0,
+                            &vault_authority,
+                            signer,
+                            amount,
+                        )
                     },
-                    signer,
-                );
-                token::transfer(cpi_ctx, investor_quote)?;
-                distributable = distributable.saturating_sub(investor_quote);
-                paid_total_local = paid_total_local
-                    .checked_add(investor_quote)
-                    .ok_or(FeeRouterError::ArithmeticOverflow)?;
-                progress.distributed_quote_today = progress
-                    .distributed_quote_today
-                    .checked_add(investor_quote)
-                    .ok_or(FeeRouterError::ArithmeticOverflow)?;
-            }
+                    |reward| {
+                        pay_crank_reward(
+                            reward,
+                            ctx.accounts.payer_quote_ata.as_deref(),
+                            &treasury_quote_ata,
+                            quote_mint,
+                            &vault_authority,
+                            &token_program,
+                            signer,
+                        )
+                    },
+                )?;
+                for ((ledger_ai, ledger), entry) in
+                    ledger_ais.iter().zip(&mut ledgers).zip(&entries)
+                {
+                    ledger.accrued_quote = entry.dust;
+                    store_entry_pda(ledger_ai, ledger)?;
+                }
 
-            (paid_total_local, total_pairs as u32)
-        };
+                // Pagination.
+                progress.page_cursor = next_cursor;
+                progress.last_distribution_ts = clock.unix_timestamp;
+
+                emit!(InvestorPayoutPage {
+                    day: progress.current_day,
+                    page_cursor: args.page_cursor,
+                    investors: page.len() as u32,
+                    paid_total: totals.paid_total,
+                    dust_accrued: totals.dust_accrued,
+                    crank_reward: totals.crank_reward,
+                    carry_after: progress.carry_quote_today
+                });
 
-        #[cfg(not(feature = "multi_page_n"))]
-        let (paid_total, investors_in_page) = {
-            let stream_info = ctx.accounts.stream.to_account_info();
-            let locked_total = <() as StreamLockedReader>::locked_amount(&stream_info)?;
-            let mut paid_total_local: u64 = 0;
-            if locked_total > 0 && distributable > 0 {
-                let investor_quote = compute_investor_quote(policy, locked_total, distributable)?;
-                if investor_quote >= policy.min_payout_lamports && investor_quote > 0 {
-                    let cpi_ctx = CpiContext::new_with_signer(
-                        ctx.accounts.token_program.to_account_info(),
-                        Transfer {
-                            from: ctx.accounts.treasury_quote_ata.to_account_info(),
-                            to: ctx.accounts.investor_quote_ata.to_account_info(),
-                            authority: ctx.accounts.vault_authority.to_account_info(),
-                        },
+                if args.is_last_page {
+                    close_day(
+                        progress,
+                        history,
+                        policy,
+                        &mut ctx.accounts.treasury_quote_ata,
+                        &ctx.accounts.quote_mint,
+                        &ctx.accounts.creator_quote_ata.to_account_info(),
+                        recipient_accounts,
+                        &ctx.accounts.vault_authority.to_account_info(),
+                        &ctx.accounts.token_program.to_account_info(),
                         signer,
-                    );
-                    token::transfer(cpi_ctx, investor_quote)?;
-                    distributable = distributable.saturating_sub(investor_quote);
-                    paid_total_local = investor_quote;
-                    progress.distributed_quote_today = progress
-                        .distributed_quote_today
-                        .checked_add(investor_quote)
-                        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+                    )?;
                 }
             }
-            (paid_total_local, 1u32)
-        };
+        }
+        Ok(())
+    }
 
-        // Update carry and pagination.
-        progress.carry_quote_today = distributable;
-        progress.page_cursor = args.page_cursor;
-        progress.last_distribution_ts = clock.unix_timestamp;
+    /// Permissionless: claims one honorary position's fees into today's snapshot pass,
+    /// opening the day like a crank page. Every position must be collected before the
+    /// first snapshot page fixes the crank reward; bundle these ahead of it.
+    pub fn collect_position_fees(
+        ctx: Context<CollectPositionFees>,
+        args: CollectPositionFeesArgs,
+    ) -> Result<()> {
+        let policy = &ctx.accounts.policy;
+        let now = Clock::get()?.unix_timestamp;
+        let today = policy.epoch_index(now)?;
+        let progress = &mut ctx.accounts.progress;
+        progress.bump = ctx.bumps.progress;
+        progress.enter_day(today, now)?;
+        require!(
+            progress.phase == DistributionPhase::Snapshot,
+            FeeRouterError::WrongPhase
+        );
+        require!(
+            args.position_index < policy.position_count,
+            FeeRouterError::ConstraintViolation
+        );
 
-        emit!(InvestorPayoutPage {
-            day: progress.current_day,
-            page_cursor: args.page_cursor,
-            investors: investors_in_page,
-            paid_total,
-            carry_after: progress.carry_quote_today
+        collect_position(
+            progress,
+            &policy.key(),
+            &ctx.accounts.honorary_position,
+            ctx.bumps.owner_pda,
+            meteora_cpi::CollectQuoteFeesAccounts {
+                meteora_program: &ctx.accounts.cp_program.to_account_info(),
+                pool_authority: &ctx.accounts.cp_pool_authority.to_account_info(),
+                pool: &ctx.accounts.cp_pool.to_account_info(),
+                position: &ctx.accounts.cp_position.to_account_info(),
+                position_nft_account: &ctx.accounts.position_nft_account.to_account_info(),
+                owner_pda: &ctx.accounts.owner_pda.to_account_info(),
+                token_a_vault: &ctx.accounts.token_a_vault.to_account_info(),
+                token_b_vault: &ctx.accounts.token_b_vault.to_account_info(),
+                token_a_mint: &ctx.accounts.token_a_mint.to_account_info(),
+                token_b_mint: &ctx.accounts.token_b_mint.to_account_info(),
+                treasury_quote_ata: &mut ctx.accounts.treasury_quote_ata,
+                treasury_base_ata: &mut ctx.accounts.treasury_base_ata,
+                event_authority: &ctx.accounts.event_authority.to_account_info(),
+                quote_token_program: &ctx.accounts.token_program.to_account_info(),
+                base_token_program: &ctx.accounts.base_token_program.to_account_info(),
+            },
+            &policy.quote_mint,
+        )?;
+        progress.last_distribution_ts = now;
+        Ok(())
+    }
+
+    /// Propose new policy parameters; applicable after `POLICY_UPDATE_MIN_DELAY_SECS`.
+    /// A new proposal replaces any pending one and restarts the delay. The remainder
+    /// recipients' quote ATAs follow in `remaining_accounts`, in split order.
+    pub fn propose_policy_update(
+        ctx: Context<ProposePolicyUpdate>,
+        args: PolicyUpdateArgs,
+    ) -> Result<()> {
+        require!(
+            args.investor_fee_share_bps <= 10_000,
+            FeeRouterError::ConstraintViolation
+        );
+        require!(
+            args.crank_reward_bps <= 10_000,
+            FeeRouterError::ConstraintViolation
+        );
+        require!(args.y0_total > 0, FeeRouterError::ConstraintViolation);
+        require!(
+            args.force_close_grace_secs >= 0,
+            FeeRouterError::ConstraintViolation
+        );
+        let policy = &mut ctx.accounts.policy;
+        require_keys_eq!(
+            ctx.accounts.creator_quote_ata.mint,
+            policy.quote_mint,
+            FeeRouterError::ConstraintViolation
+        );
+
+        let remainder_split = RemainderSplit::new(&args.remainder_recipients)?;
+        assert_remainder_accounts(
+            &remainder_split,
+            ctx.remaining_accounts,
+            &policy.quote_mint,
+            &policy.quote_token_program,
+        )?;
+
+        let eligible_ts = Clock::get()?
+            .unix_timestamp
+            .checked_add(POLICY_UPDATE_MIN_DELAY_SECS)
+            .ok_or(FeeRouterError::ArithmeticOverflow)?;
+        policy.pending_update = Some(PolicyUpdate {
+            investor_fee_share_bps: args.investor_fee_share_bps,
+            y0_total: args.y0_total,
+            daily_cap_quote: args.daily_cap_quote,
+            min_payout_lamports: args.min_payout_lamports,
+            crank_reward_bps: args.crank_reward_bps,
+            crank_reward_cap_quote: args.crank_reward_cap_quote,
+            creator_quote_ata: ctx.accounts.creator_quote_ata.key(),
+            remainder_split,
+            force_close_grace_secs: args.force_close_grace_secs,
+            roll_forced_carry: args.roll_forced_carry,
+            eligible_ts,
         });
+        emit!(PolicyUpdateProposed {
+            policy: policy.key(),
+            eligible_ts
+        });
+        Ok(())
+    }
 
-        // Day close: route remainder to creator and mark closed.
-        if args.is_last_page {
-            let remainder = progress.carry_quote_today;
-            if remainder > 0 {
-                let cpi_ctx = CpiContext::new_with_signer(
-                    ctx.accounts.token_program.to_account_info(),
-                    Transfer {
-                        from: ctx.accounts.treasury_quote_ata.to_account_info(),
-                        to: ctx.accounts.creator_quote_ata.to_account_info(),
-                        authority: ctx.accounts.vault_authority.to_account_info(),
-                    },
-                    signer,
-                );
-                token::transfer(cpi_ctx, remainder)?;
-            }
-            progress.carry_quote_today = 0;
-            progress.day_closed = true;
-            emit!(CreatorPayoutDayClosed {
-                day: progress.current_day,
-                remainder
-            });
+    /// Apply the pending policy update once its delay elapsed, between distribution
+    /// days (no day open in `Progress`). An update that only reroutes the remainder
+    /// also applies mid-day, so a day stuck on a bad recipient can still close.
+    pub fn apply_policy_update(ctx: Context<ApplyPolicyUpdate>) -> Result<()> {
+        let policy = &mut ctx.accounts.policy;
+        let update = policy
+            .pending_update
+            .ok_or(FeeRouterError::PolicyUpdateNotReady)?;
+        require!(
+            Clock::get()?.unix_timestamp >= update.eligible_ts,
+            FeeRouterError::PolicyUpdateNotReady
+        );
+        // Mid-day, the progress account must have its day closed unless the update
+        // only reroutes the remainder (see `PolicyUpdate::reroutes_only`).
+        let progress_info = ctx.accounts.progress.to_account_info();
+        if progress_info.owner == ctx.program_id && !progress_info.data_is_empty() {
+            let progress = Progress::try_deserialize(&mut &progress_info.data.borrow()[..])?;
+            require!(
+                progress.day_closed || update.reroutes_only(policy),
+                FeeRouterError::DailyWindowNotReady
+            );
         }
+
+        policy.investor_fee_share_bps = update.investor_fee_share_bps;
+        policy.y0_total = update.y0_total;
+        policy.daily_cap_quote = update.daily_cap_quote;
+        policy.min_payout_lamports = update.min_payout_lamports;
+        policy.crank_reward_bps = update.crank_reward_bps;
+        policy.crank_reward_cap_quote = update.crank_reward_cap_quote;
+        policy.creator_quote_ata = update.creator_quote_ata;
+        policy.remainder_split = update.remainder_split;
+        policy.force_close_grace_secs = update.force_close_grace_secs;
+        policy.roll_forced_carry = update.roll_forced_carry;
+        policy.pending_update = None;
+
+        emit!(PolicyUpdated {
+            policy: policy.key(),
+            config_hash: policy.config_hash()
+        });
         Ok(())
     }
-}
 
-/// Init policy arguments.
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
-pub struct InitPolicyArgs {
-    /// Total investor allocation minted at TGE (Y0).
-    pub y0_total: u64,
-    /// Max investor share in bps (<= 10_000).
-    pub investor_fee_share_bps: u16,
-    /// Optional per-day cap in quote lamports (0 disables cap).
-    pub daily_cap_quote: u64,
-    /// Dust threshold.
-    pub min_payout_lamports: u64,
-}
+    /// Guardian or authority: pause cranks, claims and policy changes. Collected fees
+    /// stay in the treasury until the guardian unpauses.
+    pub fn pause_policy(ctx: Context<SetPaused>) -> Result<()> {
+        let policy = &mut ctx.accounts.policy;
+        let signer = ctx.accounts.signer.key();
+        require!(
+            matches!(policy.role_of(&signer), Some(Role::Guardian | Role::Admin)),
+            FeeRouterError::Unauthorized
+        );
+        require!(!policy.paused, FeeRouterError::ConstraintViolation);
+        policy.paused = true;
+        emit!(PolicyPaused {
+            policy: policy.key(),
+            signer
+        });
+        Ok(())
+    }
 
-/// Accounts for init_policy.
-#[allow(missing_docs)]
+    /// Guardian only: resume a paused policy. The authority cannot lift a pause, so
+    /// a compromised authority key cannot override the guardian.
+    pub fn unpause_policy(ctx: Context<SetPaused>) -> Result<()> {
+        let policy = &mut ctx.accounts.policy;
+        let signer = ctx.accounts.signer.key();
+        require!(
+            policy.role_of(&signer) == Some(Role::Guardian),
+            FeeRouterError::Unauthorized
+        );
+        require!(policy.paused, FeeRouterError::ConstraintViolation);
+        policy.paused = false;
+        emit!(PolicyUnpaused {
+            policy: policy.key(),
+            signer
+        });
+        Ok(())
+    }
+
+    /// Permissionless recovery for a day whose last page never arrived.
+    ///
+    /// Callable once `force_close_grace_secs` passed after the open day ended. Quote
+    /// claimed but not paid to investors goes to the creator, or rolls into the next
+    /// day when `Policy::roll_forced_carry` is set.
+    pub fn force_close_day<'info>(
+        ctx: Context<'_, '_, '_, 'info, ForceCloseDay<'info>>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let policy = &ctx.accounts.policy;
+        let progress = &mut ctx.accounts.progress;
+        require!(
+            progress.day_opened && !progress.day_closed,
+            FeeRouterError::DailyWindowNotReady
+        );
+        let next_day = progress
+            .current_day
+            .checked_add(1)
+            .ok_or(FeeRouterError::ArithmeticOverflow)?;
+        let closable_ts = policy
+            .epoch_start(next_day)?
+            .checked_add(policy.force_close_grace_secs)
+            .ok_or(FeeRouterError::ArithmeticOverflow)?;
+        require!(
+            clock.unix_timestamp >= closable_ts,
+            FeeRouterError::DailyWindowNotReady
+        );
+
+        let carry = progress
+            .undistributed_quote()
+            .min(ctx.accounts.treasury_quote_ata.amount);
+        if policy.roll_forced_carry {
+            progress.rolled_over_quote = carry;
+        } else {
+            let policy_key = policy.key();
+            let signer_seeds: &[&[u8]] = &[
+                VAULT_SEED,
+                policy_key.as_ref(),
+                &[ctx.bumps.vault_authority],
+            ];
+            let signer: &[&[&[u8]]] = &[signer_seeds];
+            pay_remainder(
+                progress.current_day,
+                policy,
+                carry,
+                &ctx.accounts.treasury_quote_ata.to_account_info(),
+                &ctx.accounts.quote_mint,
+                &ctx.accounts.creator_quote_ata.to_account_info(),
+                ctx.remaining_accounts,
+                &ctx.accounts.vault_authority.to_account_info(),
+                &ctx.accounts.token_program.to_account_info(),
+                signer,
+            )?;
+        }
+        progress.carry_quote_today = 0;
+        progress.day_closed = true;
+        progress.force_closed = true;
+        progress.last_distribution_ts = clock.unix_timestamp;
+        let creator_remainder = if policy.roll_forced_carry { 0 } else { carry };
+        record_day(
+            &mut ctx.accounts.history,
+            progress,
+            policy,
+            creator_remainder,
+        )?;
+
+        emit!(DayForceClosed {
+            day: progress.current_day,
+            carry,
+            rolled_over: policy.roll_forced_carry
+        });
+        Ok(())
+    }
+
+    /// Pull mode: pays an investor their settled entitlement, each accrued day's pool
+    /// share at the locked amount that day's snapshot page recorded on the checkpoint.
+    pub fn claim_fees(ctx: Context<ClaimFees>, args: ClaimFeesArgs) -> Result<()> {
+        let policy = &ctx.accounts.policy;
+        require!(
+            policy.claim_mode == ClaimMode::Pull,
+            FeeRouterError::ClaimModeMismatch
+        );
+        require!(
+            args.index < policy.investor_set.count,
+            FeeRouterError::InvalidInvestorPage
+        );
+        let leaf = investor_leaf(
+            args.index,
+            &ctx.accounts.investor_quote_ata.key(),
+            &ctx.accounts.stream.key(),
+        );
+        require!(
+            merkle::verify_merkle_proof(leaf, &args.proof, policy.investor_set.root),
+            FeeRouterError::InvalidInvestorProof
+        );
+
+        let progress = &mut ctx.accounts.progress;
+        let checkpoint = &mut ctx.accounts.checkpoint;
+        checkpoint.policy = policy.key();
+        checkpoint.investor_quote_ata = ctx.accounts.investor_quote_ata.key();
+        checkpoint.bump = ctx.bumps.checkpoint;
+        settle_pull_checkpoint(progress, checkpoint)?;
+        let amount = core::mem::take(&mut checkpoint.owed_quote);
+
+        if amount > 0 {
+            let policy_key = policy.key();
+            let signer_seeds: &[&[u8]] = &[
+                VAULT_SEED,
+                policy_key.as_ref(),
+                &[ctx.bumps.vault_authority],
+            ];
+            let signer: &[&[&[u8]]] = &[signer_seeds];
+            let received = transfer_quote(
+                &ctx.accounts.token_program.to_account_info(),
+                &ctx.accounts.treasury_quote_ata.to_account_info(),
+                &ctx.accounts.quote_mint,
+                &ctx.accounts.investor_quote_ata.to_account_info(),
+                &ctx.accounts.vault_authority.to_account_info(),
+                signer,
+                amount,
+            )?;
+            progress.pull_owed_quote = progress
+                .pull_owed_quote
+                .checked_sub(amount)
+                .ok_or(FeeRouterError::ArithmeticOverflow)?;
+            checkpoint.claimed_total = checkpoint
+                .claimed_total
+                .checked_add(received)
+                .ok_or(FeeRouterError::ArithmeticOverflow)?;
+        }
+
+        emit!(InvestorFeesClaimed {
+            investor_quote_ata: checkpoint.investor_quote_ata,
+            amount,
+            fee_per_locked_unit_acc: checkpoint.fee_per_locked_unit_acc
+        });
+        Ok(())
+    }
+
+    /// Authority-only: unregisters the most recently added honorary position (never
+    /// position 0) between days, optionally closing its cp-amm position.
+    pub fn close_honorary_position(
+        ctx: Context<CloseHonoraryPosition>,
+        args: CloseHonoraryPositionArgs,
+    ) -> Result<()> {
+        let progress_info = ctx.accounts.progress.to_account_info();
+        if progress_info.owner == ctx.program_id && !progress_info.data_is_empty() {
+            let progress = Progress::try_deserialize(&mut &progress_info.data.borrow()[..])?;
+            require!(progress.day_closed, FeeRouterError::DailyWindowNotReady);
+        }
+
+        let policy_key = ctx.accounts.policy.key();
+        let index = ctx.accounts.honorary_position.index;
+        if args.close_position {
+            let owner_seeds: &[&[u8]] = &[
+                VAULT_SEED,
+                policy_key.as_ref(),
+                FEE_POS_OWNER_SEED,
+                position_index_seed(&index),
+                &[ctx.bumps.owner_pda],
+            ];
+            meteora_cpi::close_position(
+                meteora_cpi::ClosePositionAccounts {
+                    meteora_program: &ctx.accounts.cp_program.to_account_info(),
+                    position_nft_mint: &ctx.accounts.position_nft_mint.to_account_info(),
+                    position_nft_account: &ctx.accounts.position_nft_account.to_account_info(),
+                    pool: &ctx.accounts.cp_pool.to_account_info(),
+                    position: &ctx.accounts.cp_position.to_account_info(),
+                    pool_authority: &ctx.accounts.cp_pool_authority.to_account_info(),
+                    rent_receiver: &ctx.accounts.authority.to_account_info(),
+                    owner_pda: &ctx.accounts.owner_pda.to_account_info(),
+                    nft_token_program: &ctx.accounts.nft_token_program.to_account_info(),
+                    event_authority: &ctx.accounts.event_authority.to_account_info(),
+                },
+                owner_seeds,
+            )?;
+        }
+        ctx.accounts.policy.position_count = index;
+
+        emit!(HonoraryPositionClosed {
+            index,
+            pool: ctx.accounts.honorary_position.cp_pool,
+            position: ctx.accounts.honorary_position.position,
+            position_closed: args.close_position
+        });
+        Ok(())
+    }
+
+    /// Authority-only, between days: pays a dust ledger's accrued quote out below the
+    /// dust threshold and closes the ledger, so a policy can wind down with dust held
+    /// for investors that will never reach the threshold (e.g. unlocked to zero). Dust
+    /// of a closed investor ATA goes to the creator.
+    pub fn release_dust(ctx: Context<ReleaseDust>) -> Result<()> {
+        let progress = &mut ctx.accounts.progress;
+        require!(progress.day_closed, FeeRouterError::DailyWindowNotReady);
+
+        let amount = ctx.accounts.dust_ledger.accrued_quote;
+        let investor_info = ctx.accounts.investor_quote_ata.to_account_info();
+        let recipient = if investor_info.data_is_empty() {
+            ctx.accounts.creator_quote_ata.to_account_info()
+        } else {
+            investor_info
+        };
+        if amount > 0 {
+            let policy_key = ctx.accounts.policy.key();
+            let signer_seeds: &[&[u8]] = &[
+                VAULT_SEED,
+                policy_key.as_ref(),
+                &[ctx.bumps.vault_authority],
+            ];
+            transfer_quote(
+                &ctx.accounts.token_program.to_account_info(),
+                &ctx.accounts.treasury_quote_ata.to_account_info(),
+                &ctx.accounts.quote_mint,
+                &recipient,
+                &ctx.accounts.vault_authority.to_account_info(),
+                &[signer_seeds],
+                amount,
+            )?;
+            progress.dust_reserve_quote = progress
+                .dust_reserve_quote
+                .checked_sub(amount)
+                .ok_or(FeeRouterError::ArithmeticOverflow)?;
+        }
+
+        emit!(DustReleased {
+            investor_quote_ata: ctx.accounts.dust_ledger.investor_quote_ata,
+            recipient: recipient.key(),
+            amount
+        });
+        Ok(())
+    }
+
+    /// Authority-only wind-down once the current day is closed: sweeps the treasury to
+    /// the creator, optionally closes the cp-amm position, and closes the treasury ATA
+    /// and the policy's program accounts, returning rent to the authority.
+    pub fn close_policy(ctx: Context<ClosePolicy>, args: ClosePolicyArgs) -> Result<()> {
+        let authority_info = ctx.accounts.authority.to_account_info();
+        let progress_info = ctx.accounts.progress.to_account_info();
+        if progress_info.owner == ctx.program_id && !progress_info.data_is_empty() {
+            let progress = Progress::try_deserialize(&mut &progress_info.data.borrow()[..])?;
+            require!(progress.day_closed, FeeRouterError::DailyWindowNotReady);
+            // Quote reserved for investors (pull claims, dust ledgers) is theirs, not
+            // the creator's; it must be paid out first.
+            require!(
+                progress.pull_reserve_quote() == 0 && progress.dust_reserve_quote == 0,
+                FeeRouterError::ConstraintViolation
+            );
+        }
+        // Extra positions are unregistered first with `close_honorary_position`.
+        require!(
+            ctx.accounts.policy.position_count <= 1,
+            FeeRouterError::ConstraintViolation
+        );
+
+        let policy_key = ctx.accounts.policy.key();
+        let vault_seeds: &[&[u8]] = &[
+            VAULT_SEED,
+            policy_key.as_ref(),
+            &[ctx.bumps.vault_authority],
+        ];
+        let vault_signer: &[&[&[u8]]] = &[vault_seeds];
+        let swept_quote = ctx.accounts.treasury_quote_ata.amount;
+        if swept_quote > 0 {
+            transfer_quote(
+                &ctx.accounts.token_program.to_account_info(),
+                &ctx.accounts.treasury_quote_ata.to_account_info(),
+                &ctx.accounts.quote_mint,
+                &ctx.accounts.creator_quote_ata.to_account_info(),
+                &ctx.accounts.vault_authority.to_account_info(),
+                vault_signer,
+                swept_quote,
+            )?;
+        }
+        // Token-2022 refuses to close an account holding withheld transfer fees
+        // (withheld on fees claimed into the treasury); move them to the mint first.
+        if transfer_fee_config(&ctx.accounts.quote_mint.to_account_info())?.is_some() {
+            token_interface::harvest_withheld_tokens_to_mint(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    HarvestWithheldTokensToMint {
+                        token_program_id: ctx.accounts.token_program.to_account_info(),
+                        mint: ctx.accounts.quote_mint.to_account_info(),
+                    },
+                ),
+                vec![ctx.accounts.treasury_quote_ata.to_account_info()],
+            )?;
+        }
+        token_interface::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            token_interface::CloseAccount {
+                account: ctx.accounts.treasury_quote_ata.to_account_info(),
+                destination: authority_info.clone(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            vault_signer,
+        ))?;
+
+        if args.close_position {
+            let owner_seeds: &[&[u8]] = &[
+                VAULT_SEED,
+                policy_key.as_ref(),
+                FEE_POS_OWNER_SEED,
+                position_index_seed(&0),
+                &[ctx.bumps.owner_pda],
+            ];
+            meteora_cpi::close_position(
+                meteora_cpi::ClosePositionAccounts {
+                    meteora_program: &ctx.accounts.cp_program.to_account_info(),
+                    position_nft_mint: &ctx.accounts.position_nft_mint.to_account_info(),
+                    position_nft_account: &ctx.accounts.position_nft_account.to_account_info(),
+                    pool: &ctx.accounts.cp_pool.to_account_info(),
+                    position: &ctx.accounts.cp_position.to_account_info(),
+                    pool_authority: &ctx.accounts.cp_pool_authority.to_account_info(),
+                    rent_receiver: &authority_info,
+                    owner_pda: &ctx.accounts.owner_pda.to_account_info(),
+                    nft_token_program: &ctx.accounts.nft_token_program.to_account_info(),
+                    event_authority: &ctx.accounts.event_authority.to_account_info(),
+                },
+                owner_seeds,
+            )?;
+        }
+
+        for info in [
+            progress_info,
+            ctx.accounts.payout_bitmap.to_account_info(),
+            ctx.accounts.history.to_account_info(),
+        ] {
+            if info.owner == ctx.program_id && !info.data_is_empty() {
+                close_program_account(&info, &authority_info)?;
+            }
+        }
+
+        emit!(PolicyClosed {
+            policy: policy_key,
+            swept_quote,
+            position_closed: args.close_position
+        });
+        Ok(())
+    }
+}
+
+/// Init policy arguments.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct InitPolicyArgs {
+    /// Guardian that may pause and unpause the policy (must differ from the authority).
+    pub guardian: Pubkey,
+    /// Total investor allocation minted at TGE (Y0).
+    pub y0_total: u64,
+    /// Max investor share in bps (<= 10_000).
+    pub investor_fee_share_bps: u16,
+    /// Optional per-day cap in quote lamports (0 disables cap).
+    pub daily_cap_quote: u64,
+    /// Dust threshold.
+    pub min_payout_lamports: u64,
+    /// Crank payer's cut of claimed quote in bps (<= 10_000).
+    pub crank_reward_bps: u16,
+    /// Per-day crank reward cap in quote lamports (0 disables cap).
+    pub crank_reward_cap_quote: u64,
+    /// Merkle commitment to the investor set (`count > 0`).
+    pub investor_set: InvestorSet,
+    /// Grace after a day ends before it can be force-closed (>= 0).
+    pub force_close_grace_secs: i64,
+    /// Roll forced-close carry into the next day instead of paying the creator.
+    pub roll_forced_carry: bool,
+    /// Push payout passes or pull claims.
+    pub claim_mode: ClaimMode,
+    /// Program the investor `stream` accounts belong to.
+    pub lock_source: LockSource,
+    /// Distribution period length in seconds (> 0).
+    pub epoch_seconds: i64,
+    /// Shift of period boundaries in seconds (`|offset| < epoch_seconds`, may be negative).
+    pub epoch_offset_seconds: i64,
+    /// Most investor entries per crank page (> 0).
+    pub max_page_size: u8,
+    /// Day-end remainder recipients (empty: all to the creator ATA).
+    pub remainder_recipients: Vec<RemainderRecipient>,
+}
+
+/// Accounts for init_policy.
+#[allow(missing_docs)]
 #[derive(Accounts)]
 pub struct InitPolicy<'info> {
     #[account(mut)]
@@ -480,11 +1751,12 @@ pub struct InitPolicy<'info> {
     pub policy: Account<'info, Policy>,
     /// CHECK: cp-amm pool account; validated by helper.
     pub cp_pool: UncheckedAccount<'info>,
-    pub quote_mint: Account<'info, Mint>,
+    #[account(mint::token_program = token_program)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
     #[account(mut)]
-    pub creator_quote_ata: Account<'info, TokenAccount>,
+    pub creator_quote_ata: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
-    pub treasury_quote_ata: Account<'info, TokenAccount>,
+    pub treasury_quote_ata: InterfaceAccount<'info, TokenAccount>,
     /// Vault authority PDA must own treasury_quote_ata.
     /// CHECK: derived PDA authority.
     #[account(
@@ -492,35 +1764,60 @@ pub struct InitPolicy<'info> {
         bump
     )]
     pub vault_authority: UncheckedAccount<'info>,
-    pub token_program: Program<'info, Token>,
+    /// Token program of the quote mint (SPL Token or Token-2022).
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
 }
 
+/// Policy update arguments; all parameters are replaced together.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct PolicyUpdateArgs {
+    /// Total investor allocation (Y0).
+    pub y0_total: u64,
+    /// Max investor share in bps (<= 10_000).
+    pub investor_fee_share_bps: u16,
+    /// Per-day cap in quote lamports (0 disables cap).
+    pub daily_cap_quote: u64,
+    /// Dust threshold.
+    pub min_payout_lamports: u64,
+    /// Crank payer's cut of claimed quote in bps (<= 10_000).
+    pub crank_reward_bps: u16,
+    /// Per-day crank reward cap in quote lamports (0 disables cap).
+    pub crank_reward_cap_quote: u64,
+    /// Grace after a day ends before it can be force-closed (>= 0).
+    pub force_close_grace_secs: i64,
+    /// Roll forced-close carry into the next day instead of paying the creator.
+    pub roll_forced_carry: bool,
+    /// Day-end remainder recipients (empty: all to the creator ATA).
+    pub remainder_recipients: Vec<RemainderRecipient>,
+}
+
 /// Accounts for initializing honorary position.
 #[allow(missing_docs)]
 #[derive(Accounts)]
 pub struct InitHonoraryPosition<'info> {
     #[account(mut)]
     pub authority: Signer<'info>,
-    #[account(mut, has_one = authority)]
+    #[account(mut, has_one = authority, constraint = !policy.paused @ FeeRouterError::Paused)]
     pub policy: Account<'info, Policy>,
     /// CHECK: cp-amm pool account.
     pub cp_pool: UncheckedAccount<'info>,
-    pub quote_mint: Account<'info, Mint>,
-    /// CHECK: PDA as owner of the cp-amm position.
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+    /// CHECK: PDA as owner of the cp-amm position (one per position index).
     #[account(
-        seeds = [VAULT_SEED, policy.key().as_ref(), FEE_POS_OWNER_SEED],
+        seeds = [VAULT_SEED, policy.key().as_ref(), FEE_POS_OWNER_SEED, position_index_seed(&policy.position_count)],
         bump
     )]
     pub owner_pda: UncheckedAccount<'info>,
     /// CHECK: Meteora cp-amm position account.
     pub cp_position: UncheckedAccount<'info>,
+    /// Registry of the next position index, `policy.position_count`.
     #[account(
         init,
         payer = authority,
         space = HonoraryPosition::SPACE,
-        seeds = [POSITION_SEED, policy.key().as_ref()],
+        seeds = [POSITION_SEED, policy.key().as_ref(), position_index_seed(&policy.position_count)],
         bump
     )]
     pub honorary_position: Account<'info, HonoraryPosition>,
@@ -528,60 +1825,559 @@ pub struct InitHonoraryPosition<'info> {
     pub rent: Sysvar<'info, Rent>,
 }
 
-/// Accounts for distribution crank.
+/// Accounts for distribution crank.
+#[allow(missing_docs)]
+#[derive(Accounts)]
+#[instruction(args: CrankArgs)]
+pub struct CrankDistribute<'info> {
+    /// CHECK: Meteora cp-amm program id for CPI claim (must own `cp_pool`).
+    pub cp_program: UncheckedAccount<'info>,
+    /// CHECK: Pool of the page's honorary position (owner checked in CPI helper).
+    #[account(constraint = cp_pool.key() == honorary_position.cp_pool @ FeeRouterError::ConstraintViolation)]
+    pub cp_pool: UncheckedAccount<'info>,
+    /// CHECK: cp-amm pool authority PDA; validated by cp-amm during the claim.
+    pub cp_pool_authority: UncheckedAccount<'info>,
+    #[account(mut, constraint = !policy.paused @ FeeRouterError::Paused)]
+    pub policy: Account<'info, Policy>,
+    /// Honorary position collected by snapshot pages (`CrankArgs::position_index`).
+    #[account(
+        seeds = [POSITION_SEED, policy.key().as_ref(), position_index_seed(&args.position_index)],
+        bump = honorary_position.bump,
+        has_one = owner_pda,
+    )]
+    pub honorary_position: Account<'info, HonoraryPosition>,
+    /// CHECK: PDA owner of the honorary position; signs the fee claim.
+    #[account(
+        seeds = [VAULT_SEED, policy.key().as_ref(), FEE_POS_OWNER_SEED, position_index_seed(&args.position_index)],
+        bump
+    )]
+    pub owner_pda: UncheckedAccount<'info>,
+    /// CHECK: Meteora cp-amm position registered in `honorary_position`.
+    #[account(mut, address = honorary_position.position @ FeeRouterError::ConstraintViolation)]
+    pub cp_position: UncheckedAccount<'info>,
+    /// CHECK: Position NFT token account; validated by cp-amm during the claim.
+    pub position_nft_account: UncheckedAccount<'info>,
+    /// CHECK: Pool token A vault; validated by cp-amm during the claim.
+    #[account(mut)]
+    pub token_a_vault: UncheckedAccount<'info>,
+    /// CHECK: Pool token B vault; validated by cp-amm during the claim.
+    #[account(mut)]
+    pub token_b_vault: UncheckedAccount<'info>,
+    /// CHECK: Pool token A mint; validated by cp-amm during the claim.
+    pub token_a_mint: UncheckedAccount<'info>,
+    /// CHECK: Pool token B mint; validated by cp-amm during the claim.
+    pub token_b_mint: UncheckedAccount<'info>,
+    /// CHECK: cp-amm event authority PDA (checked in CPI helper).
+    pub event_authority: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = Progress::SPACE,
+        seeds = [PROGRESS_SEED, policy.cp_pool.as_ref()],
+        bump
+    )]
+    pub progress: Account<'info, Progress>,
+    /// Per-day record of investors already settled in the payout pass; required by
+    /// payout pages only.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = PayoutBitmap::space(policy.investor_set.count),
+        seeds = [PAYOUT_BITMAP_SEED, policy.key().as_ref()],
+        bump
+    )]
+    pub payout_bitmap: Option<Box<Account<'info, PayoutBitmap>>>,
+    /// Rolling record of the policy's last closed days.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = DistributionHistory::SPACE,
+        seeds = [HISTORY_SEED, policy.key().as_ref()],
+        bump
+    )]
+    pub history: Box<Account<'info, DistributionHistory>>,
+    /// Signer paying rent for progress, bitmap and history accounts if needed.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Payer quote account for the crank reward; without it the reward stays with
+    /// the remainder.
+    #[account(
+        mut,
+        constraint = payer_quote_ata.mint == policy.quote_mint @ FeeRouterError::ConstraintViolation,
+        constraint = payer_quote_ata.owner == payer.key() @ FeeRouterError::Unauthorized
+    )]
+    pub payer_quote_ata: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    /// CHECK: vault authority derived from policy.
+    #[account(
+        seeds = [VAULT_SEED, policy.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+    /// Treasury quote ATA to transfer from.
+    #[account(mut, constraint = treasury_quote_ata.key() == policy.treasury_quote_ata)]
+    pub treasury_quote_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Treasury base token account owned by the vault PDA; must not receive fees.
+    #[account(mut, constraint = treasury_base_ata.owner == vault_authority.key() @ FeeRouterError::Unauthorized)]
+    pub treasury_base_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Creator ATA to receive remainder on day close.
+    #[account(mut, constraint = creator_quote_ata.key() == policy.creator_quote_ata)]
+    pub creator_quote_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Investor quote ATA for this page entry (must match policy.quote_mint).
+    #[account(mut, constraint = investor_quote_ata.mint == policy.quote_mint)]
+    pub investor_quote_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// CHECK: Investor lock account (`policy.lock_source`) to read the locked amount.
+    pub stream: UncheckedAccount<'info>,
+    /// CHECK: Dust ledger of the page's first investor, required by push-mode pages;
+    /// PDA checked and created in `load_dust_ledger`.
+    #[account(mut)]
+    pub dust_ledger: Option<UncheckedAccount<'info>>,
+    /// CHECK: Pull-mode checkpoint of the page's first investor, required by
+    /// snapshot pages in pull mode; PDA checked and created in `load_checkpoint`.
+    #[account(mut)]
+    pub checkpoint: Option<UncheckedAccount<'info>>,
+    #[account(address = policy.quote_mint @ FeeRouterError::ConstraintViolation)]
+    pub quote_mint: Box<InterfaceAccount<'info, Mint>>,
+    /// Token program of the quote mint.
+    #[account(address = policy.quote_token_program @ FeeRouterError::ConstraintViolation)]
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the page position's base mint.
+    pub base_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for propose_policy_update.
+#[allow(missing_docs)]
+#[derive(Accounts)]
+pub struct ProposePolicyUpdate<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut, has_one = authority, constraint = !policy.paused @ FeeRouterError::Paused)]
+    pub policy: Account<'info, Policy>,
+    /// Creator quote ATA to use once the update applies.
+    pub creator_quote_ata: InterfaceAccount<'info, TokenAccount>,
+}
+
+/// Accounts for apply_policy_update.
+#[allow(missing_docs)]
+#[derive(Accounts)]
+pub struct ApplyPolicyUpdate<'info> {
+    pub authority: Signer<'info>,
+    #[account(mut, has_one = authority, constraint = !policy.paused @ FeeRouterError::Paused)]
+    pub policy: Account<'info, Policy>,
+    /// CHECK: progress PDA of the policy; may be uninitialized before the first crank.
+    #[account(
+        seeds = [PROGRESS_SEED, policy.cp_pool.as_ref()],
+        bump
+    )]
+    pub progress: UncheckedAccount<'info>,
+}
+
+/// Accounts for pause_policy and unpause_policy.
+#[allow(missing_docs)]
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    /// Guardian, or the authority when pausing.
+    pub signer: Signer<'info>,
+    #[account(mut)]
+    pub policy: Account<'info, Policy>,
+}
+
+/// Accounts for close_policy.
+#[allow(missing_docs)]
+#[derive(Accounts)]
+pub struct ClosePolicy<'info> {
+    /// Policy authority; receives the rent of every closed account.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        close = authority,
+        constraint = !policy.paused @ FeeRouterError::Paused
+    )]
+    pub policy: Box<Account<'info, Policy>>,
+    /// CHECK: progress PDA of the policy; may be uninitialized if never cranked.
+    #[account(
+        mut,
+        seeds = [PROGRESS_SEED, policy.cp_pool.as_ref()],
+        bump
+    )]
+    pub progress: UncheckedAccount<'info>,
+    /// CHECK: payout bitmap PDA of the policy; may be uninitialized.
+    #[account(
+        mut,
+        seeds = [PAYOUT_BITMAP_SEED, policy.key().as_ref()],
+        bump
+    )]
+    pub payout_bitmap: UncheckedAccount<'info>,
+    /// CHECK: distribution history PDA of the policy; may be uninitialized.
+    #[account(
+        mut,
+        seeds = [HISTORY_SEED, policy.key().as_ref()],
+        bump
+    )]
+    pub history: UncheckedAccount<'info>,
+    /// Registry of position 0; later positions must already be unregistered.
+    #[account(
+        mut,
+        seeds = [POSITION_SEED, policy.key().as_ref(), position_index_seed(&0)],
+        bump = honorary_position.bump,
+        has_one = owner_pda,
+        close = authority
+    )]
+    pub honorary_position: Box<Account<'info, HonoraryPosition>>,
+    /// CHECK: PDA owner of the honorary position; signs the position close.
+    #[account(
+        seeds = [VAULT_SEED, policy.key().as_ref(), FEE_POS_OWNER_SEED, position_index_seed(&0)],
+        bump
+    )]
+    pub owner_pda: UncheckedAccount<'info>,
+    /// CHECK: vault authority derived from policy.
+    #[account(
+        seeds = [VAULT_SEED, policy.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(mut, constraint = treasury_quote_ata.key() == policy.treasury_quote_ata)]
+    pub treasury_quote_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, constraint = creator_quote_ata.key() == policy.creator_quote_ata)]
+    pub creator_quote_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Quote mint; receives withheld Token-2022 transfer fees before the treasury closes.
+    #[account(mut, address = policy.quote_mint @ FeeRouterError::ConstraintViolation)]
+    pub quote_mint: Box<InterfaceAccount<'info, Mint>>,
+    /// CHECK: Meteora cp-amm program; must own the pool and the position.
+    #[account(constraint = cp_program.key() == *cp_pool.owner @ FeeRouterError::ConstraintViolation)]
+    pub cp_program: UncheckedAccount<'info>,
+    /// CHECK: Meteora cp-amm pool of the position.
+    #[account(mut, constraint = cp_pool.key() == honorary_position.cp_pool @ FeeRouterError::ConstraintViolation)]
+    pub cp_pool: UncheckedAccount<'info>,
+    /// CHECK: cp-amm pool authority PDA; validated by cp-amm.
+    pub cp_pool_authority: UncheckedAccount<'info>,
+    /// CHECK: Meteora cp-amm position registered in `honorary_position`.
+    #[account(mut, address = honorary_position.position @ FeeRouterError::ConstraintViolation)]
+    pub cp_position: UncheckedAccount<'info>,
+    /// CHECK: Position NFT mint; validated by cp-amm.
+    #[account(mut)]
+    pub position_nft_mint: UncheckedAccount<'info>,
+    /// CHECK: Position NFT token account; validated by cp-amm.
+    #[account(mut)]
+    pub position_nft_account: UncheckedAccount<'info>,
+    /// CHECK: Token program of the position NFT; validated by cp-amm.
+    pub nft_token_program: UncheckedAccount<'info>,
+    /// CHECK: cp-amm event authority PDA (checked in CPI helper).
+    pub event_authority: UncheckedAccount<'info>,
+    /// Token program of the quote mint.
+    #[account(address = policy.quote_token_program @ FeeRouterError::ConstraintViolation)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Accounts for collect_position_fees.
+#[allow(missing_docs)]
+#[derive(Accounts)]
+#[instruction(args: CollectPositionFeesArgs)]
+pub struct CollectPositionFees<'info> {
+    /// CHECK: Meteora cp-amm program id for CPI claim (must own `cp_pool`).
+    pub cp_program: UncheckedAccount<'info>,
+    /// CHECK: Pool of the honorary position (owner checked in CPI helper).
+    #[account(constraint = cp_pool.key() == honorary_position.cp_pool @ FeeRouterError::ConstraintViolation)]
+    pub cp_pool: UncheckedAccount<'info>,
+    /// CHECK: cp-amm pool authority PDA; validated by cp-amm during the claim.
+    pub cp_pool_authority: UncheckedAccount<'info>,
+    #[account(constraint = !policy.paused @ FeeRouterError::Paused)]
+    pub policy: Account<'info, Policy>,
+    #[account(
+        seeds = [POSITION_SEED, policy.key().as_ref(), position_index_seed(&args.position_index)],
+        bump = honorary_position.bump,
+        has_one = owner_pda,
+    )]
+    pub honorary_position: Account<'info, HonoraryPosition>,
+    /// CHECK: PDA owner of the honorary position; signs the fee claim.
+    #[account(
+        seeds = [VAULT_SEED, policy.key().as_ref(), FEE_POS_OWNER_SEED, position_index_seed(&args.position_index)],
+        bump
+    )]
+    pub owner_pda: UncheckedAccount<'info>,
+    /// CHECK: Meteora cp-amm position registered in `honorary_position`.
+    #[account(mut, address = honorary_position.position @ FeeRouterError::ConstraintViolation)]
+    pub cp_position: UncheckedAccount<'info>,
+    /// CHECK: Position NFT token account; validated by cp-amm during the claim.
+    pub position_nft_account: UncheckedAccount<'info>,
+    /// CHECK: Pool token A vault; validated by cp-amm during the claim.
+    #[account(mut)]
+    pub token_a_vault: UncheckedAccount<'info>,
+    /// CHECK: Pool token B vault; validated by cp-amm during the claim.
+    #[account(mut)]
+    pub token_b_vault: UncheckedAccount<'info>,
+    /// CHECK: Pool token A mint; validated by cp-amm during the claim.
+    pub token_a_mint: UncheckedAccount<'info>,
+    /// CHECK: Pool token B mint; validated by cp-amm during the claim.
+    pub token_b_mint: UncheckedAccount<'info>,
+    /// CHECK: cp-amm event authority PDA (checked in CPI helper).
+    pub event_authority: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = Progress::SPACE,
+        seeds = [PROGRESS_SEED, policy.cp_pool.as_ref()],
+        bump
+    )]
+    pub progress: Account<'info, Progress>,
+    /// Signer paying rent for progress if needed.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: vault authority derived from policy.
+    #[account(
+        seeds = [VAULT_SEED, policy.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(mut, constraint = treasury_quote_ata.key() == policy.treasury_quote_ata)]
+    pub treasury_quote_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Treasury token account for the pool's base mint; must not receive fees.
+    #[account(mut, constraint = treasury_base_ata.owner == vault_authority.key() @ FeeRouterError::Unauthorized)]
+    pub treasury_base_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    /// Token program of the quote mint.
+    #[account(address = policy.quote_token_program @ FeeRouterError::ConstraintViolation)]
+    pub token_program: Interface<'info, TokenInterface>,
+    /// Token program of the position's base mint.
+    pub base_token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+/// Collect position fees arguments.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct CollectPositionFeesArgs {
+    /// Honorary position to collect, `< Policy::position_count`.
+    pub position_index: u8,
+}
+
+/// Accounts for close_honorary_position.
+#[allow(missing_docs)]
+#[derive(Accounts)]
+pub struct CloseHonoraryPosition<'info> {
+    /// Policy authority; receives the rent of the closed accounts.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = policy.position_count > 1 @ FeeRouterError::ConstraintViolation,
+        constraint = !policy.paused @ FeeRouterError::Paused
+    )]
+    pub policy: Box<Account<'info, Policy>>,
+    /// CHECK: progress PDA of the policy; may be uninitialized if never cranked.
+    #[account(
+        seeds = [PROGRESS_SEED, policy.cp_pool.as_ref()],
+        bump
+    )]
+    pub progress: UncheckedAccount<'info>,
+    /// Registry of the last position, `policy.position_count - 1`.
+    #[account(
+        mut,
+        seeds = [POSITION_SEED, policy.key().as_ref(), position_index_seed(&(policy.position_count - 1))],
+        bump = honorary_position.bump,
+        has_one = owner_pda,
+        close = authority
+    )]
+    pub honorary_position: Box<Account<'info, HonoraryPosition>>,
+    /// CHECK: PDA owner of the honorary position; signs the position close.
+    #[account(
+        seeds = [VAULT_SEED, policy.key().as_ref(), FEE_POS_OWNER_SEED, position_index_seed(&honorary_position.index)],
+        bump
+    )]
+    pub owner_pda: UncheckedAccount<'info>,
+    /// CHECK: Meteora cp-amm program; must own the pool and the position.
+    #[account(constraint = cp_program.key() == *cp_pool.owner @ FeeRouterError::ConstraintViolation)]
+    pub cp_program: UncheckedAccount<'info>,
+    /// CHECK: Meteora cp-amm pool of the position.
+    #[account(mut, constraint = cp_pool.key() == honorary_position.cp_pool @ FeeRouterError::ConstraintViolation)]
+    pub cp_pool: UncheckedAccount<'info>,
+    /// CHECK: cp-amm pool authority PDA; validated by cp-amm.
+    pub cp_pool_authority: UncheckedAccount<'info>,
+    /// CHECK: Meteora cp-amm position registered in `honorary_position`.
+    #[account(mut, address = honorary_position.position @ FeeRouterError::ConstraintViolation)]
+    pub cp_position: UncheckedAccount<'info>,
+    /// CHECK: Position NFT mint; validated by cp-amm.
+    #[account(mut)]
+    pub position_nft_mint: UncheckedAccount<'info>,
+    /// CHECK: Position NFT token account; validated by cp-amm.
+    #[account(mut)]
+    pub position_nft_account: UncheckedAccount<'info>,
+    /// CHECK: Token program of the position NFT; validated by cp-amm.
+    pub nft_token_program: UncheckedAccount<'info>,
+    /// CHECK: cp-amm event authority PDA (checked in CPI helper).
+    pub event_authority: UncheckedAccount<'info>,
+}
+
+/// Close honorary position arguments.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct CloseHonoraryPositionArgs {
+    /// Close the cp-amm position via CPI; false only drops the registry.
+    pub close_position: bool,
+}
+
+/// Close policy arguments.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ClosePolicyArgs {
+    /// Close the cp-amm position via CPI; false leaves it (e.g. not empty) and only
+    /// drops the registry.
+    pub close_position: bool,
+}
+
+/// Accounts for force_close_day.
+#[allow(missing_docs)]
+#[derive(Accounts)]
+pub struct ForceCloseDay<'info> {
+    #[account(constraint = !policy.paused @ FeeRouterError::Paused)]
+    pub policy: Account<'info, Policy>,
+    #[account(
+        mut,
+        seeds = [PROGRESS_SEED, policy.cp_pool.as_ref()],
+        bump = progress.bump
+    )]
+    pub progress: Account<'info, Progress>,
+    #[account(
+        mut,
+        seeds = [HISTORY_SEED, policy.key().as_ref()],
+        bump = history.bump
+    )]
+    pub history: Box<Account<'info, DistributionHistory>>,
+    /// CHECK: vault authority derived from policy.
+    #[account(
+        seeds = [VAULT_SEED, policy.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(mut, constraint = treasury_quote_ata.key() == policy.treasury_quote_ata)]
+    pub treasury_quote_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = creator_quote_ata.key() == policy.creator_quote_ata)]
+    pub creator_quote_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = policy.quote_mint @ FeeRouterError::ConstraintViolation)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+    /// Token program of the quote mint.
+    #[account(address = policy.quote_token_program @ FeeRouterError::ConstraintViolation)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Accounts for release_dust.
+#[allow(missing_docs)]
+#[derive(Accounts)]
+pub struct ReleaseDust<'info> {
+    /// Policy authority; receives the ledger's rent.
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(has_one = authority, constraint = !policy.paused @ FeeRouterError::Paused)]
+    pub policy: Account<'info, Policy>,
+    #[account(
+        mut,
+        seeds = [PROGRESS_SEED, policy.cp_pool.as_ref()],
+        bump = progress.bump
+    )]
+    pub progress: Account<'info, Progress>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [DUST_LEDGER_SEED, policy.key().as_ref(), investor_quote_ata.key().as_ref()],
+        bump = dust_ledger.bump
+    )]
+    pub dust_ledger: Account<'info, DustLedger>,
+    /// CHECK: quote ATA the ledger belongs to (PDA seed); may have been closed.
+    #[account(mut)]
+    pub investor_quote_ata: UncheckedAccount<'info>,
+    #[account(mut, constraint = creator_quote_ata.key() == policy.creator_quote_ata)]
+    pub creator_quote_ata: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: vault authority derived from policy.
+    #[account(
+        seeds = [VAULT_SEED, policy.key().as_ref()],
+        bump
+    )]
+    pub vault_authority: UncheckedAccount<'info>,
+    #[account(mut, constraint = treasury_quote_ata.key() == policy.treasury_quote_ata)]
+    pub treasury_quote_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = policy.quote_mint @ FeeRouterError::ConstraintViolation)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+    /// Token program of the quote mint.
+    #[account(address = policy.quote_token_program @ FeeRouterError::ConstraintViolation)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Accounts for claim_fees.
 #[allow(missing_docs)]
 #[derive(Accounts)]
-pub struct CrankDistribute<'info> {
-    /// CHECK: Meteora cp-amm program id for CPI claim (checked against constant).
-    pub cp_program: UncheckedAccount<'info>,
-    /// CHECK: Meteora cp-amm pool account (owner check only).
-    pub cp_pool: UncheckedAccount<'info>,
+pub struct ClaimFees<'info> {
+    /// Investor wallet: owner of the quote ATA.
     #[account(mut)]
+    pub investor: Signer<'info>,
+    #[account(constraint = !policy.paused @ FeeRouterError::Paused)]
     pub policy: Account<'info, Policy>,
     #[account(
-        init_if_needed,
-        payer = payer,
-        space = Progress::SPACE,
+        mut,
         seeds = [PROGRESS_SEED, policy.cp_pool.as_ref()],
-        bump
+        bump = progress.bump
     )]
     pub progress: Account<'info, Progress>,
-    /// Signer paying rent for progress pagination account if needed.
-    #[account(mut)]
-    pub payer: Signer<'info>,
+    #[account(
+        init_if_needed,
+        payer = investor,
+        space = InvestorCheckpoint::SPACE,
+        seeds = [CHECKPOINT_SEED, policy.key().as_ref(), investor_quote_ata.key().as_ref()],
+        bump
+    )]
+    pub checkpoint: Account<'info, InvestorCheckpoint>,
     /// CHECK: vault authority derived from policy.
     #[account(
         seeds = [VAULT_SEED, policy.key().as_ref()],
         bump
     )]
     pub vault_authority: UncheckedAccount<'info>,
-    /// Treasury quote ATA to transfer from.
     #[account(mut, constraint = treasury_quote_ata.key() == policy.treasury_quote_ata)]
-    pub treasury_quote_ata: Account<'info, TokenAccount>,
-    /// Creator ATA to receive remainder on day close.
-    #[account(mut, constraint = creator_quote_ata.key() == policy.creator_quote_ata)]
-    pub creator_quote_ata: Account<'info, TokenAccount>,
-    /// Investor quote ATA for this page entry (must match policy.quote_mint).
-    #[account(mut, constraint = investor_quote_ata.mint == policy.quote_mint)]
-    pub investor_quote_ata: Account<'info, TokenAccount>,
-    /// CHECK: Streamflow stream account for the investor to read locked amount.
+    pub treasury_quote_ata: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        constraint = investor_quote_ata.mint == policy.quote_mint @ FeeRouterError::ConstraintViolation,
+        constraint = investor_quote_ata.owner == investor.key() @ FeeRouterError::Unauthorized
+    )]
+    pub investor_quote_ata: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: investor stream account, bound to the ATA by the Merkle leaf.
     pub stream: UncheckedAccount<'info>,
-    pub token_program: Program<'info, Token>,
+    #[account(address = policy.quote_mint @ FeeRouterError::ConstraintViolation)]
+    pub quote_mint: InterfaceAccount<'info, Mint>,
+    /// Token program of the quote mint.
+    #[account(address = policy.quote_token_program @ FeeRouterError::ConstraintViolation)]
+    pub token_program: Interface<'info, TokenInterface>,
+
     pub system_program: Program<'info, System>,
 }
 
+/// Pull-mode claim arguments.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ClaimFeesArgs {
+    /// Investor set index of the claimant.
+    pub index: u32,
+    /// Merkle proof of `(index, investor_quote_ata, stream)`.
+    pub proof: Vec<[u8; 32]>,
+}
+
 /// Crank arguments per page.
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
 pub struct CrankArgs {
-    /// Caller-chosen opaque cursor value for pagination bookkeeping.
+    /// Investor index of the first page entry; must equal `Progress::page_cursor`.
     pub page_cursor: u64,
-    /// Mark if this is the last page for the day.
+    /// Mark if this is the last page of the current phase; required exactly when
+    /// the page ends at `InvestorSet::count`.
     pub is_last_page: bool,
+    /// Phase the page belongs to; must match `Progress::phase`.
+    pub phase: DistributionPhase,
+    /// Honorary position a snapshot page collects (`< Policy::position_count`).
+    pub position_index: u8,
+    /// Merkle proof per page entry, in page order.
+    pub proofs: Vec<Vec<[u8; 32]>>,
 }
 
 /// Emitted when we set up the honorary position binding.
 #[event]
 pub struct HonoraryPositionInitialized {
+    /// Position index within the policy.
+    pub index: u8,
     /// Pool account.
     pub pool: Pubkey,
     /// Position account.
@@ -599,23 +2395,79 @@ pub struct PolicyInitialized {
     pub config_hash: [u8; 32],
 }
 
+/// Emitted when a policy update is proposed.
+#[event]
+pub struct PolicyUpdateProposed {
+    /// Policy account.
+    pub policy: Pubkey,
+    /// Earliest unix timestamp the update may be applied.
+    pub eligible_ts: i64,
+}
+
+/// Emitted after a policy update is applied, with the new config hash.
+#[event]
+pub struct PolicyUpdated {
+    /// Policy account.
+    pub policy: Pubkey,
+    /// Keccak hash of key config fields.
+    pub config_hash: [u8; 32],
+}
+
+/// Emitted when `pause_policy` pauses a policy.
+#[event]
+pub struct PolicyPaused {
+    /// Policy account.
+    pub policy: Pubkey,
+    /// Guardian or authority that paused.
+    pub signer: Pubkey,
+}
+
+/// Emitted when the guardian unpauses a policy.
+#[event]
+pub struct PolicyUnpaused {
+    /// Policy account.
+    pub policy: Pubkey,
+    /// Guardian that unpaused.
+    pub signer: Pubkey,
+}
+
 /// Emitted when a page of investors was paid.
 #[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct InvestorPayoutPage {
-    /// Current day key (floor(ts/86400)).
+    /// Day key (`Policy::epoch_index`).
     pub day: i64,
-    /// Cursor supplied by caller.
+    /// Investor index of the first entry in the page.
     pub page_cursor: u64,
     /// Number of investors in page.
     pub investors: u32,
-    /// Total paid to investors this page.
+    /// Total paid to investors this page, including released dust.
     pub paid_total: u64,
+    /// Quote added to investor dust ledgers this page.
+    pub dust_accrued: u64,
+    /// Crank reward paid to the page payer.
+    pub crank_reward: u64,
     /// Carry remainder after payouts.
     pub carry_after: u64,
 }
 
+/// Emitted when the snapshot pass closes and the investor pool is fixed.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvestorSnapshotClosed {
+    /// Day key.
+    pub day: i64,
+    /// Sum of still-locked amounts across all snapshot pages.
+    pub locked_total: u64,
+    /// Investor pool paid out pro rata during the payout pass.
+    pub investor_pool: u64,
+    /// Crank reward reserved for page payers.
+    pub crank_reward: u64,
+}
+
 /// Emitted on day close when routing remainder to creator.
 #[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct CreatorPayoutDayClosed {
     /// Day key.
     pub day: i64,
@@ -623,10 +2475,98 @@ pub struct CreatorPayoutDayClosed {
     pub remainder: u64,
 }
 
-/// Validates a cp-amm pool looks like a Meteora DLMM v2 pool and is bound to the quote mint.
+/// Emitted when an investor pulls accrued fees in pull mode.
+#[event]
+pub struct InvestorFeesClaimed {
+    /// Investor quote ATA paid.
+    pub investor_quote_ata: Pubkey,
+    /// Quote paid.
+    pub amount: u64,
+    /// Accumulator value the investor is now checkpointed at.
+    pub fee_per_locked_unit_acc: u128,
+}
+
+/// Emitted when `release_dust` pays out and closes a dust ledger.
+#[event]
+pub struct DustReleased {
+    /// Investor quote ATA the ledger belongs to.
+    pub investor_quote_ata: Pubkey,
+    /// Account paid: the investor quote ATA, or the creator's if it was closed.
+    pub recipient: Pubkey,
+    /// Dust paid.
+    pub amount: u64,
+}
+
+/// Emitted per remainder recipient when a day's remainder is paid out.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemainderPaid {
+    /// Day key (`Policy::epoch_index`).
+    pub day: i64,
+    /// Recipient quote ATA.
+    pub recipient: Pubkey,
+    /// Recipient share in bps.
+    pub bps: u16,
+    /// Quote paid.
+    pub amount: u64,
+}
+
+/// Emitted per honorary position fee claim.
+#[event]
+pub struct PositionFeesCollected {
+    /// Day the quote is booked to.
+    pub day: i64,
+    /// Position index within the policy.
+    pub index: u8,
+    /// Pool account.
+    pub pool: Pubkey,
+    /// Position account.
+    pub position: Pubkey,
+    /// Quote collected into the treasury.
+    pub amount: u64,
+}
+
+/// Emitted when `close_honorary_position` unregisters a position.
+#[event]
+pub struct HonoraryPositionClosed {
+    /// Position index within the policy.
+    pub index: u8,
+    /// Pool account.
+    pub pool: Pubkey,
+    /// Position account.
+    pub position: Pubkey,
+    /// Whether the cp-amm position was closed.
+    pub position_closed: bool,
+}
+
+/// Emitted when a policy is wound down by `close_policy`.
+#[event]
+pub struct PolicyClosed {
+    /// Closed policy account.
+    pub policy: Pubkey,
+    /// Treasury quote swept to the creator.
+    pub swept_quote: u64,
+    /// Whether the cp-amm position was closed.
+    pub position_closed: bool,
+}
+
+/// Emitted when a stalled day is closed by `force_close_day`.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DayForceClosed {
+    /// Day key.
+    pub day: i64,
+    /// Quote claimed but not paid to investors.
+    pub carry: u64,
+    /// True if the carry rolled into the next day; false if paid to the creator.
+    pub rolled_over: bool,
+}
+
+/// Validates a cp-amm pool is a Meteora DAMM v2 pool that accrues fees only in
+/// `quote_mint`: `quote_mint` must be token B under `CollectFeeMode::OnlyB`.
 fn assert_cp_pool_quote_only(
     cp_pool: &UncheckedAccount,
-    _quote_mint: &Account<Mint>,
+    quote_mint: &InterfaceAccount<Mint>,
 ) -> Result<()> {
     let default_program = match Pubkey::from_str(meteora_cpi::DEFAULT_DLMM_PROGRAM_ID) {
         Ok(pk) => pk,
@@ -637,25 +2577,798 @@ fn assert_cp_pool_quote_only(
         default_program,
         FeeRouterError::QuoteOnlyViolation
     );
+    let pool = meteora_cpi::PoolState::decode(&cp_pool.try_borrow_data()?)?;
+    require!(
+        pool.token_a_mint == quote_mint.key() || pool.token_b_mint == quote_mint.key(),
+        FeeRouterError::QuoteOnlyViolation
+    );
+    require!(
+        pool.fee_mint() == Some(quote_mint.key()),
+        FeeRouterError::QuoteOnlyViolation
+    );
+    Ok(())
+}
+
+/// Claims one honorary position's fees into the treasury, books them as claimed today
+/// and marks the position collected. Returns the quote collected.
+fn collect_position(
+    progress: &mut Progress,
+    policy_key: &Pubkey,
+    position: &HonoraryPosition,
+    owner_bump: u8,
+    accounts: meteora_cpi::CollectQuoteFeesAccounts,
+    quote_mint: &Pubkey,
+) -> Result<u64> {
+    let owner_seeds: &[&[u8]] = &[
+        VAULT_SEED,
+        policy_key.as_ref(),
+        FEE_POS_OWNER_SEED,
+        position_index_seed(&position.index),
+        &[owner_bump],
+    ];
+    let pool = accounts.pool.key();
+    let amount = meteora_cpi::collect_quote_fees(accounts, quote_mint, owner_seeds)?;
+    progress.claimed_quote_today = progress
+        .claimed_quote_today
+        .checked_add(amount)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    progress.collected_positions |= 1 << position.index;
+    emit!(PositionFeesCollected {
+        day: progress.current_day,
+        index: position.index,
+        pool,
+        position: position.position,
+        amount
+    });
+    Ok(amount)
+}
+
+/// Adds `share` to an investor's dust and releases it once it reaches `min_payout`.
+/// Returns `(payout, accrued_after)`.
+pub fn settle_dust(accrued: u64, share: u64, min_payout: u64) -> Result<(u64, u64)> {
+    let owed = accrued
+        .checked_add(share)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    if owed > 0 && owed >= min_payout {
+        Ok((owed, 0))
+    } else {
+        Ok((0, owed))
+    }
+}
+
+/// Crank pages of a full day at `max_page_size` entries per page: the snapshot pass
+/// in pull mode, snapshot and payout passes in push mode.
+pub fn pages_per_day(policy: &Policy) -> u64 {
+    let per_pass = (policy.investor_set.count as u64)
+        .div_ceil(policy.max_page_size.max(1) as u64)
+        .max(1);
+    match policy.claim_mode {
+        ClaimMode::Pull => per_pass,
+        ClaimMode::Push => 2 * per_pass,
+    }
+}
+
+/// Fixes the day's crank reward on its first page from the quote claimed so far (at
+/// most `treasury_quote`): [`compute_crank_reward`] split into an equal amount per
+/// page of [`pages_per_day`]. The rounding residue is not reserved and stays in the
+/// split between investors and the remainder.
+pub fn fix_crank_reward(
+    progress: &mut Progress,
+    policy: &Policy,
+    treasury_quote: u64,
+) -> Result<()> {
+    let claimed = progress.claimed_quote_today.min(treasury_quote);
+    let pages = pages_per_day(policy);
+    progress.crank_reward_per_page = compute_crank_reward(policy, claimed)? / pages;
+    progress.crank_reward_today = progress
+        .crank_reward_per_page
+        .checked_mul(pages)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    Ok(())
+}
+
+/// Pays one page's crank reward: the fixed per-page amount, bounded by what is left of
+/// the day's reward, offered to `pay_reward`, which returns the amount paid. Whatever
+/// goes unpaid stays undistributed and ends up in the remainder.
+pub fn pay_page_crank_reward(
+    progress: &mut Progress,
+    pay_reward: impl FnOnce(u64) -> Result<u64>,
+) -> Result<u64> {
+    let owed = progress.crank_reward_per_page.min(
+        progress
+            .crank_reward_today
+            .saturating_sub(progress.crank_reward_paid_today),
+    );
+    let paid = pay_reward(owed)?;
+    progress.crank_reward_paid_today = progress
+        .crank_reward_paid_today
+        .checked_add(paid)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    Ok(paid)
+}
+
+/// Snapshot close: fixes the investor pool from the quote claimed today (at most
+/// `treasury_quote`) net of the crank reward, and starts the payout pass.
+pub fn close_snapshot(progress: &mut Progress, policy: &Policy, treasury_quote: u64) -> Result<()> {
+    // Only quote claimed today is distributable; pre-existing funds are not. Snapshot
+    // pages have already paid part of the crank reward out of the treasury.
+    let claimed = progress
+        .claimed_quote_today
+        .min(treasury_quote.saturating_add(progress.crank_reward_paid_today));
+    // The crank reward comes off the top, before investors and creator.
+    progress.investor_pool_today = compute_investor_pool(
+        policy,
+        claimed.saturating_sub(progress.crank_reward_today),
+        progress.locked_total_today,
+    )?;
+    progress.carry_quote_today = progress.investor_pool_today;
+    progress.phase = DistributionPhase::Payout;
+    progress.page_cursor = 0;
+    Ok(())
+}
+
+/// One investor entry of a payout page, as [`run_payout_page`] settles it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PayoutEntry {
+    /// Already settled today (its payout bitmap bit was set); left untouched.
+    pub settled: bool,
+    /// Still-locked amount at the day's snapshot.
+    pub locked: u64,
+    /// Dust ledger balance, updated in place.
+    pub dust: u64,
+    /// Share of the investor pool allocated today.
+    pub share: u64,
+    /// Quote sent out of the treasury, released dust included.
+    pub payout: u64,
+    /// Quote the investor received after the quote mint's transfer fee.
+    pub received: u64,
+}
+
+/// Page totals reported by `InvestorPayoutPage`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PayoutPageTotals {
+    /// Quote received by the page's investors.
+    pub paid_total: u64,
+    /// Dust newly accrued on the page.
+    pub dust_accrued: u64,
+    /// Crank reward paid to the page payer.
+    pub crank_reward: u64,
+}
+
+/// Payout page bookkeeping, shared by `crank_distribute` and off-chain replays.
+///
+/// Allocates each unsettled entry `investor_pool * locked / locked_total` (capped by
+/// the pool left), settles it against the entry's dust and calls
+/// `transfer(offset, amount)` for every non-zero payout. `transfer` returns what the
+/// investor received; the withheld transfer fee is booked against today's share. The
+/// page's crank reward is then paid through [`pay_page_crank_reward`].
+pub fn run_payout_page(
+    progress: &mut Progress,
+    policy: &Policy,
+    entries: &mut [PayoutEntry],
+    mut transfer: impl FnMut(usize, u64) -> Result<u64>,
+    pay_reward: impl FnOnce(u64) -> Result<u64>,
+) -> Result<PayoutPageTotals> {
+    let mut totals = PayoutPageTotals::default();
+    for (offset, entry) in entries.iter_mut().enumerate() {
+        if entry.settled {
+            continue;
+        }
+        let share = compute_investor_quote(
+            progress.investor_pool_today,
+            entry.locked,
+            progress.locked_total_today,
+        )?
+        .min(progress.investor_pool_remaining());
+        // The share is allocated to the investor either way; below the dust threshold
+        // it waits in the ledger instead of going to the creator.
+        progress.distributed_quote_today = progress
+            .distributed_quote_today
+            .checked_add(share)
+            .ok_or(FeeRouterError::ArithmeticOverflow)?;
+        let prior_dust = entry.dust;
+        let (payout, accrued) = settle_dust(prior_dust, share, policy.min_payout_lamports)?;
+        entry.share = share;
+        entry.payout = payout;
+        entry.dust = accrued;
+        progress.dust_reserve_quote = progress
+            .dust_reserve_quote
+            .checked_add(accrued)
+            .and_then(|reserve| reserve.checked_sub(prior_dust))
+            .ok_or(FeeRouterError::ArithmeticOverflow)?;
+        totals.dust_accrued = totals
+            .dust_accrued
+            .checked_add(accrued.saturating_sub(prior_dust))
+            .ok_or(FeeRouterError::ArithmeticOverflow)?;
+        if payout == 0 {
+            continue;
+        }
+        let received = transfer(offset, payout)?;
+        entry.received = received;
+        // The transfer fee comes out of today's share; any excess was withheld from
+        // dust already counted on earlier days.
+        let fee = (payout - received).min(share);
+        progress.distributed_quote_today -= fee;
+        progress.transfer_fees_today = progress
+            .transfer_fees_today
+            .checked_add(fee)
+            .ok_or(FeeRouterError::ArithmeticOverflow)?;
+        totals.paid_total = totals
+            .paid_total
+            .checked_add(received)
+            .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    }
+
+    totals.crank_reward = pay_page_crank_reward(progress, pay_reward)?;
+    progress.carry_quote_today = progress.investor_pool_remaining();
+    Ok(totals)
+}
+
+/// Loads the dust ledger of a page entry, creating the PDA on first use (rent paid
+/// by `payer`). Persist changes with `store_entry_pda`.
+fn load_dust_ledger<'info>(
+    program_id: &Pubkey,
+    policy: &Pubkey,
+    investor_ai: &AccountInfo<'info>,
+    ledger_ai: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program_ai: &AccountInfo<'info>,
+) -> Result<DustLedger> {
+    let seeds: &[&[u8]] = &[DUST_LEDGER_SEED, policy.as_ref(), investor_ai.key.as_ref()];
+    let Some(bump) = create_entry_pda(
+        program_id,
+        seeds,
+        DustLedger::SPACE,
+        ledger_ai,
+        payer,
+        system_program_ai,
+    )?
+    else {
+        return DustLedger::try_deserialize(&mut &ledger_ai.try_borrow_data()?[..]);
+    };
+    Ok(DustLedger {
+        policy: *policy,
+        investor_quote_ata: *investor_ai.key,
+        accrued_quote: 0,
+        locked_snapshot: 0,
+        bump,
+    })
+}
+
+/// Loads the pull-mode checkpoint of a page entry, creating the PDA on first use
+/// (rent paid by `payer`). Persist changes with `store_entry_pda`.
+fn load_checkpoint<'info>(
+    program_id: &Pubkey,
+    policy: &Pubkey,
+    investor_ai: &AccountInfo<'info>,
+    checkpoint_ai: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program_ai: &AccountInfo<'info>,
+) -> Result<InvestorCheckpoint> {
+    let seeds: &[&[u8]] = &[CHECKPOINT_SEED, policy.as_ref(), investor_ai.key.as_ref()];
+    let Some(bump) = create_entry_pda(
+        program_id,
+        seeds,
+        InvestorCheckpoint::SPACE,
+        checkpoint_ai,
+        payer,
+        system_program_ai,
+    )?
+    else {
+        return InvestorCheckpoint::try_deserialize(&mut &checkpoint_ai.try_borrow_data()?[..]);
+    };
+    Ok(InvestorCheckpoint {
+        policy: *policy,
+        investor_quote_ata: *investor_ai.key,
+        fee_per_locked_unit_acc: 0,
+        locked_snapshot: 0,
+        owed_quote: 0,
+        claimed_total: 0,
+        bump,
+    })
+}
+
+/// Writes a page entry PDA loaded by `load_checkpoint` or `load_dust_ledger` back to
+/// its account.
+fn store_entry_pda<T: AccountSerialize>(target: &AccountInfo, state: &T) -> Result<()> {
+    let mut data = target.try_borrow_mut_data()?;
+    let mut writer: &mut [u8] = &mut data;
+    state.try_serialize(&mut writer)
+}
+
+/// Checks that `target` is the page entry PDA of `seeds` and creates it if it does not
+/// exist yet. Returns the bump if the account was created, `None` if it already
+/// belongs to the program.
+fn create_entry_pda<'info>(
+    program_id: &Pubkey,
+    seeds: &[&[u8]],
+    space: usize,
+    target: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program_ai: &AccountInfo<'info>,
+) -> Result<Option<u8>> {
+    let (expected, bump) = Pubkey::find_program_address(seeds, program_id);
+    require_keys_eq!(*target.key, expected, FeeRouterError::InvalidInvestorPage);
+    if target.owner == program_id {
+        return Ok(None);
+    }
+    let rent = Rent::get()?.minimum_balance(space);
+    let bump_seed = [bump];
+    let mut signer_seeds = seeds.to_vec();
+    signer_seeds.push(&bump_seed);
+    system_program::create_account(
+        CpiContext::new_with_signer(
+            system_program_ai.clone(),
+            system_program::CreateAccount {
+                from: payer.clone(),
+                to: target.clone(),
+            },
+            &[&signer_seeds],
+        ),
+        rent,
+        space as u64,
+        program_id,
+    )?;
+    Ok(Some(bump))
+}
+
+/// Crank reward for a day's claimed quote:
+/// `min(claimed * crank_reward_bps / 10_000, crank_reward_cap_quote)` (cap 0 disables).
+pub fn compute_crank_reward(policy: &Policy, claimed: u64) -> Result<u64> {
+    let reward = (claimed as u128)
+        .checked_mul(policy.crank_reward_bps as u128)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?
+        / 10_000;
+    let reward = u64::try_from(reward).map_err(|_| error!(FeeRouterError::ArithmeticOverflow))?;
+    if policy.crank_reward_cap_quote > 0 {
+        Ok(reward.min(policy.crank_reward_cap_quote))
+    } else {
+        Ok(reward)
+    }
+}
+
+/// Pays `amount` of crank reward to the page payer, if they passed a quote account.
+/// Returns the amount paid out of the treasury; the payer bears any transfer fee.
+fn pay_crank_reward<'info>(
+    amount: u64,
+    payer_quote_ata: Option<&InterfaceAccount<'info, TokenAccount>>,
+    treasury_quote_ata: &AccountInfo<'info>,
+    quote_mint: &InterfaceAccount<'info, Mint>,
+    vault_authority: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    signer: &[&[&[u8]]],
+) -> Result<u64> {
+    let Some(payer_quote_ata) = payer_quote_ata else {
+        return Ok(0);
+    };
+    if amount == 0 {
+        return Ok(0);
+    }
+    transfer_quote(
+        token_program,
+        treasury_quote_ata,
+        quote_mint,
+        &payer_quote_ata.to_account_info(),
+        vault_authority,
+        signer,
+        amount,
+    )?;
+    Ok(amount)
+}
+
+/// Day close: routes quote claimed but not paid to investors to the remainder
+/// recipients.
+#[allow(clippy::too_many_arguments)]
+fn close_day<'info>(
+    progress: &mut Progress,
+    history: &mut DistributionHistory,
+    policy: &Policy,
+    treasury_quote_ata: &mut InterfaceAccount<'info, TokenAccount>,
+    quote_mint: &InterfaceAccount<'info, Mint>,
+    creator_quote_ata: &AccountInfo<'info>,
+    recipient_accounts: &[AccountInfo<'info>],
+    vault_authority: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    signer: &[&[&[u8]]],
+) -> Result<()> {
+    treasury_quote_ata.reload()?;
+    let remainder = progress
+        .undistributed_quote()
+        .min(treasury_quote_ata.amount);
+    pay_remainder(
+        progress.current_day,
+        policy,
+        remainder,
+        &treasury_quote_ata.to_account_info(),
+        quote_mint,
+        creator_quote_ata,
+        recipient_accounts,
+        vault_authority,
+        token_program,
+        signer,
+    )?;
+    progress.carry_quote_today = 0;
+    progress.day_closed = true;
+    record_day(history, progress, policy, remainder)?;
+    emit!(CreatorPayoutDayClosed {
+        day: progress.current_day,
+        remainder
+    });
+    Ok(())
+}
+
+/// Appends the just-closed day of `progress` to `history`.
+fn record_day(
+    history: &mut DistributionHistory,
+    progress: &Progress,
+    policy: &Policy,
+    creator_remainder: u64,
+) -> Result<()> {
+    history.push(DayRecord {
+        day: progress.current_day,
+        claimed_quote: progress.claimed_quote_today,
+        investor_quote: progress.distributed_quote_today,
+        creator_remainder,
+        investor_count: policy.investor_set.count,
+        force_closed: progress.force_closed,
+        closed_slot: Clock::get()?.slot,
+    });
+    Ok(())
+}
+
+/// Pays `amount` out of the treasury along the policy remainder split, emitting
+/// `RemainderPaid` per recipient. `recipient_accounts` hold the split's quote ATAs
+/// in policy order; with no split the whole amount goes to `creator_quote_ata`.
+#[allow(clippy::too_many_arguments)]
+fn pay_remainder<'info>(
+    day: i64,
+    policy: &Policy,
+    amount: u64,
+    treasury_quote_ata: &AccountInfo<'info>,
+    quote_mint: &InterfaceAccount<'info, Mint>,
+    creator_quote_ata: &AccountInfo<'info>,
+    recipient_accounts: &[AccountInfo<'info>],
+    vault_authority: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    signer: &[&[&[u8]]],
+) -> Result<()> {
+    let shares = split_remainder(&policy.remainder_split, policy.creator_quote_ata, amount)?;
+    if policy.remainder_split.count > 0 {
+        require!(
+            recipient_accounts.len() == shares.len(),
+            FeeRouterError::ConstraintViolation
+        );
+    }
+    for (i, (recipient, share)) in shares.iter().enumerate() {
+        let destination = match policy.remainder_split.count {
+            0 => creator_quote_ata,
+            _ => &recipient_accounts[i],
+        };
+        require_keys_eq!(
+            *destination.key,
+            recipient.quote_ata,
+            FeeRouterError::ConstraintViolation
+        );
+        if *share > 0 {
+            transfer_quote(
+                token_program,
+                treasury_quote_ata,
+                quote_mint,
+                destination,
+                vault_authority,
+                signer,
+                *share,
+            )?;
+        }
+        emit!(RemainderPaid {
+            day,
+            recipient: recipient.quote_ata,
+            bps: recipient.bps,
+            amount: *share
+        });
+    }
+    Ok(())
+}
+
+/// Checks that `accounts` are the split's recipient quote ATAs in order: initialized,
+/// unfrozen token accounts of `quote_mint` under `token_program`, so that paying the
+/// remainder cannot fail on them.
+fn assert_remainder_accounts(
+    split: &RemainderSplit,
+    accounts: &[AccountInfo],
+    quote_mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<()> {
+    require!(
+        accounts.len() == split.recipients().len(),
+        FeeRouterError::ConstraintViolation
+    );
+    for (recipient, account_ai) in split.recipients().iter().zip(accounts) {
+        require_keys_eq!(
+            *account_ai.key,
+            recipient.quote_ata,
+            FeeRouterError::ConstraintViolation
+        );
+        require_keys_eq!(
+            *account_ai.owner,
+            *token_program,
+            FeeRouterError::ConstraintViolation
+        );
+        let account = TokenAccount::try_deserialize(&mut &account_ai.try_borrow_data()?[..])
+            .map_err(|_| error!(FeeRouterError::ConstraintViolation))?;
+        require_keys_eq!(
+            account.mint,
+            *quote_mint,
+            FeeRouterError::ConstraintViolation
+        );
+        require!(!account.is_frozen(), FeeRouterError::ConstraintViolation);
+    }
+    Ok(())
+}
+
+/// Moves `amount` quote out of the treasury with `transfer_checked`. Returns what the
+/// destination receives after the quote mint's transfer fee.
+fn transfer_quote<'info>(
+    token_program: &AccountInfo<'info>,
+    treasury_quote_ata: &AccountInfo<'info>,
+    quote_mint: &InterfaceAccount<'info, Mint>,
+    destination: &AccountInfo<'info>,
+    vault_authority: &AccountInfo<'info>,
+    signer: &[&[&[u8]]],
+    amount: u64,
+) -> Result<u64> {
+    let cpi_ctx = CpiContext::new_with_signer(
+        token_program.clone(),
+        TransferChecked {
+            from: treasury_quote_ata.clone(),
+            mint: quote_mint.to_account_info(),
+            to: destination.clone(),
+            authority: vault_authority.clone(),
+        },
+        signer,
+    );
+    token_interface::transfer_checked(cpi_ctx, amount, quote_mint.decimals)?;
+    let fee = transfer_fee(&quote_mint.to_account_info(), amount, Clock::get()?.epoch)?;
+    Ok(amount - fee)
+}
+
+/// Token-2022 transfer fee configuration of `mint`, if it has one.
+fn transfer_fee_config(mint: &AccountInfo) -> Result<Option<TransferFeeConfig>> {
+    if *mint.owner != spl_token_2022::ID {
+        return Ok(None);
+    }
+    let data = mint.try_borrow_data()?;
+    let state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+    Ok(state.get_extension::<TransferFeeConfig>().ok().copied())
+}
+
+/// Fee withheld by the transfer fee extension of `mint` on a transfer of `amount`
+/// in `epoch`; 0 for mints without the extension.
+pub fn transfer_fee(mint: &AccountInfo, amount: u64, epoch: u64) -> Result<u64> {
+    match transfer_fee_config(mint)? {
+        Some(config) => config
+            .calculate_epoch_fee(epoch, amount)
+            .ok_or(error!(FeeRouterError::ArithmeticOverflow)),
+        None => Ok(0),
+    }
+}
+
+/// Closes a program-owned account that is not deserialized as an `Account`,
+/// moving its lamports to `destination`.
+fn close_program_account<'info>(
+    info: &AccountInfo<'info>,
+    destination: &AccountInfo<'info>,
+) -> Result<()> {
+    let lamports = info.lamports();
+    **destination.try_borrow_mut_lamports()? = destination
+        .lamports()
+        .checked_add(lamports)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    **info.try_borrow_mut_lamports()? = 0;
+    info.assign(&system_program::ID);
+    info.resize(0)?;
+    Ok(())
+}
+
+/// Splits `amount` by recipient bps (floored); the last recipient takes the rounding
+/// dust. An empty split yields a single 10_000 bps share for `creator_quote_ata`.
+pub fn split_remainder(
+    split: &RemainderSplit,
+    creator_quote_ata: Pubkey,
+    amount: u64,
+) -> Result<Vec<(RemainderRecipient, u64)>> {
+    let recipients = split.recipients();
+    let Some((last, rest)) = recipients.split_last() else {
+        let creator = RemainderRecipient {
+            quote_ata: creator_quote_ata,
+            bps: 10_000,
+        };
+        return Ok(vec![(creator, amount)]);
+    };
+    let mut shares = Vec::with_capacity(recipients.len());
+    let mut paid: u64 = 0;
+    for recipient in rest {
+        let share = (amount as u128)
+            .checked_mul(recipient.bps as u128)
+            .ok_or(FeeRouterError::ArithmeticOverflow)?
+            / 10_000;
+        let share = u64::try_from(share).map_err(|_| error!(FeeRouterError::ArithmeticOverflow))?;
+        paid = paid
+            .checked_add(share)
+            .ok_or(FeeRouterError::ArithmeticOverflow)?;
+        shares.push((*recipient, share));
+    }
+    let last_share = amount
+        .checked_sub(paid)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    shares.push((*last, last_share));
+    Ok(shares)
+}
+
+/// Pull mode snapshot close: adds today's investor pool to the per-locked-unit
+/// accumulator and reserves it in the treasury for `claim_fees`.
+pub fn accrue_pull_pool(progress: &mut Progress) -> Result<()> {
+    let pool = progress.investor_pool_today;
+    if pool == 0 || progress.locked_total_today == 0 {
+        return Ok(());
+    }
+    let per_unit = (pool as u128)
+        .checked_mul(ACC_SCALE)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?
+        .checked_div(progress.locked_total_today as u128)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    progress.fee_per_locked_unit_acc = progress
+        .fee_per_locked_unit_acc
+        .checked_add(per_unit)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    // Only what the recorded locked amounts can settle is reserved; the rounding
+    // residue of `per_unit` stays undistributed and goes to the remainder.
+    let booked = per_unit
+        .checked_mul(progress.locked_total_today as u128)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    let reserved_before = progress.pull_reserve_quote();
+    progress.pull_unsettled_scaled = progress
+        .pull_unsettled_scaled
+        .checked_add(booked)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    progress.distributed_quote_today = progress.pull_reserve_quote() - reserved_before;
+    progress.carry_quote_today = 0;
     Ok(())
 }
 
-fn compute_investor_quote(policy: &Policy, locked_total: u64, distributable: u64) -> Result<u64> {
-    if locked_total == 0 || distributable == 0 {
+/// Pull mode: settles what the checkpoint's recorded locked amount accrued since its
+/// last settlement into `owed_quote` (floored; the fraction is released from the
+/// reserve). Returns the newly owed quote.
+pub fn settle_pull_checkpoint(
+    progress: &mut Progress,
+    checkpoint: &mut InvestorCheckpoint,
+) -> Result<u64> {
+    let delta = progress
+        .fee_per_locked_unit_acc
+        .checked_sub(checkpoint.fee_per_locked_unit_acc)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    let accrued = (checkpoint.locked_snapshot as u128)
+        .checked_mul(delta)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    progress.pull_unsettled_scaled = progress
+        .pull_unsettled_scaled
+        .checked_sub(accrued)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    let owed = u64::try_from(accrued / ACC_SCALE)
+        .map_err(|_| error!(FeeRouterError::ArithmeticOverflow))?;
+    checkpoint.owed_quote = checkpoint
+        .owed_quote
+        .checked_add(owed)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    progress.pull_owed_quote = progress
+        .pull_owed_quote
+        .checked_add(owed)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    checkpoint.fee_per_locked_unit_acc = progress.fee_per_locked_unit_acc;
+    Ok(owed)
+}
+
+/// Merkle leaf of investor `index`: `keccak(index u32 LE || investor_quote_ata || stream)`.
+pub fn investor_leaf(index: u32, investor_quote_ata: &Pubkey, stream: &Pubkey) -> [u8; 32] {
+    keccak::hashv(&[
+        &index.to_le_bytes(),
+        investor_quote_ata.as_ref(),
+        stream.as_ref(),
+    ])
+    .to_bytes()
+}
+
+/// Verifies every page entry against the investor set, starting at index `start`.
+/// Returns the index following the last entry.
+fn verify_investor_page(
+    investor_set: &InvestorSet,
+    start: u64,
+    page: &[(AccountInfo, AccountInfo)],
+    proofs: &[Vec<[u8; 32]>],
+) -> Result<u64> {
+    require!(
+        proofs.len() == page.len(),
+        FeeRouterError::InvalidInvestorPage
+    );
+    let end = start
+        .checked_add(page.len() as u64)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    require!(
+        end <= investor_set.count as u64,
+        FeeRouterError::InvalidInvestorPage
+    );
+    for (offset, ((investor_ai, stream_ai), proof)) in page.iter().zip(proofs).enumerate() {
+        let index = (start + offset as u64) as u32;
+        let leaf = investor_leaf(index, investor_ai.key, stream_ai.key);
+        require!(
+            merkle::verify_merkle_proof(leaf, proof, investor_set.root),
+            FeeRouterError::InvalidInvestorProof
+        );
+    }
+    Ok(end)
+}
+
+/// Reads the still-locked amount of a page entry at `ts`.
+/// The investor ATA must hold the policy quote mint; its owner is the stream recipient.
+fn read_locked(
+    policy: &Policy,
+    investor_ai: &AccountInfo,
+    stream_ai: &AccountInfo,
+    ts: i64,
+) -> Result<u64> {
+    require_keys_eq!(
+        *investor_ai.owner,
+        policy.quote_token_program,
+        FeeRouterError::ConstraintViolation
+    );
+    let investor_ata = TokenAccount::try_deserialize(&mut &investor_ai.data.borrow()[..])?;
+    require_keys_eq!(
+        investor_ata.mint,
+        policy.quote_mint,
+        FeeRouterError::ConstraintViolation
+    );
+    stream_adapter::locked_amount(policy.lock_source, stream_ai, &investor_ata.owner, ts)
+}
+
+/// Investor pool for a day:
+/// `min(claimed * min(bps, locked_total * 10_000 / y0_total) / 10_000, daily_cap_quote)`.
+pub fn compute_investor_pool(
+    policy: &Policy,
+    claimed_quote: u64,
+    locked_total: u64,
+) -> Result<u64> {
+    if locked_total == 0 || claimed_quote == 0 {
         return Ok(0);
     }
     let f_locked_bps = (locked_total as u128)
         .checked_mul(10_000)
         .ok_or(FeeRouterError::ArithmeticOverflow)?
         .checked_div(policy.y0_total as u128)
-        .ok_or(FeeRouterError::ArithmeticOverflow)? as u64;
-    let eligible_bps = f_locked_bps.min(policy.investor_fee_share_bps as u64);
-    let investor_fee_quote = (distributable as u128)
-        .checked_mul(eligible_bps as u128)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    let eligible_bps = f_locked_bps.min(policy.investor_fee_share_bps as u128);
+    let pool = (claimed_quote as u128)
+        .checked_mul(eligible_bps)
         .ok_or(FeeRouterError::ArithmeticOverflow)?
         .checked_div(10_000)
         .ok_or(FeeRouterError::ArithmeticOverflow)? as u64;
-    Ok(investor_fee_quote)
+    if policy.daily_cap_quote > 0 {
+        Ok(pool.min(policy.daily_cap_quote))
+    } else {
+        Ok(pool)
+    }
+}
+
+/// Pro-rata investor share: `investor_pool * locked / locked_total` (floored).
+pub fn compute_investor_quote(investor_pool: u64, locked: u64, locked_total: u64) -> Result<u64> {
+    if locked == 0 || locked_total == 0 || investor_pool == 0 {
+        return Ok(0);
+    }
+    let share = (investor_pool as u128)
+        .checked_mul(locked as u128)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?
+        .checked_div(locked_total as u128)
+        .ok_or(FeeRouterError::ArithmeticOverflow)?;
+    u64::try_from(share).map_err(|_| error!(FeeRouterError::ArithmeticOverflow))
 }
 
 #[cfg(all(test, not(target_arch = "bpf")))]
@@ -672,33 +3385,181 @@ mod tests {
         );
     }
 
+    #[test]
+    fn position_zero_keeps_single_position_seeds() {
+        let policy = Pubkey::new_unique();
+        let pda = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &crate::ID);
+        let owner = |index: u8| {
+            pda(&[
+                VAULT_SEED,
+                policy.as_ref(),
+                FEE_POS_OWNER_SEED,
+                position_index_seed(&index),
+            ])
+        };
+        let registry =
+            |index: u8| pda(&[POSITION_SEED, policy.as_ref(), position_index_seed(&index)]);
+
+        // Policies from before multiple positions keep their position 0 accounts.
+        assert_eq!(
+            owner(0),
+            pda(&[VAULT_SEED, policy.as_ref(), FEE_POS_OWNER_SEED])
+        );
+        assert_eq!(registry(0), pda(&[POSITION_SEED, policy.as_ref()]));
+        assert_eq!(
+            owner(1),
+            pda(&[VAULT_SEED, policy.as_ref(), FEE_POS_OWNER_SEED, &[1]])
+        );
+        assert_ne!(owner(1), owner(0));
+        assert_ne!(registry(1), registry(0));
+    }
+
+    fn policy(investor_fee_share_bps: u16, daily_cap_quote: u64) -> Policy {
+        Policy {
+            authority: Pubkey::default(),
+            guardian: Pubkey::default(),
+            cp_pool: Pubkey::default(),
+            quote_mint: Pubkey::default(),
+            quote_token_program: Pubkey::default(),
+            creator_quote_ata: Pubkey::default(),
+            remainder_split: RemainderSplit::default(),
+            treasury_quote_ata: Pubkey::default(),
+            investor_fee_share_bps,
+            y0_total: 1_000_000,
+            daily_cap_quote,
+            min_payout_lamports: 0,
+            crank_reward_bps: 0,
+            crank_reward_cap_quote: 0,
+            investor_set: InvestorSet::default(),
+            force_close_grace_secs: 0,
+            roll_forced_carry: false,
+            claim_mode: ClaimMode::Push,
+            lock_source: LockSource::Streamflow,
+            epoch_seconds: 86_400,
+            epoch_offset_seconds: 0,
+            max_page_size: 1,
+            position_count: 1,
+            pending_update: None,
+            paused: false,
+            bump: 0,
+            initialized: true,
+        }
+    }
+
     #[test]
     fn pro_rata_math_and_caps() {
-        // Simulate one investor page
-        let y0_total = 1_000_000u64;
-        let locked = 250_000u64; // 25%
-        let investor_fee_share_bps = 2_000u16; // max 20%
-        let distributable = 10_000u64; // today’s distributable
-        let f_locked_bps = ((locked as u128 * 10_000) / (y0_total as u128)) as u64; // 2500 bps
-        let eligible_bps = core::cmp::min(f_locked_bps, investor_fee_share_bps as u64); // 2000
-        let share = ((distributable as u128) * (eligible_bps as u128) / 10_000) as u64; // 2000
-        assert_eq!(share, 2_000);
+        // 25% of Y0 still locked, max investor share 20%.
+        let p = policy(2_000, 0);
+        assert_eq!(compute_investor_pool(&p, 10_000, 250_000).unwrap(), 2_000);
+        // 10% locked scales the share below the max.
+        assert_eq!(compute_investor_pool(&p, 10_000, 100_000).unwrap(), 1_000);
+        // Nothing locked, nothing for investors.
+        assert_eq!(compute_investor_pool(&p, 10_000, 0).unwrap(), 0);
 
         // Cap clamp
-        let cap = 1500u64;
-        let remaining_cap = cap;
-        let dist = core::cmp::min(remaining_cap, distributable);
-        let share_capped = ((dist as u128) * (eligible_bps as u128) / 10_000) as u64;
-        assert_eq!(dist, 1_500);
-        assert_eq!(share_capped, 300);
+        let capped = policy(2_000, 1_500);
+        assert_eq!(
+            compute_investor_pool(&capped, 10_000, 250_000).unwrap(),
+            1_500
+        );
+
+        // Pro-rata split floors each share.
+        assert_eq!(compute_investor_quote(2_000, 1, 3).unwrap(), 666);
+        assert_eq!(compute_investor_quote(2_000, 0, 3).unwrap(), 0);
+    }
+
+    #[test]
+    fn pull_accumulator_matches_push_shares() {
+        let mut progress = Progress {
+            current_day: 19_700,
+            last_distribution_ts: 0,
+            claimed_quote_today: 5_000,
+            distributed_quote_today: 0,
+            transfer_fees_today: 0,
+            carry_quote_today: 1_000,
+            locked_total_today: 200_000,
+            investor_pool_today: 1_000,
+            snapshot_ts: 0,
+            phase: DistributionPhase::Snapshot,
+            rolled_over_quote: 0,
+            force_closed: false,
+            fee_per_locked_unit_acc: 0,
+            pull_unsettled_scaled: 0,
+            pull_owed_quote: 0,
+            crank_reward_today: 0,
+            crank_reward_paid_today: 0,
+            crank_reward_per_page: 0,
+            collected_positions: 0,
+            dust_reserve_quote: 0,
+            page_cursor: 0,
+            day_opened: true,
+            day_closed: false,
+            bump: 0,
+        };
+        let checkpoint = |locked_snapshot| InvestorCheckpoint {
+            policy: Pubkey::default(),
+            investor_quote_ata: Pubkey::default(),
+            fee_per_locked_unit_acc: 0,
+            locked_snapshot,
+            owed_quote: 0,
+            claimed_total: 0,
+            bump: 0,
+        };
+        let mut a = checkpoint(150_000);
+        let mut b = checkpoint(50_000);
+        accrue_pull_pool(&mut progress).unwrap();
+        assert_eq!(progress.pull_reserve_quote(), 1_000);
+        assert_eq!(progress.distributed_quote_today, 1_000);
+        assert_eq!(progress.carry_quote_today, 0);
 
-        // Dust carry
-        let min_payout = 500u64;
-        assert!(share_capped < min_payout);
+        assert_eq!(
+            settle_pull_checkpoint(&mut progress, &mut a).unwrap(),
+            compute_investor_quote(1_000, 150_000, 200_000).unwrap()
+        );
+        // Settlements can never exceed what the day booked.
+        b.locked_snapshot = 10_000_000;
+        assert!(settle_pull_checkpoint(&mut progress, &mut b).is_err());
+        b.locked_snapshot = 50_000;
+        assert_eq!(settle_pull_checkpoint(&mut progress, &mut b).unwrap(), 250);
+        // A settled checkpoint owes nothing more until the next accrual.
+        assert_eq!(settle_pull_checkpoint(&mut progress, &mut a).unwrap(), 0);
+        assert_eq!((a.owed_quote, b.owed_quote), (750, 250));
+        assert_eq!(progress.pull_unsettled_scaled, 0);
+        assert_eq!(progress.pull_reserve_quote(), 1_000);
+    }
+
+    #[test]
+    fn payouts_independent_of_page_order() {
+        let p = policy(5_000, 0);
+        let locked = [400_000u64, 100_000, 250_000, 50_000];
+        let locked_total: u64 = locked.iter().sum();
+        let pool = compute_investor_pool(&p, 9_000, locked_total).unwrap();
+        assert_eq!(pool, 4_500);
+
+        let pay = |order: &[usize]| {
+            let mut paid = [0u64; 4];
+            let mut distributed = 0u64;
+            for &i in order {
+                let share = compute_investor_quote(pool, locked[i], locked_total)
+                    .unwrap()
+                    .min(pool - distributed);
+                paid[i] = share;
+                distributed += share;
+            }
+            paid
+        };
+        let forward = pay(&[0, 1, 2, 3]);
+        assert_eq!(forward, pay(&[3, 2, 1, 0]));
+        assert_eq!(forward, pay(&[2, 0, 3, 1]));
+        assert_eq!(forward, [2_250, 562, 1_406, 281]);
+        assert!(forward.iter().sum::<u64>() <= pool);
     }
 
     #[cfg(feature = "bankrun-test")]
     mod host_integration {
-        include!(concat!(env!("CARGO_MANIFEST_DIR"), "/../../tests/fee_router_integration.rs"));
+        include!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../../tests/fee_router_integration.rs"
+        ));
     }
 }
@@ -3,42 +3,294 @@
 //! Default known id is provided for convenience but not enforced on-chain.
 
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount};
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke_signed;
+use anchor_spl::token_interface::TokenAccount;
 
 /// Known Meteora DLMM v2 program id (devnet+mainnet). Not enforced on-chain.
 pub const DEFAULT_DLMM_PROGRAM_ID: &str = "cpamdpZCGKUy5JxQXB4dcpGPiikHawvSWAd6mEn1sGG";
 
-/// Minimal account bundle for future Meteora fee collection CPI wiring.
-#[allow(dead_code)]
-pub struct CollectQuoteFeesAccounts<'info, 'a> {
-    /// Meteora DLMM pool account (unchecked; ownership validated at runtime).
-    pub pool_ai: &'a UncheckedAccount<'info>,
-    /// Vault authority PDA that signs for treasury transfers.
-    pub vault_authority_pda: &'a UncheckedAccount<'info>,
-    /// Treasury quote token account owned by the vault authority.
-    pub treasury_quote_ata: &'a TokenAccount,
-    /// SPL token program.
-    pub token_program: &'a Program<'info, Token>,
-    /// Meteora DLMM program account.
-    pub meteora_program: &'a UncheckedAccount<'info>,
+/// Anchor discriminator of cp-amm `claim_position_fee` (sha256("global:claim_position_fee")[..8]).
+pub const CLAIM_POSITION_FEE_DISCRIMINATOR: [u8; 8] = [180, 38, 154, 17, 133, 33, 162, 211];
+
+/// Anchor discriminator of cp-amm `close_position` (sha256("global:close_position")[..8]).
+pub const CLOSE_POSITION_DISCRIMINATOR: [u8; 8] = [123, 134, 81, 0, 49, 68, 98, 98];
+
+/// Seed of the cp-amm event authority PDA required by `#[event_cpi]` instructions.
+pub const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+
+/// Anchor discriminator of the cp-amm `Pool` account (sha256("account:Pool")[..8]).
+pub const POOL_DISCRIMINATOR: [u8; 8] = [241, 154, 109, 4, 17, 177, 109, 188];
+
+/// Byte offset of `Pool::token_a_mint` (after the discriminator and `PoolFeesStruct`).
+pub const POOL_TOKEN_A_MINT_OFFSET: usize = 168;
+/// Byte offset of `Pool::token_b_mint`.
+pub const POOL_TOKEN_B_MINT_OFFSET: usize = 200;
+/// Byte offset of `Pool::collect_fee_mode`.
+pub const POOL_COLLECT_FEE_MODE_OFFSET: usize = 484;
+
+/// cp-amm `CollectFeeMode::BothToken`: fees accrue in both pool tokens.
+pub const COLLECT_FEE_MODE_BOTH_TOKEN: u8 = 0;
+/// cp-amm `CollectFeeMode::OnlyB`: fees accrue in token B only.
+pub const COLLECT_FEE_MODE_ONLY_B: u8 = 1;
+
+/// Fields of the cp-amm `Pool` account the fee router relies on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolState {
+    /// Pool token A mint.
+    pub token_a_mint: Pubkey,
+    /// Pool token B mint.
+    pub token_b_mint: Pubkey,
+    /// cp-amm `CollectFeeMode` as its `u8` repr.
+    pub collect_fee_mode: u8,
+}
+
+impl PoolState {
+    /// Decodes a cp-amm `Pool` account's data.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() > POOL_COLLECT_FEE_MODE_OFFSET && data[..8] == POOL_DISCRIMINATOR,
+            crate::FeeRouterError::QuoteOnlyViolation
+        );
+        let pubkey_at = |offset: usize| {
+            let bytes: [u8; 32] = data[offset..offset + 32].try_into().unwrap();
+            Pubkey::new_from_array(bytes)
+        };
+        Ok(Self {
+            token_a_mint: pubkey_at(POOL_TOKEN_A_MINT_OFFSET),
+            token_b_mint: pubkey_at(POOL_TOKEN_B_MINT_OFFSET),
+            collect_fee_mode: data[POOL_COLLECT_FEE_MODE_OFFSET],
+        })
+    }
+
+    /// The only mint fees accrue in, or `None` if the pool collects both tokens.
+    pub fn fee_mint(&self) -> Option<Pubkey> {
+        match self.collect_fee_mode {
+            COLLECT_FEE_MODE_ONLY_B => Some(self.token_b_mint),
+            _ => None,
+        }
+    }
+}
+
+/// Account bundle for the cp-amm `claim_position_fee` CPI.
+pub struct CollectQuoteFeesAccounts<'a, 'info> {
+    /// Meteora cp-amm program account.
+    pub meteora_program: &'a AccountInfo<'info>,
+    /// cp-amm pool authority PDA (owner of the pool vaults).
+    pub pool_authority: &'a AccountInfo<'info>,
+    /// Meteora cp-amm pool account.
+    pub pool: &'a AccountInfo<'info>,
+    /// Honorary position account.
+    pub position: &'a AccountInfo<'info>,
+    /// Token account holding the position NFT, owned by `owner_pda`.
+    pub position_nft_account: &'a AccountInfo<'info>,
+    /// Honorary position owner PDA; signs the claim.
+    pub owner_pda: &'a AccountInfo<'info>,
+    /// Pool token A vault.
+    pub token_a_vault: &'a AccountInfo<'info>,
+    /// Pool token B vault.
+    pub token_b_vault: &'a AccountInfo<'info>,
+    /// Pool token A mint.
+    pub token_a_mint: &'a AccountInfo<'info>,
+    /// Pool token B mint.
+    pub token_b_mint: &'a AccountInfo<'info>,
+    /// Treasury quote token account receiving claimed quote fees.
+    pub treasury_quote_ata: &'a mut InterfaceAccount<'info, TokenAccount>,
+    /// Treasury base token account; must stay untouched by the claim.
+    pub treasury_base_ata: &'a mut InterfaceAccount<'info, TokenAccount>,
+    /// cp-amm event authority PDA.
+    pub event_authority: &'a AccountInfo<'info>,
+    /// Token program of the quote mint.
+    pub quote_token_program: &'a AccountInfo<'info>,
+    /// Token program of the base mint.
+    pub base_token_program: &'a AccountInfo<'info>,
 }
 
-/// Collect quote-side fees from the Meteora DLMM pool into the treasury.
+/// Claims accrued fees of the honorary position into the treasury via cp-amm
+/// `claim_position_fee`, signed by the position owner PDA.
 ///
-/// MVP scaffolding: only enforces that the provided pool is owned by the supplied
-/// Meteora program account. The real CPI will be wired once integration details
-/// are finalized.
+/// Returns the quote amount collected, measured as the treasury quote balance delta.
+/// Fails with `QuoteOnlyViolation` if the claim moved any base tokens.
 pub fn collect_quote_fees(
-    pool_ai: &UncheckedAccount,
-    _vault_authority_pda: &UncheckedAccount,
-    _treasury_quote_ata: &TokenAccount,
-    _token_program: &Program<Token>,
-    meteora_program: &UncheckedAccount,
-) -> Result<()> {
+    accounts: CollectQuoteFeesAccounts,
+    quote_mint: &Pubkey,
+    owner_signer_seeds: &[&[u8]],
+) -> Result<u64> {
+    let program_id = accounts.meteora_program.key();
     require_keys_eq!(
-        *pool_ai.owner,
-        meteora_program.key(),
+        *accounts.pool.owner,
+        program_id,
         crate::FeeRouterError::QuoteOnlyViolation
     );
+    let (event_authority, _) = Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], &program_id);
+    require_keys_eq!(
+        accounts.event_authority.key(),
+        event_authority,
+        crate::FeeRouterError::ConstraintViolation
+    );
+
+    // Resolve which pool side is quote; the other one must be the treasury base account.
+    let quote_is_a = if accounts.token_a_mint.key() == *quote_mint {
+        true
+    } else if accounts.token_b_mint.key() == *quote_mint {
+        false
+    } else {
+        return Err(error!(crate::FeeRouterError::QuoteOnlyViolation));
+    };
+    let base_mint = if quote_is_a {
+        accounts.token_b_mint.key()
+    } else {
+        accounts.token_a_mint.key()
+    };
+    require_keys_eq!(
+        accounts.treasury_base_ata.mint,
+        base_mint,
+        crate::FeeRouterError::ConstraintViolation
+    );
+
+    let pre_quote = accounts.treasury_quote_ata.amount;
+    let pre_base = accounts.treasury_base_ata.amount;
+
+    let quote_info = accounts.treasury_quote_ata.to_account_info();
+    let base_info = accounts.treasury_base_ata.to_account_info();
+    let (token_a_account, token_b_account) = if quote_is_a {
+        (&quote_info, &base_info)
+    } else {
+        (&base_info, &quote_info)
+    };
+    let (token_a_program, token_b_program) = if quote_is_a {
+        (accounts.quote_token_program, accounts.base_token_program)
+    } else {
+        (accounts.base_token_program, accounts.quote_token_program)
+    };
+
+    let ix = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(accounts.pool_authority.key(), false),
+            AccountMeta::new_readonly(accounts.pool.key(), false),
+            AccountMeta::new(accounts.position.key(), false),
+            AccountMeta::new(token_a_account.key(), false),
+            AccountMeta::new(token_b_account.key(), false),
+            AccountMeta::new(accounts.token_a_vault.key(), false),
+            AccountMeta::new(accounts.token_b_vault.key(), false),
+            AccountMeta::new_readonly(accounts.token_a_mint.key(), false),
+            AccountMeta::new_readonly(accounts.token_b_mint.key(), false),
+            AccountMeta::new_readonly(accounts.position_nft_account.key(), false),
+            AccountMeta::new_readonly(accounts.owner_pda.key(), true),
+            AccountMeta::new_readonly(token_a_program.key(), false),
+            AccountMeta::new_readonly(token_b_program.key(), false),
+            AccountMeta::new_readonly(event_authority, false),
+            AccountMeta::new_readonly(program_id, false),
+        ],
+        data: CLAIM_POSITION_FEE_DISCRIMINATOR.to_vec(),
+    };
+    invoke_signed(
+        &ix,
+        &[
+            accounts.pool_authority.clone(),
+            accounts.pool.clone(),
+            accounts.position.clone(),
+            token_a_account.clone(),
+            token_b_account.clone(),
+            accounts.token_a_vault.clone(),
+            accounts.token_b_vault.clone(),
+            accounts.token_a_mint.clone(),
+            accounts.token_b_mint.clone(),
+            accounts.position_nft_account.clone(),
+            accounts.owner_pda.clone(),
+            token_a_program.clone(),
+            token_b_program.clone(),
+            accounts.event_authority.clone(),
+            accounts.meteora_program.clone(),
+        ],
+        &[owner_signer_seeds],
+    )?;
+
+    accounts.treasury_quote_ata.reload()?;
+    accounts.treasury_base_ata.reload()?;
+    require!(
+        accounts.treasury_base_ata.amount == pre_base,
+        crate::FeeRouterError::QuoteOnlyViolation
+    );
+    accounts
+        .treasury_quote_ata
+        .amount
+        .checked_sub(pre_quote)
+        .ok_or_else(|| error!(crate::FeeRouterError::ArithmeticOverflow))
+}
+
+/// Account bundle for the cp-amm `close_position` CPI.
+pub struct ClosePositionAccounts<'a, 'info> {
+    /// Meteora cp-amm program account.
+    pub meteora_program: &'a AccountInfo<'info>,
+    /// Position NFT mint.
+    pub position_nft_mint: &'a AccountInfo<'info>,
+    /// Token account holding the position NFT, owned by `owner_pda`.
+    pub position_nft_account: &'a AccountInfo<'info>,
+    /// Meteora cp-amm pool account.
+    pub pool: &'a AccountInfo<'info>,
+    /// Honorary position account.
+    pub position: &'a AccountInfo<'info>,
+    /// cp-amm pool authority PDA.
+    pub pool_authority: &'a AccountInfo<'info>,
+    /// Receives the rent of the closed position accounts.
+    pub rent_receiver: &'a AccountInfo<'info>,
+    /// Honorary position owner PDA; signs the close.
+    pub owner_pda: &'a AccountInfo<'info>,
+    /// Token program of the position NFT (Token-2022 on cp-amm).
+    pub nft_token_program: &'a AccountInfo<'info>,
+    /// cp-amm event authority PDA.
+    pub event_authority: &'a AccountInfo<'info>,
+}
+
+/// Closes the (empty) honorary position via cp-amm `close_position`, signed by the
+/// position owner PDA. cp-amm rejects positions with liquidity or unclaimed fees.
+pub fn close_position(accounts: ClosePositionAccounts, owner_signer_seeds: &[&[u8]]) -> Result<()> {
+    let program_id = accounts.meteora_program.key();
+    require_keys_eq!(
+        *accounts.position.owner,
+        program_id,
+        crate::FeeRouterError::ConstraintViolation
+    );
+    let (event_authority, _) = Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], &program_id);
+    require_keys_eq!(
+        accounts.event_authority.key(),
+        event_authority,
+        crate::FeeRouterError::ConstraintViolation
+    );
+
+    let ix = Instruction {
+        program_id,
+        accounts: vec![
+            AccountMeta::new(accounts.position_nft_mint.key(), false),
+            AccountMeta::new(accounts.position_nft_account.key(), false),
+            AccountMeta::new(accounts.pool.key(), false),
+            AccountMeta::new(accounts.position.key(), false),
+            AccountMeta::new_readonly(accounts.pool_authority.key(), false),
+            AccountMeta::new(accounts.rent_receiver.key(), false),
+            AccountMeta::new_readonly(accounts.owner_pda.key(), true),
+            AccountMeta::new_readonly(accounts.nft_token_program.key(), false),
+            AccountMeta::new_readonly(event_authority, false),
+            AccountMeta::new_readonly(program_id, false),
+        ],
+        data: CLOSE_POSITION_DISCRIMINATOR.to_vec(),
+    };
+    invoke_signed(
+        &ix,
+        &[
+            accounts.position_nft_mint.clone(),
+            accounts.position_nft_account.clone(),
+            accounts.pool.clone(),
+            accounts.position.clone(),
+            accounts.pool_authority.clone(),
+            accounts.rent_receiver.clone(),
+            accounts.owner_pda.clone(),
+            accounts.nft_token_program.clone(),
+            accounts.event_authority.clone(),
+            accounts.meteora_program.clone(),
+        ],
+        &[owner_signer_seeds],
+    )?;
     Ok(())
 }
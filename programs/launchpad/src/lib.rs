@@ -10,15 +10,16 @@ use keystone_common::errors::CommonError;
 use keystone_common::events::{ConfigUpdated, TreasuryMovement};
 use keystone_common::merkle::assert_merkle_proof;
 use keystone_common::time::{assert_not_expired, assert_started};
-#[cfg(not(target_arch = "bpf"))]
-use solana_security_txt::security_txt;
 
 pub mod state;
 
-use crate::state::{AuctionConfig, LaunchConfig, LaunchPricing, SaleState, SaleStatus};
+use crate::state::{
+    AuctionConfig, BuyerReceipt, BuyerTier, LaunchConfig, LaunchPricing, SaleState, SaleStatus,
+    RECEIPT_SEED,
+};
 
-#[cfg(not(target_arch = "bpf"))]
-security_txt! {
+#[cfg(all(not(target_arch = "bpf"), not(feature = "no-entrypoint")))]
+solana_security_txt::security_txt! {
     name: "Keystone Launchpad",
     project_url: "https://github.com/keystone-labs/keystone-vertex",
     contacts: "email:security@keystonelabs.xyz",
@@ -85,15 +86,27 @@ pub mod keystone_launchpad {
         assert_started(&clock, config.start_time)?;
         assert_not_expired(&clock, config.end_time)?;
 
+        let mut tier = BuyerTier::Public;
         if let Some(root) = config.whitelist_root {
             let buyer = ctx.accounts.buyer.key();
             let leaf = keccak::hashv(&[buyer.as_ref()]).to_bytes();
             let proof_vec = proof.clone().ok_or(LaunchError::WhitelistRequired)?;
             assert_merkle_proof(leaf, &proof_vec, root)?;
+            tier = BuyerTier::Whitelisted;
         }
 
         let mut state = ctx.accounts.sale_state.load_mut()?;
-        state.assert_allows_purchase(&ctx.accounts.buyer.key(), amount, config)?;
+        let receipt = &mut ctx.accounts.receipt;
+        if receipt.is_new() {
+            receipt.open(
+                &ctx.accounts.launch_config.key(),
+                &ctx.accounts.buyer.key(),
+                tier,
+                ctx.bumps.receipt,
+            );
+            state.record_new_buyer()?;
+        }
+        state.assert_allows_purchase(receipt, amount, config)?;
 
         let quote_amount = config.pricing.quote_buy(state.sold, amount)?;
         require!(quote_amount <= max_quote, LaunchError::SlippageExceeded);
@@ -106,7 +119,8 @@ pub mod keystone_launchpad {
         let cpi_ctx = CpiContext::new(ctx.accounts.token_program.to_account_info(), cpi_accounts);
         token::transfer(cpi_ctx, quote_amount)?;
 
-        state.record_purchase(&ctx.accounts.buyer.key(), amount, quote_amount, config)?;
+        state.record_purchase(amount, quote_amount)?;
+        receipt.record_purchase(amount, quote_amount)?;
         emit!(TreasuryMovement {
             program: crate::ID,
             entity: ctx.accounts.launch_config.key(),
@@ -173,7 +187,8 @@ pub mod keystone_launchpad {
             &binding,
         );
         token::transfer(cpi_ctx, quote_amount)?;
-        state.record_sell(&ctx.accounts.seller.key(), amount, quote_amount)?;
+        state.record_sell(amount, quote_amount)?;
+        ctx.accounts.receipt.record_sell(amount, quote_amount)?;
         Ok(())
     }
 
@@ -364,9 +379,18 @@ pub struct Buy<'info> {
         bump = launch_config.sale_state_bump,
     )]
     pub sale_state: AccountLoader<'info, SaleState>,
-    #[account(constraint = mint.key() == launch_config.mint)]
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        space = BuyerReceipt::SPACE,
+        seeds = [RECEIPT_SEED, launch_config.key().as_ref(), buyer.key().as_ref()],
+        bump
+    )]
+    pub receipt: Account<'info, BuyerReceipt>,
+    #[account(mut, constraint = mint.key() == launch_config.mint)]
     pub mint: Account<'info, Mint>,
     pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 /// Auction bid context.
@@ -424,7 +448,14 @@ pub struct Sell<'info> {
         bump = launch_config.sale_state_bump,
     )]
     pub sale_state: AccountLoader<'info, SaleState>,
-    #[account(constraint = mint.key() == launch_config.mint)]
+    #[account(
+        mut,
+        seeds = [RECEIPT_SEED, launch_config.key().as_ref(), seller.key().as_ref()],
+        bump = receipt.bump,
+        constraint = receipt.buyer == seller.key() @ LaunchError::UnknownBuyer,
+    )]
+    pub receipt: Account<'info, BuyerReceipt>,
+    #[account(mut, constraint = mint.key() == launch_config.mint)]
     pub mint: Account<'info, Mint>,
     pub token_program: Program<'info, Token>,
 }
@@ -535,3 +566,11 @@ pub enum LaunchError {
     #[msg("Buyer record not found")]
     UnknownBuyer,
 }
+
+#[cfg(all(test, not(target_arch = "bpf"), feature = "bankrun-test"))]
+mod host_integration {
+    include!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../tests/launchpad_integration.rs"
+    ));
+}
@@ -3,11 +3,14 @@
 use super::LaunchError;
 use anchor_lang::prelude::*;
 use anchor_lang::solana_program::keccak;
-use keystone_common::curve::{CurveConfig, CurveKind, LinearCurve, PricingCurve};
+use keystone_common::curve::{
+    assert_quotable, CurveConfig, CurveKind, ExponentialCurve, LinearCurve, PricingCurve,
+    SigmoidCurve,
+};
 use keystone_common::errors::CommonError;
 
-/// Maximum number of buyers tracked for wallet caps in base state.
-pub const MAX_TRACKED_BUYERS: usize = 64;
+/// Seed prefix for per-buyer receipt PDAs `(b"receipt", launch_config, buyer)`.
+pub const RECEIPT_SEED: &[u8] = b"receipt";
 
 /// Auction configuration covering English & Dutch auctions.
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
@@ -45,6 +48,17 @@ pub enum LaunchPricing {
 }
 
 impl LaunchPricing {
+    /// Rejects bonding curves that cannot quote every trade up to their max supply.
+    pub fn assert_valid(&self) -> Result<()> {
+        if let LaunchPricing::BondingCurve { curve } = self {
+            quote_curve(curve, |calc| {
+                assert_quotable(calc, curve.max_supply)
+                    .map_err(|_| CommonError::ConstraintViolation.into())
+            })?;
+        }
+        Ok(())
+    }
+
     /// Quotes price for requested amount.
     pub fn quote_buy(&self, sold: u64, amount: u64) -> Result<u64> {
         match self {
@@ -53,13 +67,7 @@ impl LaunchPricing {
                 .and_then(|_| price.checked_mul(amount))
                 .ok_or(CommonError::ArithmeticOverflow.into()),
             LaunchPricing::BondingCurve { curve } => {
-                if matches!(curve.kind, CurveKind::Linear) {
-                    let curve_calc =
-                        LinearCurve::new(curve).map_err(|_| CommonError::ConstraintViolation)?;
-                    Ok(curve_calc.quote_buy(sold, amount)?.quote_amount)
-                } else {
-                    Err(LaunchError::UnsupportedCurve.into())
-                }
+                Ok(quote_curve(curve, |calc| calc.quote_buy(sold, amount))?.quote_amount)
             }
             LaunchPricing::Auction { .. } => Err(LaunchError::AuctionBidRequired.into()),
         }
@@ -76,15 +84,28 @@ impl LaunchPricing {
     /// Quotes sell amount for bonding curves.
     pub fn quote_sell(&self, sold: u64, amount: u64) -> Result<u64> {
         match self {
-            LaunchPricing::BondingCurve { curve } if matches!(curve.kind, CurveKind::Linear) => {
-                let calc = LinearCurve::new(curve).map_err(|_| CommonError::ConstraintViolation)?;
-                Ok(calc.quote_sell(sold, amount)?.quote_amount)
+            LaunchPricing::BondingCurve { curve } => {
+                Ok(quote_curve(curve, |calc| calc.quote_sell(sold, amount))?.quote_amount)
             }
             _ => Err(LaunchError::SellOnlyCurve.into()),
         }
     }
 }
 
+/// Runs `quote` against the calculator for the curve's kind.
+fn quote_curve<T>(
+    curve: &CurveConfig,
+    quote: impl Fn(&dyn PricingCurve) -> Result<T>,
+) -> Result<T> {
+    let invalid = |_| CommonError::ConstraintViolation;
+    match curve.kind {
+        CurveKind::Linear => quote(&LinearCurve::new(curve).map_err(invalid)?),
+        CurveKind::Exponential => quote(&ExponentialCurve::new(curve).map_err(invalid)?),
+        CurveKind::Sigmoid => quote(&SigmoidCurve::new(curve).map_err(invalid)?),
+        CurveKind::Fixed => Err(LaunchError::UnsupportedCurve.into()),
+    }
+}
+
 /// Primary configuration account for a sale.
 #[account]
 pub struct LaunchConfig {
@@ -136,6 +157,7 @@ impl LaunchConfig {
             args.end_time > args.start_time,
             CommonError::TimestampInvalid
         );
+        args.pricing.assert_valid()?;
         Ok(Self {
             authority: *authority,
             treasury_vault: *treasury_vault,
@@ -204,14 +226,96 @@ impl keystone_common::authority::HasAuthority for LaunchConfig {
     }
 }
 
-/// Tracks per-wallet purchases to enforce wallet caps.
-#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
-#[repr(C)]
-pub struct BuyerContribution {
-    /// Wallet pubkey tracked.
+/// How a buyer entered the sale.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BuyerTier {
+    /// Open sale without a whitelist.
+    #[default]
+    Public,
+    /// Purchase admitted through a whitelist proof.
+    Whitelisted,
+}
+
+/// Per-buyer receipt PDA `(b"receipt", launch_config, buyer)` tracking wallet totals.
+#[account]
+#[derive(Debug, Default)]
+pub struct BuyerReceipt {
+    /// Launch configuration the receipt belongs to.
+    pub launch: Pubkey,
+    /// Buyer wallet.
     pub buyer: Pubkey,
-    /// Total purchased amount.
+    /// Tokens currently held against the wallet cap (purchases minus sells).
     pub purchased: u64,
+    /// Net quote paid (purchases minus sell proceeds, floored at zero).
+    pub quote_paid: u64,
+    /// Tier the buyer entered the sale with.
+    pub tier: BuyerTier,
+    /// Tokens already delivered to the buyer's token account.
+    pub claimed: u64,
+    /// PDA bump.
+    pub bump: u8,
+}
+
+impl BuyerReceipt {
+    /// Account space including discriminator.
+    pub const SPACE: usize = 8 + 32 + 32 + 8 + 8 + 1 + 8 + 1;
+
+    /// Returns true until the receipt has been bound to a buyer.
+    pub fn is_new(&self) -> bool {
+        self.buyer == Pubkey::default()
+    }
+
+    /// Binds a freshly created receipt to its launch and buyer.
+    pub fn open(&mut self, launch: &Pubkey, buyer: &Pubkey, tier: BuyerTier, bump: u8) {
+        self.launch = *launch;
+        self.buyer = *buyer;
+        self.tier = tier;
+        self.bump = bump;
+    }
+
+    /// Ensures the purchase keeps the wallet within its cap.
+    pub fn assert_within_wallet_cap(&self, amount: u64, config: &LaunchConfig) -> Result<()> {
+        if config.wallet_cap > 0 {
+            let updated = self
+                .purchased
+                .checked_add(amount)
+                .ok_or(CommonError::ArithmeticOverflow)?;
+            require!(updated <= config.wallet_cap, LaunchError::WalletCapExceeded);
+        }
+        Ok(())
+    }
+
+    /// Records a purchase; `buy` mints the tokens to the buyer in the same instruction,
+    /// so they are marked delivered straight away.
+    pub fn record_purchase(&mut self, amount: u64, quote_amount: u64) -> Result<()> {
+        self.purchased = self
+            .purchased
+            .checked_add(amount)
+            .ok_or(CommonError::ArithmeticOverflow)?;
+        self.claimed = self
+            .claimed
+            .checked_add(amount)
+            .ok_or(CommonError::ArithmeticOverflow)?;
+        self.quote_paid = self
+            .quote_paid
+            .checked_add(quote_amount)
+            .ok_or(CommonError::ArithmeticOverflow)?;
+        Ok(())
+    }
+
+    /// Records a sellback, releasing wallet cap headroom.
+    pub fn record_sell(&mut self, amount: u64, quote_amount: u64) -> Result<()> {
+        self.purchased = self
+            .purchased
+            .checked_sub(amount)
+            .ok_or(LaunchError::SellTooLarge)?;
+        self.claimed = self
+            .claimed
+            .checked_sub(amount)
+            .ok_or(LaunchError::SellTooLarge)?;
+        self.quote_paid = self.quote_paid.saturating_sub(quote_amount);
+        Ok(())
+    }
 }
 
 /// Sale lifecycle states.
@@ -242,10 +346,8 @@ pub struct SaleState {
     pub highest_bidder: Pubkey,
     /// Auction end override (anti-snipe extensions).
     pub auction_end: i64,
-    /// Buyer tracker array.
-    pub buyers: [BuyerContribution; MAX_TRACKED_BUYERS],
-    /// Number of buyer entries used.
-    pub buyer_count: u16,
+    /// Number of buyer receipts opened.
+    pub buyer_count: u32,
 }
 
 impl Default for SaleState {
@@ -257,7 +359,6 @@ impl Default for SaleState {
             highest_bid: 0,
             highest_bidder: Pubkey::default(),
             auction_end: 0,
-            buyers: [BuyerContribution::default(); MAX_TRACKED_BUYERS],
             buyer_count: 0,
         }
     }
@@ -270,7 +371,7 @@ impl SaleState {
     /// Ensures sale allows requested purchase.
     pub fn assert_allows_purchase(
         &self,
-        buyer: &Pubkey,
+        receipt: &BuyerReceipt,
         amount: u64,
         config: &LaunchConfig,
     ) -> Result<()> {
@@ -280,27 +381,20 @@ impl SaleState {
             .checked_add(amount)
             .ok_or(CommonError::ArithmeticOverflow)?;
         require!(new_sold <= config.global_cap, LaunchError::CapExceeded);
-        if config.wallet_cap > 0 {
-            let existing = self
-                .find_buyer(*buyer)
-                .map(|(_, contrib)| contrib.purchased)
-                .unwrap_or(0);
-            let updated = existing
-                .checked_add(amount)
-                .ok_or(CommonError::ArithmeticOverflow)?;
-            require!(updated <= config.wallet_cap, LaunchError::WalletCapExceeded);
-        }
+        receipt.assert_within_wallet_cap(amount, config)
+    }
+
+    /// Counts a newly opened buyer receipt.
+    pub fn record_new_buyer(&mut self) -> Result<()> {
+        self.buyer_count = self
+            .buyer_count
+            .checked_add(1)
+            .ok_or(CommonError::ArithmeticOverflow)?;
         Ok(())
     }
 
-    /// Records purchase, updating per-wallet totals.
-    pub fn record_purchase(
-        &mut self,
-        buyer: &Pubkey,
-        amount: u64,
-        quote_amount: u64,
-        config: &LaunchConfig,
-    ) -> Result<()> {
+    /// Records purchase into the aggregate totals.
+    pub fn record_purchase(&mut self, amount: u64, quote_amount: u64) -> Result<()> {
         self.sold = self
             .sold
             .checked_add(amount)
@@ -309,7 +403,6 @@ impl SaleState {
             .proceeds
             .checked_add(quote_amount)
             .ok_or(CommonError::ArithmeticOverflow)?;
-        self.upsert_buyer(buyer, amount, config)?;
         if self.status == SaleStatus::Pending {
             self.status = SaleStatus::Active;
         }
@@ -317,7 +410,7 @@ impl SaleState {
     }
 
     /// Records a sellback into curve inventory.
-    pub fn record_sell(&mut self, buyer: &Pubkey, amount: u64, quote_amount: u64) -> Result<()> {
+    pub fn record_sell(&mut self, amount: u64, quote_amount: u64) -> Result<()> {
         self.sold = self
             .sold
             .checked_sub(amount)
@@ -326,15 +419,6 @@ impl SaleState {
             .proceeds
             .checked_sub(quote_amount)
             .ok_or(CommonError::ArithmeticOverflow)?;
-        if let Some((idx, entry)) = self.find_buyer(*buyer) {
-            let new_total = entry
-                .purchased
-                .checked_sub(amount)
-                .ok_or(LaunchError::SellTooLarge)?;
-            self.buyers[idx].purchased = new_total;
-        } else {
-            return Err(LaunchError::UnknownBuyer.into());
-        }
         Ok(())
     }
 
@@ -414,48 +498,6 @@ impl SaleState {
         }
         Ok(())
     }
-
-    fn find_buyer(&self, buyer: Pubkey) -> Option<(usize, &BuyerContribution)> {
-        (0..self.buyer_count as usize)
-            .find(|&i| self.buyers[i].buyer == buyer)
-            .map(|idx| (idx, &self.buyers[idx]))
-    }
-
-    fn upsert_buyer(&mut self, buyer: &Pubkey, amount: u64, config: &LaunchConfig) -> Result<()> {
-        if config.wallet_cap == 0 {
-            return Ok(());
-        }
-        if let Some((idx, entry)) = self.find_buyer(*buyer) {
-            let new_total = entry
-                .purchased
-                .checked_add(amount)
-                .ok_or(CommonError::ArithmeticOverflow)?;
-            self.buyers[idx].purchased = new_total;
-            return Ok(());
-        }
-        let idx = self.buyer_count as usize;
-        require!(
-            idx < MAX_TRACKED_BUYERS,
-            SaleStateError::BuyerCapacityExceeded
-        );
-        self.buyers[idx] = BuyerContribution {
-            buyer: *buyer,
-            purchased: amount,
-        };
-        self.buyer_count = self
-            .buyer_count
-            .checked_add(1)
-            .ok_or(CommonError::ArithmeticOverflow)?;
-        Ok(())
-    }
-}
-
-/// Custom sale errors.
-#[error_code]
-pub enum SaleStateError {
-    /// Buyer tracking is full.
-    #[msg("Buyer tracking capacity exceeded")]
-    BuyerCapacityExceeded,
 }
 
 #[cfg(all(test, not(target_arch = "bpf")))]
@@ -482,9 +524,78 @@ mod tests {
             initialized: true,
         };
         let mut state = SaleState::default();
-        let buyer = Pubkey::new_unique();
-        state.assert_allows_purchase(&buyer, 5, &config).unwrap();
-        state.record_purchase(&buyer, 5, 5, &config).unwrap();
-        assert!(state.assert_allows_purchase(&buyer, 6, &config).is_err());
+        let mut receipt = BuyerReceipt::default();
+        state.assert_allows_purchase(&receipt, 5, &config).unwrap();
+        state.record_purchase(5, 5).unwrap();
+        receipt.record_purchase(5, 5).unwrap();
+        assert!(state.assert_allows_purchase(&receipt, 6, &config).is_err());
+        receipt.record_sell(2, 2).unwrap();
+        state.record_sell(2, 2).unwrap();
+        state.assert_allows_purchase(&receipt, 6, &config).unwrap();
+    }
+
+    #[test]
+    fn receipts_scale_past_former_buyer_limit() {
+        let config = LaunchConfig {
+            authority: Pubkey::new_unique(),
+            treasury_vault: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            pricing: LaunchPricing::Fixed { price: 1 },
+            global_cap: 1_000,
+            wallet_cap: 5,
+            start_time: 0,
+            end_time: 10,
+            whitelist_root: None,
+            treasury_bump: 0,
+            config_bump: 0,
+            sale_state_bump: 0,
+            initialized: true,
+        };
+        let launch = Pubkey::new_unique();
+        let mut state = SaleState::default();
+        for _ in 0..100 {
+            let mut receipt = BuyerReceipt::default();
+            assert!(receipt.is_new());
+            receipt.open(&launch, &Pubkey::new_unique(), BuyerTier::Public, 255);
+            state.record_new_buyer().unwrap();
+            state.assert_allows_purchase(&receipt, 5, &config).unwrap();
+            state.record_purchase(5, 5).unwrap();
+            receipt.record_purchase(5, 5).unwrap();
+            assert!(state.assert_allows_purchase(&receipt, 1, &config).is_err());
+        }
+        assert_eq!(state.buyer_count, 100);
+        assert_eq!(state.sold, 500);
+        assert_eq!(state.proceeds, 500);
+    }
+
+    #[test]
+    fn init_rejects_curves_that_overflow_inside_supply() {
+        let args = |max_supply| crate::InitLaunchArgs {
+            pricing: LaunchPricing::BondingCurve {
+                curve: CurveConfig {
+                    kind: CurveKind::Exponential,
+                    base_price: 1_000_000_000,
+                    k: 1,
+                    x0: 0,
+                    max_supply,
+                    fee_config: keystone_common::fees::FeeConfig {
+                        numerator: 0,
+                        denominator: 10_000,
+                        fee_vault: Pubkey::default(),
+                    },
+                },
+            },
+            global_cap: 1_000,
+            wallet_cap: 0,
+            start_time: 0,
+            end_time: 10,
+            whitelist_root: None,
+        };
+        let key = Pubkey::new_unique();
+        assert_eq!(
+            LaunchConfig::try_from_args(&key, &key, &key, args(1_000_000_000_000)).err(),
+            Some(CommonError::ConstraintViolation.into())
+        );
+        LaunchConfig::try_from_args(&key, &key, &key, args(1_000_000_000)).unwrap();
     }
 }
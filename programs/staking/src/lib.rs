@@ -5,11 +5,8 @@
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
 use keystone_common::errors::CommonError;
-#[cfg(not(target_arch = "bpf"))]
-use solana_security_txt::security_txt;
-
-#[cfg(not(target_arch = "bpf"))]
-security_txt! {
+#[cfg(all(not(target_arch = "bpf"), not(feature = "no-entrypoint")))]
+solana_security_txt::security_txt! {
     name: "Keystone Staking",
     project_url: "https://github.com/keystone-labs/keystone-vertex",
     contacts: "email:security@keystonelabs.xyz",
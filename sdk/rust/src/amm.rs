@@ -1,8 +1,8 @@
 //! AMM instruction builders.
 
-use anchor_lang::InstructionData;
+use anchor_lang::{InstructionData, ToAccountMetas};
 use keystone_amm_cp::{accounts, instruction};
-use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
 
 /// Init pool instruction helper.
 pub fn init_pool(
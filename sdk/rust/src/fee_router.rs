@@ -0,0 +1,424 @@
+//! Fee router instruction builders, PDA helpers and account fetchers.
+
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use anyhow::{Context, Result};
+use keystone_fee_router::{
+    accounts, instruction, position_index_seed, ClaimMode, CrankArgs, DistributionHistory,
+    InitPolicyArgs, Policy, PolicyUpdateArgs, Progress, RemainderRecipient, CHECKPOINT_SEED,
+    DUST_LEDGER_SEED, FEE_POS_OWNER_SEED, HISTORY_SEED, PAYOUT_BITMAP_SEED, POLICY_SEED,
+    POSITION_SEED, PROGRESS_SEED, VAULT_SEED,
+};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+/// `Policy` PDA of `cp_pool`.
+pub fn policy_pda(program_id: &Pubkey, cp_pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[POLICY_SEED, cp_pool.as_ref()], program_id)
+}
+
+/// `Progress` PDA of `cp_pool`.
+pub fn progress_pda(program_id: &Pubkey, cp_pool: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PROGRESS_SEED, cp_pool.as_ref()], program_id)
+}
+
+/// Vault authority PDA of `policy` (owns the treasury ATAs).
+pub fn vault_authority_pda(program_id: &Pubkey, policy: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[VAULT_SEED, policy.as_ref()], program_id)
+}
+
+/// Owner PDA of honorary position `index` (position 0 has no index seed).
+pub fn position_owner_pda(program_id: &Pubkey, policy: &Pubkey, index: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            VAULT_SEED,
+            policy.as_ref(),
+            FEE_POS_OWNER_SEED,
+            position_index_seed(&index),
+        ],
+        program_id,
+    )
+}
+
+/// `HonoraryPosition` registry PDA of position `index` (position 0 has no index seed).
+pub fn honorary_position_pda(program_id: &Pubkey, policy: &Pubkey, index: u8) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[POSITION_SEED, policy.as_ref(), position_index_seed(&index)],
+        program_id,
+    )
+}
+
+/// `PayoutBitmap` PDA of `policy`.
+pub fn payout_bitmap_pda(program_id: &Pubkey, policy: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PAYOUT_BITMAP_SEED, policy.as_ref()], program_id)
+}
+
+/// `DistributionHistory` PDA of `policy`.
+pub fn distribution_history_pda(program_id: &Pubkey, policy: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[HISTORY_SEED, policy.as_ref()], program_id)
+}
+
+/// `DustLedger` PDA of an investor quote ATA.
+pub fn dust_ledger_pda(
+    program_id: &Pubkey,
+    policy: &Pubkey,
+    investor_quote_ata: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            DUST_LEDGER_SEED,
+            policy.as_ref(),
+            investor_quote_ata.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+/// `InvestorCheckpoint` PDA of an investor quote ATA (pull mode).
+pub fn checkpoint_pda(
+    program_id: &Pubkey,
+    policy: &Pubkey,
+    investor_quote_ata: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[
+            CHECKPOINT_SEED,
+            policy.as_ref(),
+            investor_quote_ata.as_ref(),
+        ],
+        program_id,
+    )
+}
+
+/// One investor of a crank page.
+#[derive(Clone, Copy, Debug)]
+pub struct PageEntry {
+    /// Investor quote ATA.
+    pub investor_quote_ata: Pubkey,
+    /// Investor stream account.
+    pub stream: Pubkey,
+}
+
+/// Builds `init_policy` instruction; the remainder recipients' quote ATAs are
+/// appended to the accounts in split order.
+pub fn init_policy(
+    program_id: Pubkey,
+    accounts: accounts::InitPolicy,
+    args: InitPolicyArgs,
+) -> Instruction {
+    let mut metas = accounts.to_account_metas(None);
+    metas.extend(recipient_metas(&args.remainder_recipients));
+    Instruction {
+        program_id,
+        accounts: metas,
+        data: instruction::InitPolicy { args }.data(),
+    }
+}
+
+/// Builds `propose_policy_update` instruction; the remainder recipients' quote ATAs
+/// are appended to the accounts in split order.
+pub fn propose_policy_update(
+    program_id: Pubkey,
+    accounts: accounts::ProposePolicyUpdate,
+    args: PolicyUpdateArgs,
+) -> Instruction {
+    let mut metas = accounts.to_account_metas(None);
+    metas.extend(recipient_metas(&args.remainder_recipients));
+    Instruction {
+        program_id,
+        accounts: metas,
+        data: instruction::ProposePolicyUpdate { args }.data(),
+    }
+}
+
+fn recipient_metas(recipients: &[RemainderRecipient]) -> Vec<AccountMeta> {
+    recipients
+        .iter()
+        .map(|recipient| AccountMeta::new_readonly(recipient.quote_ata, false))
+        .collect()
+}
+
+/// Builds `init_honorary_position` instruction; the position index is the
+/// policy's current `position_count`.
+pub fn init_honorary_position(
+    program_id: Pubkey,
+    accounts: accounts::InitHonoraryPosition,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: accounts.to_account_metas(None),
+        data: instruction::InitHonoraryPosition {}.data(),
+    }
+}
+
+/// Remaining accounts of a crank page: a (quote ATA, stream, entry PDA) triple per
+/// entry after the first, the PDA being the dust ledger in push mode and the
+/// checkpoint in pull mode, then on the last page the remainder split recipients'
+/// quote ATAs in policy order. A page holds at most `Policy::max_page_size` entries.
+pub fn page_remaining_accounts(
+    program_id: &Pubkey,
+    policy: &Pubkey,
+    claim_mode: ClaimMode,
+    extra_entries: &[PageEntry],
+    remainder_recipients: &[Pubkey],
+) -> Vec<AccountMeta> {
+    let mut metas = Vec::with_capacity(extra_entries.len() * 3 + remainder_recipients.len());
+    for entry in extra_entries {
+        let (entry_pda, _) = match claim_mode {
+            ClaimMode::Push => dust_ledger_pda(program_id, policy, &entry.investor_quote_ata),
+            ClaimMode::Pull => checkpoint_pda(program_id, policy, &entry.investor_quote_ata),
+        };
+        metas.push(AccountMeta::new(entry.investor_quote_ata, false));
+        metas.push(AccountMeta::new_readonly(entry.stream, false));
+        metas.push(AccountMeta::new(entry_pda, false));
+    }
+    metas.extend(
+        remainder_recipients
+            .iter()
+            .map(|quote_ata| AccountMeta::new(*quote_ata, false)),
+    );
+    metas
+}
+
+/// Builds `crank_distribute` instruction. `accounts` carries the page's first
+/// entry (with its checkpoint on pull-mode snapshot pages, its dust ledger on
+/// push-mode pages and the payout bitmap on payout pages, `None` otherwise);
+/// `extra_entries` and
+/// `remainder_recipients` (last page only) are packed by
+/// [`page_remaining_accounts`]. `args.proofs` covers every entry of the page.
+pub fn crank_distribute(
+    program_id: Pubkey,
+    accounts: accounts::CrankDistribute,
+    args: CrankArgs,
+    claim_mode: ClaimMode,
+    extra_entries: &[PageEntry],
+    remainder_recipients: &[Pubkey],
+) -> Instruction {
+    let policy = accounts.policy;
+    let mut metas = accounts.to_account_metas(None);
+    metas.extend(page_remaining_accounts(
+        &program_id,
+        &policy,
+        claim_mode,
+        extra_entries,
+        remainder_recipients,
+    ));
+    Instruction {
+        program_id,
+        accounts: metas,
+        data: instruction::CrankDistribute { args }.data(),
+    }
+}
+
+/// Builds `pause_policy` instruction (signed by the guardian or the authority).
+pub fn pause_policy(program_id: Pubkey, accounts: accounts::SetPaused) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: accounts.to_account_metas(None),
+        data: instruction::PausePolicy {}.data(),
+    }
+}
+
+/// Builds `unpause_policy` instruction (signed by the guardian).
+pub fn unpause_policy(program_id: Pubkey, accounts: accounts::SetPaused) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: accounts.to_account_metas(None),
+        data: instruction::UnpausePolicy {}.data(),
+    }
+}
+
+/// Fetches and decodes a `Policy` account.
+pub fn fetch_policy(rpc: &RpcClient, policy: &Pubkey) -> Result<Policy> {
+    fetch(rpc, policy)
+}
+
+/// Fetches and decodes the `Progress` account of `cp_pool`.
+pub fn fetch_progress(rpc: &RpcClient, program_id: &Pubkey, cp_pool: &Pubkey) -> Result<Progress> {
+    fetch(rpc, &progress_pda(program_id, cp_pool).0)
+}
+
+/// Fetches and decodes the `DistributionHistory` account of `policy`.
+pub fn fetch_distribution_history(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    policy: &Pubkey,
+) -> Result<DistributionHistory> {
+    fetch(rpc, &distribution_history_pda(program_id, policy).0)
+}
+
+fn fetch<T: AccountDeserialize>(rpc: &RpcClient, address: &Pubkey) -> Result<T> {
+    let data = rpc
+        .get_account_data(address)
+        .with_context(|| format!("fetching account {address}"))?;
+    T::try_deserialize(&mut data.as_slice()).with_context(|| format!("decoding account {address}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_lang::Discriminator;
+    use keystone_fee_router::DistributionPhase;
+
+    fn crank_accounts(policy: Pubkey) -> accounts::CrankDistribute {
+        let key = Pubkey::new_unique;
+        accounts::CrankDistribute {
+            cp_program: key(),
+            cp_pool: key(),
+            cp_pool_authority: key(),
+            policy,
+            honorary_position: key(),
+            owner_pda: key(),
+            cp_position: key(),
+            position_nft_account: key(),
+            token_a_vault: key(),
+            token_b_vault: key(),
+            token_a_mint: key(),
+            token_b_mint: key(),
+            event_authority: key(),
+            progress: key(),
+            payout_bitmap: Some(key()),
+            history: key(),
+            payer: key(),
+            payer_quote_ata: None,
+            vault_authority: key(),
+            treasury_quote_ata: key(),
+            treasury_base_ata: key(),
+            creator_quote_ata: key(),
+            investor_quote_ata: key(),
+            stream: key(),
+            dust_ledger: Some(key()),
+            checkpoint: None,
+            quote_mint: key(),
+            token_program: key(),
+            base_token_program: key(),
+            system_program: key(),
+        }
+    }
+
+    #[test]
+    fn position_pdas_omit_the_index_seed_for_position_zero() {
+        let program_id = keystone_fee_router::ID;
+        let policy = Pubkey::new_unique();
+        let (legacy, _) =
+            Pubkey::find_program_address(&[POSITION_SEED, policy.as_ref()], &program_id);
+        assert_eq!(honorary_position_pda(&program_id, &policy, 0).0, legacy);
+        let (second, _) =
+            Pubkey::find_program_address(&[POSITION_SEED, policy.as_ref(), &[1]], &program_id);
+        assert_eq!(honorary_position_pda(&program_id, &policy, 1).0, second);
+        let (owner, _) = Pubkey::find_program_address(
+            &[VAULT_SEED, policy.as_ref(), FEE_POS_OWNER_SEED],
+            &program_id,
+        );
+        assert_eq!(position_owner_pda(&program_id, &policy, 0).0, owner);
+        assert_ne!(position_owner_pda(&program_id, &policy, 1).0, owner);
+    }
+
+    #[test]
+    fn page_entries_carry_the_claim_mode_pda() {
+        let program_id = keystone_fee_router::ID;
+        let policy = Pubkey::new_unique();
+        let entry = PageEntry {
+            investor_quote_ata: Pubkey::new_unique(),
+            stream: Pubkey::new_unique(),
+        };
+        let recipient = Pubkey::new_unique();
+        for (mode, (entry_pda, _)) in [
+            (
+                ClaimMode::Push,
+                dust_ledger_pda(&program_id, &policy, &entry.investor_quote_ata),
+            ),
+            (
+                ClaimMode::Pull,
+                checkpoint_pda(&program_id, &policy, &entry.investor_quote_ata),
+            ),
+        ] {
+            let metas = page_remaining_accounts(&program_id, &policy, mode, &[entry], &[recipient]);
+            assert_eq!(
+                metas,
+                vec![
+                    AccountMeta::new(entry.investor_quote_ata, false),
+                    AccountMeta::new_readonly(entry.stream, false),
+                    AccountMeta::new(entry_pda, false),
+                    AccountMeta::new(recipient, false),
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn crank_distribute_appends_the_page_after_the_named_accounts() {
+        let program_id = keystone_fee_router::ID;
+        let policy = Pubkey::new_unique();
+        let accounts = crank_accounts(policy);
+        let named = accounts.to_account_metas(None);
+        let entry = PageEntry {
+            investor_quote_ata: Pubkey::new_unique(),
+            stream: Pubkey::new_unique(),
+        };
+        let ix = crank_distribute(
+            program_id,
+            accounts,
+            CrankArgs {
+                page_cursor: 0,
+                is_last_page: true,
+                phase: DistributionPhase::Payout,
+                position_index: 0,
+                proofs: vec![vec![], vec![]],
+            },
+            ClaimMode::Push,
+            &[entry],
+            &[],
+        );
+        assert_eq!(ix.program_id, program_id);
+        assert_eq!(ix.accounts[..named.len()], named[..]);
+        assert_eq!(
+            ix.accounts[named.len()..],
+            page_remaining_accounts(&program_id, &policy, ClaimMode::Push, &[entry], &[])[..]
+        );
+        assert_eq!(&ix.data[..8], instruction::CrankDistribute::DISCRIMINATOR);
+    }
+
+    #[test]
+    fn policy_updates_list_the_recipient_accounts() {
+        let recipients = vec![
+            RemainderRecipient {
+                quote_ata: Pubkey::new_unique(),
+                bps: 4_000,
+            },
+            RemainderRecipient {
+                quote_ata: Pubkey::new_unique(),
+                bps: 6_000,
+            },
+        ];
+        let ix = propose_policy_update(
+            keystone_fee_router::ID,
+            accounts::ProposePolicyUpdate {
+                authority: Pubkey::new_unique(),
+                policy: Pubkey::new_unique(),
+                creator_quote_ata: Pubkey::new_unique(),
+            },
+            PolicyUpdateArgs {
+                y0_total: 1,
+                investor_fee_share_bps: 5_000,
+                daily_cap_quote: 0,
+                min_payout_lamports: 0,
+                crank_reward_bps: 0,
+                crank_reward_cap_quote: 0,
+                force_close_grace_secs: 0,
+                roll_forced_carry: false,
+                remainder_recipients: recipients.clone(),
+            },
+        );
+        let tail: Vec<_> = ix.accounts[ix.accounts.len() - 2..].to_vec();
+        assert_eq!(
+            tail,
+            recipients
+                .iter()
+                .map(|recipient| AccountMeta::new_readonly(recipient.quote_ata, false))
+                .collect::<Vec<_>>()
+        );
+    }
+}
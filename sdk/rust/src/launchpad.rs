@@ -1,14 +1,23 @@
 //! Launchpad instruction builders.
 
-use anchor_lang::InstructionData;
-use keystone_launchpad::{accounts, instruction};
-use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use keystone_launchpad::state::RECEIPT_SEED;
+use keystone_launchpad::{accounts, instruction, InitLaunchArgs};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+/// `BuyerReceipt` PDA of `buyer` in `launch_config`.
+pub fn receipt_pda(program_id: &Pubkey, launch_config: &Pubkey, buyer: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[RECEIPT_SEED, launch_config.as_ref(), buyer.as_ref()],
+        program_id,
+    )
+}
 
 /// Builds `init_launch` instruction.
 pub fn init_launch(
     program_id: Pubkey,
     accounts: accounts::InitLaunch,
-    args: instruction::InitLaunchArgs,
+    args: InitLaunchArgs,
 ) -> Instruction {
     Instruction {
         program_id,
@@ -52,13 +61,30 @@ pub fn bid(
 }
 
 /// Builds auction settlement instruction.
-pub fn settle(
-    program_id: Pubkey,
-    accounts: accounts::SettleAuction,
-) -> Instruction {
+pub fn settle(program_id: Pubkey, accounts: accounts::SettleAuction) -> Instruction {
     Instruction {
         program_id,
         accounts: accounts.to_account_metas(None),
         data: instruction::SettleAuction {}.data(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn receipt_pda_is_per_launch_and_buyer() {
+        let program_id = keystone_launchpad::ID;
+        let (launch, buyer) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (receipt, bump) = receipt_pda(&program_id, &launch, &buyer);
+        assert_eq!(
+            Pubkey::create_program_address(
+                &[RECEIPT_SEED, launch.as_ref(), buyer.as_ref(), &[bump]],
+                &program_id
+            ),
+            Ok(receipt)
+        );
+        assert_ne!(receipt_pda(&program_id, &buyer, &launch).0, receipt);
+    }
+}
@@ -1,10 +1,11 @@
 #![deny(missing_docs)]
 //! Rust client SDK for interacting with Keystone Vertex programs.
 
-pub mod launchpad;
 pub mod amm;
+pub mod fee_router;
+pub mod launchpad;
 pub mod staking;
 pub mod vesting;
 
-pub use anchor_client::Program;
-pub use solana_program::instruction::Instruction;
+pub use solana_rpc_client::rpc_client::RpcClient;
+pub use solana_sdk::instruction::Instruction;
@@ -1,11 +1,11 @@
 //! Staking instruction builders.
 
-use anchor_lang::InstructionData;
-use keystone_staking::{accounts, instruction};
-use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use keystone_staking::{accounts, instruction, InitArgs};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
 
 /// Initialize staking pool instruction helper.
-pub fn init_pool(program_id: Pubkey, accounts: accounts::InitPool, args: instruction::InitArgs) -> Instruction {
+pub fn init_pool(program_id: Pubkey, accounts: accounts::InitPool, args: InitArgs) -> Instruction {
     Instruction {
         program_id,
         accounts: accounts.to_account_metas(None),
@@ -1,14 +1,14 @@
 //! Vesting instruction builders.
 
-use anchor_lang::InstructionData;
-use keystone_vesting::{accounts, instruction};
-use solana_program::{instruction::Instruction, pubkey::Pubkey};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use keystone_vesting::{accounts, instruction, CreateArgs};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
 
 /// Create schedule helper.
 pub fn create_schedule(
     program_id: Pubkey,
     accounts: accounts::CreateSchedule,
-    args: instruction::CreateArgs,
+    args: CreateArgs,
 ) -> Instruction {
     Instruction {
         program_id,
@@ -19,8 +19,10 @@ const TEST_IDL: Idl = {
         { name: 'treasuryAuthority', isMut: false, isSigner: false },
         { name: 'treasuryVault', isMut: true, isSigner: false },
         { name: 'saleState', isMut: true, isSigner: false },
+        { name: 'receipt', isMut: true, isSigner: false },
         { name: 'mint', isMut: false, isSigner: false },
-        { name: 'tokenProgram', isMut: false, isSigner: false }
+        { name: 'tokenProgram', isMut: false, isSigner: false },
+        { name: 'systemProgram', isMut: false, isSigner: false }
       ],
       args: [
         { name: 'amount', type: 'u64' },
@@ -39,8 +39,8 @@ export function findVaultAuthorityPda(policy: PublicKey, programId: PublicKey):
   return PublicKey.findProgramAddressSync([VAULT_SEED, policy.toBuffer()], programId);
 }
 
-export function findPositionPda(policy: PublicKey, programId: PublicKey): [PublicKey, number] {
-  return PublicKey.findProgramAddressSync([POSITION_SEED, policy.toBuffer()], programId);
+export function findPositionPda(policy: PublicKey, programId: PublicKey, index = 0): [PublicKey, number] {
+  return PublicKey.findProgramAddressSync([POSITION_SEED, policy.toBuffer(), Buffer.from([index])], programId);
 }
 
 export interface InitPolicyParams {
@@ -100,17 +100,20 @@ export interface InitHonoraryPositionParams {
   quoteMint: PublicKey;
   cpPosition: PublicKey;
   authority: PublicKey;
+  /** Next position index, `policy.positionCount` (0 for the first position). */
+  index?: number;
   systemProgram?: PublicKey;
   rentSysvar?: PublicKey;
 }
 
 export async function buildInitHonoraryPositionIx(p: InitHonoraryPositionParams): Promise<TransactionInstruction> {
   const program = getProgram(p.provider, p.programId, p.idl);
+  const index = p.index ?? 0;
   const [ownerPda] = PublicKey.findProgramAddressSync(
-    [VAULT_SEED, p.policy.toBuffer(), FEE_POS_OWNER_SEED],
+    [VAULT_SEED, p.policy.toBuffer(), FEE_POS_OWNER_SEED, Buffer.from([index])],
     p.programId
   );
-  const [honoraryPosition] = findPositionPda(p.policy, p.programId);
+  const [honoraryPosition] = findPositionPda(p.policy, p.programId, index);
   const systemProgram = p.systemProgram ?? SystemProgram.programId;
   const rent = p.rentSysvar ?? SYSVAR_RENT_PUBKEY;
 
@@ -1,5 +1,5 @@
 import { AnchorProvider, Idl } from '@coral-xyz/anchor';
-import { PublicKey, TransactionInstruction } from '@solana/web3.js';
+import { PublicKey, SystemProgram, TransactionInstruction } from '@solana/web3.js';
 import { getProgram } from './feeRouter';
 
 export interface LaunchpadProgramAccounts {
@@ -30,6 +30,10 @@ export async function buildLaunchpadBuyInstruction(params: LaunchpadBuyParams):
     [Buffer.from('treasury'), params.accounts.launchConfig.toBuffer()],
     params.programId
   )[0];
+  const receipt = PublicKey.findProgramAddressSync(
+    [Buffer.from('receipt'), params.accounts.launchConfig.toBuffer(), params.accounts.buyer.toBuffer()],
+    params.programId
+  )[0];
 
   return program.methods
     .buy(params.amount, params.proof ?? null, params.maxQuote)
@@ -41,8 +45,10 @@ export async function buildLaunchpadBuyInstruction(params: LaunchpadBuyParams):
       treasuryAuthority,
       treasuryVault: params.accounts.treasuryVault,
       saleState: params.accounts.saleState,
+      receipt,
       mint: params.accounts.mint,
-      tokenProgram: params.accounts.tokenProgram
+      tokenProgram: params.accounts.tokenProgram,
+      systemProgram: SystemProgram.programId
     })
     .instruction();
 }
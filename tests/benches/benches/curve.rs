@@ -1,5 +1,7 @@
 use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
-use keystone_common::curve::{CurveConfig, CurveKind, LinearCurve, PricingCurve};
+use keystone_common::curve::{
+    CurveConfig, CurveKind, ExponentialCurve, LinearCurve, PricingCurve, SigmoidCurve,
+};
 use keystone_common::fees::FeeConfig;
 use solana_program::pubkey::Pubkey;
 
@@ -22,5 +24,62 @@ fn bench_linear_curve(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_linear_curve);
+fn bench_exponential_curve(c: &mut Criterion) {
+    let cfg = CurveConfig {
+        kind: CurveKind::Exponential,
+        base_price: 1_000_000_000,
+        k: 20_000,
+        x0: 0,
+        max_supply: 1_000_000,
+        fee_config: FeeConfig::new(30, 10_000, Pubkey::default()).unwrap(),
+    };
+    let curve = ExponentialCurve::new(&cfg).unwrap();
+    c.bench_function("exponential_quote_buy", |b| {
+        b.iter_batched(
+            || (curve, 50_000u64, 1_000u64),
+            |(curve, supply, amount)| curve.quote_buy(supply, amount).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    c.bench_function("exponential_quote_sell", |b| {
+        b.iter_batched(
+            || (curve, 50_000u64, 1_000u64),
+            |(curve, supply, amount)| curve.quote_sell(supply, amount).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+fn bench_sigmoid_curve(c: &mut Criterion) {
+    let cfg = CurveConfig {
+        kind: CurveKind::Sigmoid,
+        base_price: 1_000_000_000,
+        k: 50_000,
+        x0: 500_000,
+        max_supply: 1_000_000,
+        fee_config: FeeConfig::new(30, 10_000, Pubkey::default()).unwrap(),
+    };
+    let curve = SigmoidCurve::new(&cfg).unwrap();
+    c.bench_function("sigmoid_quote_buy", |b| {
+        b.iter_batched(
+            || (curve, 450_000u64, 1_000u64),
+            |(curve, supply, amount)| curve.quote_buy(supply, amount).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+    c.bench_function("sigmoid_quote_sell", |b| {
+        b.iter_batched(
+            || (curve, 550_000u64, 1_000u64),
+            |(curve, supply, amount)| curve.quote_sell(supply, amount).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(
+    benches,
+    bench_linear_curve,
+    bench_exponential_curve,
+    bench_sigmoid_curve
+);
 criterion_main!(benches);
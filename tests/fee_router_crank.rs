@@ -1,59 +1,388 @@
 #![cfg(all(test, not(target_arch = "bpf")))]
 
 // Host-only tests validating fee_router pro-rata math, cap clamp, dust carry, idempotency.
+// Built as the `fee_router_crank` test of `keystone-fee-router`.
+
+use anchor_lang::prelude::Pubkey;
+use keystone_fee_router::{
+    compute_crank_reward, compute_investor_pool, compute_investor_quote, fix_crank_reward,
+    pages_per_day, pay_page_crank_reward, settle_dust, split_remainder, ClaimMode, DayRecord,
+    DistributionHistory, InvestorSet, LockSource, PayoutBitmap, Policy, Progress,
+    RemainderRecipient, RemainderSplit, DISTRIBUTION_HISTORY_LEN,
+};
+
+fn policy(investor_fee_share_bps: u16, daily_cap_quote: u64, min_payout_lamports: u64) -> Policy {
+    Policy {
+        authority: Pubkey::default(),
+        guardian: Pubkey::default(),
+        cp_pool: Pubkey::default(),
+        quote_mint: Pubkey::default(),
+        quote_token_program: Pubkey::default(),
+        creator_quote_ata: Pubkey::default(),
+        remainder_split: RemainderSplit::default(),
+        treasury_quote_ata: Pubkey::default(),
+        investor_fee_share_bps,
+        y0_total: 1_000_000,
+        daily_cap_quote,
+        min_payout_lamports,
+        crank_reward_bps: 0,
+        crank_reward_cap_quote: 0,
+        investor_set: InvestorSet::default(),
+        force_close_grace_secs: 0,
+        roll_forced_carry: false,
+        claim_mode: ClaimMode::Push,
+        lock_source: LockSource::Streamflow,
+        epoch_seconds: 86_400,
+        epoch_offset_seconds: 0,
+        max_page_size: 1,
+        position_count: 1,
+        pending_update: None,
+        paused: false,
+        bump: 0,
+        initialized: true,
+    }
+}
+
+/// Replays a payout pass over `pages` of locked amounts with empty dust ledgers,
+/// returning per-investor payouts (in page order) and the total allocated to investors.
+fn payout_pass(policy: &Policy, pool: u64, locked_total: u64, pages: &[&[u64]]) -> (Vec<u64>, u64) {
+    let mut distributed = 0u64;
+    let mut paid = Vec::new();
+    for page in pages {
+        for &locked in page.iter() {
+            let share = compute_investor_quote(pool, locked, locked_total)
+                .unwrap()
+                .min(pool - distributed);
+            let (payout, _dust) = settle_dust(0, share, policy.min_payout_lamports).unwrap();
+            distributed += share;
+            paid.push(payout);
+        }
+    }
+    (paid, distributed)
+}
 
 #[test]
 fn pro_rata_math() {
-    let y0_total: u128 = 1_000_000;
-    let locked: u128 = 250_000; // 25%
-    let investor_fee_share_bps: u128 = 2_000; // 20%
-    let distributable: u128 = 10_000;
-    let f_locked_bps = (locked * 10_000) / y0_total; // 2500 bps
-    let eligible_bps = core::cmp::min(f_locked_bps, investor_fee_share_bps); // 2000 bps
-    let share = (distributable * eligible_bps) / 10_000; // 2000
-    assert_eq!(share, 2_000);
+    let p = policy(2_000, 0, 0);
+    // 25% locked, capped by the 20% investor share.
+    assert_eq!(compute_investor_pool(&p, 10_000, 250_000).unwrap(), 2_000);
+    // Investors split the pool by locked_i / locked_total.
+    assert_eq!(
+        compute_investor_quote(2_000, 150_000, 250_000).unwrap(),
+        1_200
+    );
+    assert_eq!(
+        compute_investor_quote(2_000, 100_000, 250_000).unwrap(),
+        800
+    );
+}
+
+#[test]
+fn pages_do_not_change_payouts() {
+    let p = policy(5_000, 0, 0);
+    let locked = [300_000u64, 120_000, 80_000, 0, 50_000];
+    let locked_total: u64 = locked.iter().sum();
+    let pool = compute_investor_pool(&p, 7_777, locked_total).unwrap();
+
+    let (one_page, total_one) = payout_pass(&p, pool, locked_total, &[&locked]);
+    let (paged, total_paged) = payout_pass(
+        &p,
+        pool,
+        locked_total,
+        &[&locked[..2], &locked[2..3], &locked[3..]],
+    );
+    assert_eq!(one_page, paged);
+    assert_eq!(total_one, total_paged);
+
+    let reversed: Vec<u64> = locked.iter().rev().copied().collect();
+    let (mut rev_paid, _) = payout_pass(&p, pool, locked_total, &[&reversed[..3], &reversed[3..]]);
+    rev_paid.reverse();
+    assert_eq!(one_page, rev_paid);
+    assert!(total_one <= pool);
 }
 
 #[test]
 fn cap_and_carry_logic() {
-    let distributed_today: u64 = 5_000;
-    let daily_cap: u64 = 6_000;
-    let treasury_balance: u64 = 10_000;
-    let cap_remaining = daily_cap.saturating_sub(distributed_today); // 1_000
-    let pool_remaining = treasury_balance; // using current treasury balance
-    let distributable = core::cmp::min(cap_remaining, pool_remaining);
-    assert_eq!(distributable, 1_000);
+    // Cap clamps the investor pool; the rest of the claimed quote goes to the creator.
+    let p = policy(5_000, 1_000, 500);
+    let claimed = 10_000u64;
+    let pool = compute_investor_pool(&p, claimed, 1_000_000).unwrap();
+    assert_eq!(pool, 1_000);
+
+    // Shares below the dust threshold are not paid but stay allocated to investors.
+    let locked = [600_000u64, 250_000, 150_000];
+    let (paid, distributed) = payout_pass(&p, pool, 1_000_000, &[&locked]);
+    assert_eq!(paid, vec![600, 0, 0]);
+    assert_eq!(distributed, pool);
+    assert_eq!(claimed - distributed, 9_000);
+}
 
-    // With dust threshold above share, carry should remain non-zero.
-    let eligible_bps = 1500u64; // 15%
-    let share = (distributable as u128 * eligible_bps as u128 / 10_000) as u64; // 150
-    let min_payout = 500u64;
-    let paid = if share >= min_payout { share } else { 0 };
-    let carry = distributable.saturating_sub(paid);
-    assert_eq!(paid, 0);
-    assert_eq!(carry, 1_000);
+#[test]
+fn dust_accrues_across_days_until_threshold() {
+    // 250 per day against a 500 threshold: paid every second day.
+    let mut accrued = 0u64;
+    let mut paid = Vec::new();
+    for _ in 0..4 {
+        let (payout, after) = settle_dust(accrued, 250, 500).unwrap();
+        accrued = after;
+        paid.push(payout);
+    }
+    assert_eq!(paid, vec![0, 500, 0, 500]);
+    assert_eq!(accrued, 0);
+    // A zero threshold pays any positive amount; nothing owed pays nothing.
+    assert_eq!(settle_dust(0, 1, 0).unwrap(), (1, 0));
+    assert_eq!(settle_dust(0, 0, 0).unwrap(), (0, 0));
+    assert!(settle_dust(u64::MAX, 1, 0).is_err());
 }
 
 #[test]
 fn idempotency_handling() {
-    // If a page pays 600 and distributed increases, a retry cannot overpay as
-    // subsequent distributable clamps by current treasury.
-    let start_treasury = 2_000u64;
+    // The pool is fixed at snapshot close; a replayed page cannot push payouts past it.
+    let p = policy(5_000, 0, 0);
+    let pool = compute_investor_pool(&p, 2_000, 1_000_000).unwrap();
+    assert_eq!(pool, 1_000);
+    let (paid, distributed) = payout_pass(&p, pool, 1_000_000, &[&[800_000], &[800_000]]);
+    assert_eq!(paid, vec![800, 200]);
+    assert_eq!(distributed, pool);
+}
+
+/// Replays payout pages of investor indices against a payout bitmap, mirroring the
+/// crank: an index already settled today is a no-op. Returns the total paid.
+fn replay_pages(
+    bitmap: &mut PayoutBitmap,
+    day: i64,
+    pool: u64,
+    locked: &[u64],
+    pages: &[&[u32]],
+) -> u64 {
+    let locked_total: u64 = locked.iter().sum();
+    bitmap.roll_to(day, locked.len() as u32);
     let mut distributed = 0u64;
-    let bps = 5000u64; // 50%
+    for page in pages {
+        for &index in page.iter() {
+            if !bitmap.mark(index).unwrap() {
+                continue;
+            }
+            distributed += compute_investor_quote(pool, locked[index as usize], locked_total)
+                .unwrap()
+                .min(pool - distributed);
+        }
+    }
+    distributed
+}
 
-    // First run
-    let cap_remaining = u64::MAX;
-    let pool_remaining = start_treasury;
-    let mut distributable = core::cmp::min(cap_remaining, pool_remaining);
-    let share = (distributable as u128 * bps as u128 / 10_000) as u64; // 1_000
-    distributed += share;
+fn empty_bitmap() -> PayoutBitmap {
+    PayoutBitmap {
+        day: 0,
+        bits: Vec::new(),
+        bump: 0,
+    }
+}
+
+#[test]
+fn replayed_pages_pay_once() {
+    let locked = [400_000u64, 300_000, 200_000, 100_000];
+    let mut bitmap = empty_bitmap();
+    let once = replay_pages(&mut bitmap, 19_700, 1_000, &locked, &[&[0, 1], &[2, 3]]);
+    assert_eq!(once, 1_000);
 
-    // Treasury after transfer: 1_000; retry run computes distributable from treasury
-    let pool_remaining_retry = start_treasury - share; // 1000
-    let distributable_retry = core::cmp::min(u64::MAX, pool_remaining_retry);
-    let share_retry = (distributable_retry as u128 * bps as u128 / 10_000) as u64; // 500
-    assert_eq!(share, 1_000);
-    assert_eq!(share_retry, 500);
+    let mut bitmap = empty_bitmap();
+    let replayed = replay_pages(
+        &mut bitmap,
+        19_700,
+        1_000,
+        &locked,
+        &[&[0, 1], &[0, 1], &[2, 3], &[2, 3]],
+    );
+    assert_eq!(replayed, once);
+    // Replaying after the pass completes pays nothing more.
+    assert_eq!(
+        replay_pages(&mut bitmap, 19_700, 1_000, &locked, &[&[0, 1, 2, 3]]),
+        0
+    );
+}
+
+#[test]
+fn duplicate_entries_in_page_pay_once() {
+    let locked = [500_000u64, 500_000];
+    let mut bitmap = empty_bitmap();
+    let paid = replay_pages(&mut bitmap, 19_700, 1_000, &locked, &[&[0, 0, 0]]);
+    assert_eq!(paid, 500);
+    assert!(bitmap.is_set(0));
+    assert!(!bitmap.is_set(1));
+}
+
+#[test]
+fn bitmap_resets_on_new_day() {
+    let locked = [1u64; 9];
+    let mut bitmap = empty_bitmap();
+    bitmap.roll_to(19_700, 9);
+    assert_eq!(bitmap.bits.len(), 2);
+    assert!(bitmap.mark(8).unwrap());
+    assert!(!bitmap.mark(8).unwrap());
+    // Same day keeps the record; the next day starts clean.
+    bitmap.roll_to(19_700, 9);
+    assert!(bitmap.is_set(8));
+    assert_eq!(
+        replay_pages(&mut bitmap, 19_701, 900, &locked, &[&[8]]),
+        100
+    );
+    // Indices outside the investor set are rejected.
+    assert!(bitmap.mark(16).is_err());
 }
 
+#[test]
+fn history_keeps_the_latest_days() {
+    let mut history = DistributionHistory {
+        policy: Pubkey::new_unique(),
+        head: 0,
+        len: 0,
+        records: [DayRecord::default(); DISTRIBUTION_HISTORY_LEN],
+        bump: 0,
+    };
+    assert!(history.latest().is_none());
+    assert!(history.records().is_empty());
+
+    let day = |day: i64| DayRecord {
+        day,
+        claimed_quote: day as u64 * 10,
+        ..DayRecord::default()
+    };
+    for d in 1..=3 {
+        history.push(day(d));
+    }
+    assert_eq!(
+        history.records().iter().map(|r| r.day).collect::<Vec<_>>(),
+        [1, 2, 3]
+    );
+    assert_eq!(history.latest(), Some(day(3)));
+
+    // Once full, each new day overwrites the oldest one.
+    let total = DISTRIBUTION_HISTORY_LEN as i64 + 5;
+    for d in 4..=total {
+        history.push(day(d));
+    }
+    let records = history.records();
+    assert_eq!(records.len(), DISTRIBUTION_HISTORY_LEN);
+    assert_eq!(records.first().unwrap().day, 6);
+    assert_eq!(records.last().unwrap().day, total);
+    assert!(records.windows(2).all(|w| w[1].day == w[0].day + 1));
+    assert_eq!(history.latest(), Some(day(total)));
+}
+
+#[test]
+fn remainder_split_rounding() {
+    let creator = Pubkey::new_unique();
+    let recipients: Vec<RemainderRecipient> = [3_333u16, 3_333, 3_334]
+        .iter()
+        .map(|&bps| RemainderRecipient {
+            quote_ata: Pubkey::new_unique(),
+            bps,
+        })
+        .collect();
+    let split = RemainderSplit::new(&recipients).unwrap();
+    let shares: Vec<u64> = split_remainder(&split, creator, 1_000)
+        .unwrap()
+        .iter()
+        .map(|(_, amount)| *amount)
+        .collect();
+    // Floors of 333.3 each; the last recipient takes the rounding dust.
+    assert_eq!(shares, vec![333, 333, 334]);
+    // Remainders too small to split land on the last recipient.
+    assert_eq!(split_remainder(&split, creator, 2).unwrap()[2].1, 2);
+
+    // No split: everything to the creator.
+    let all = split_remainder(&RemainderSplit::default(), creator, 77).unwrap();
+    assert_eq!(all.len(), 1);
+    assert_eq!(
+        (all[0].0.quote_ata, all[0].0.bps, all[0].1),
+        (creator, 10_000, 77)
+    );
+}
+
+#[test]
+fn remainder_split_validation() {
+    let recipient = |bps| RemainderRecipient {
+        quote_ata: Pubkey::new_unique(),
+        bps,
+    };
+    assert!(RemainderSplit::new(&[]).is_ok());
+    assert!(RemainderSplit::new(&[recipient(10_000)]).is_ok());
+    assert!(RemainderSplit::new(&[recipient(6_000), recipient(3_000)]).is_err());
+    assert!(RemainderSplit::new(&[recipient(10_000), recipient(0)]).is_err());
+    assert!(RemainderSplit::new(&[recipient(2_000); 5]).is_err());
+    assert!(RemainderSplit::new(&[recipient(2_500); 4]).is_ok());
+}
+
+#[test]
+fn epoch_boundaries() {
+    let mut p = policy(0, 0, 0);
+    // UTC days: the boundary second opens the next day.
+    assert_eq!(p.epoch_index(19_700 * 86_400 - 1).unwrap(), 19_699);
+    assert_eq!(p.epoch_index(19_700 * 86_400).unwrap(), 19_700);
+    assert_eq!(p.epoch_start(19_700).unwrap(), 19_700 * 86_400);
+
+    // Hourly periods starting at :30 past the hour.
+    p.epoch_seconds = 3_600;
+    p.epoch_offset_seconds = -1_800;
+    let start = p.epoch_start(500_000).unwrap();
+    assert_eq!(start, 500_000 * 3_600 - 1_800);
+    assert_eq!(p.epoch_index(start).unwrap(), 500_000);
+    assert_eq!(p.epoch_index(start - 1).unwrap(), 499_999);
+    assert_eq!(p.epoch_index(start + 3_599).unwrap(), 500_000);
+
+    // Weekly periods with a positive offset; timestamps before the offset floor down.
+    p.epoch_seconds = 7 * 86_400;
+    p.epoch_offset_seconds = 3 * 86_400;
+    assert_eq!(p.epoch_index(3 * 86_400).unwrap(), 0);
+    assert_eq!(p.epoch_index(3 * 86_400 - 1).unwrap(), -1);
+    assert_eq!(p.epoch_start(-1).unwrap(), -4 * 86_400);
+
+    p.epoch_seconds = 0;
+    assert!(p.epoch_index(0).is_err());
+}
+
+#[test]
+fn crank_reward_comes_off_the_top() {
+    let mut p = policy(2_000, 0, 0);
+    p.crank_reward_bps = 50;
+    assert_eq!(compute_crank_reward(&p, 10_000).unwrap(), 50);
+    p.crank_reward_cap_quote = 20;
+    assert_eq!(compute_crank_reward(&p, 10_000).unwrap(), 20);
+    p.crank_reward_bps = 0;
+    assert_eq!(compute_crank_reward(&p, 10_000).unwrap(), 0);
+}
+
+#[test]
+fn every_page_earns_the_same_crank_reward() {
+    let mut p = policy(2_000, 0, 0);
+    p.crank_reward_bps = 100;
+    p.investor_set.count = 7;
+    p.max_page_size = 3;
+    // Three pages per pass: snapshot and payout passes in push mode, one in pull.
+    assert_eq!(pages_per_day(&p), 6);
+    p.claim_mode = ClaimMode::Pull;
+    assert_eq!(pages_per_day(&p), 3);
+    p.claim_mode = ClaimMode::Push;
+
+    let mut progress = Progress {
+        claimed_quote_today: 10_000,
+        ..Default::default()
+    };
+    fix_crank_reward(&mut progress, &p, 10_000).unwrap();
+    // 100 split over 6 pages; the rounding residue is not reserved and stays in
+    // the split between investors and the remainder.
+    assert_eq!(progress.crank_reward_per_page, 16);
+    assert_eq!(progress.crank_reward_today, 96);
+
+    // A page cranked without a payer quote account leaves its reward undistributed;
+    // extra pages never take more than the day's reward.
+    let mut paid = 0;
+    for page in 0..8 {
+        paid += pay_page_crank_reward(&mut progress, |owed| Ok(if page == 1 { 0 } else { owed }))
+            .unwrap();
+    }
+    assert_eq!(paid, 96);
+    assert_eq!(progress.crank_reward_paid_today, 96);
+    assert_eq!(pay_page_crank_reward(&mut progress, Ok).unwrap(), 0);
+}
@@ -1,266 +1,2809 @@
-#![cfg(feature = "bankrun-test")]
+// Host integration tests for the fee router on `solana-program-test`.
+// Included from `programs/fee_router/src/lib.rs` behind the `bankrun-test` feature:
+// `cargo test -p keystone-fee-router --features bankrun-test`.
+// Builtin (native) programs do not surface `emit!` logs, so tests assert on state.
+//
+// A mock cp-amm program is loaded at the Meteora DAMM v2 program id. It serves
+// `claim_position_fee` by paying the pending fees recorded in the position account
+// (`fee_a: u64 LE || fee_b: u64 LE`) out of the pool vaults, and `close_position`
+// by refunding the position account to the rent receiver once no fees are pending.
+// Claims use `transfer_checked` with each side's token program, so Token-2022 quote
+// mints withhold their transfer fee on the way into the treasury.
 
 use std::str::FromStr;
 
-use anchor_lang::{prelude::*, InstructionData, ToAccountMetas};
-use anchor_lang::solana_program::{self, system_program, sysvar};
+use crate::meteora_cpi::{
+    CLAIM_POSITION_FEE_DISCRIMINATOR, CLOSE_POSITION_DISCRIMINATOR, COLLECT_FEE_MODE_BOTH_TOKEN,
+    COLLECT_FEE_MODE_ONLY_B, DEFAULT_DLMM_PROGRAM_ID, EVENT_AUTHORITY_SEED,
+    POOL_COLLECT_FEE_MODE_OFFSET, POOL_DISCRIMINATOR, POOL_TOKEN_A_MINT_OFFSET,
+    POOL_TOKEN_B_MINT_OFFSET,
+};
 use crate::{
-    CrankArgs,
-    InitPolicyArgs,
-    FEE_POS_OWNER_SEED,
-    POLICY_SEED,
-    POSITION_SEED,
-    PROGRESS_SEED,
-    VAULT_SEED,
+    ClaimFeesArgs, ClaimMode, CloseHonoraryPositionArgs, ClosePolicyArgs, CollectPositionFeesArgs,
+    CrankArgs, DistributionPhase, FeeRouterError, InitPolicyArgs, InvestorSet, LockSource,
+    PolicyUpdateArgs, RemainderRecipient,
+};
+use anchor_lang::solana_program::{
+    entrypoint::ProgramResult, program::invoke_signed, program_error::ProgramError,
+    program_pack::Pack, sysvar,
 };
+use anchor_lang::{prelude::*, system_program, InstructionData, ToAccountMetas};
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{transfer_fee, ExtensionType, StateWithExtensions},
+};
+use keystone_common::merkle::hash_nodes;
 use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+#[allow(deprecated)]
+use solana_sdk::system_instruction;
 use solana_sdk::{
-    account::Account as SolanaAccount,
-    instruction::Instruction,
-    signature::{Keypair, Signature, Signer},
-    system_instruction,
-    transaction::Transaction,
+    account::AccountSharedData,
+    instruction::{Instruction, InstructionError},
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_associated_token_account::{
+    get_associated_token_address, get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account,
 };
-use spl_associated_token_account::instruction::create_associated_token_account;
-use spl_token::{instruction as token_instruction, state::{Account as TokenAccount, Mint}};
+use spl_token::{
+    instruction as token_instruction,
+    state::{Account as TokenAccount, Mint},
+};
+
+const MOCK_POOL_AUTHORITY_SEED: &[u8] = b"pool_authority";
+
+/// Adapts Anchor's lifetime-bound entrypoint to the `processor!` signature.
+fn fee_router_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(accounts.to_vec().into_boxed_slice());
+    crate::entry(program_id, accounts, data)
+}
+
+/// Mock cp-amm entrypoint for `claim_position_fee` and `close_position`.
+fn mock_cp_amm(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    match data.get(..8) {
+        Some(d) if d == CLAIM_POSITION_FEE_DISCRIMINATOR => {
+            mock_claim_position_fee(program_id, accounts)
+        }
+        Some(d) if d == CLOSE_POSITION_DISCRIMINATOR => mock_close_position(accounts),
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+/// Mock cp-amm `close_position`: refunds the fee-free position to the rent receiver.
+fn mock_close_position(accounts: &[AccountInfo]) -> ProgramResult {
+    let [_nft_mint, _nft_account, _pool, position, _pool_authority, rent_receiver, owner, ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if position.data.borrow().iter().any(|byte| *byte != 0) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+    let lamports = position.lamports();
+    **rent_receiver.try_borrow_mut_lamports()? += lamports;
+    **position.try_borrow_mut_lamports()? = 0;
+    position.resize(0)?;
+    Ok(())
+}
+
+/// Mock cp-amm `claim_position_fee`: pays pending position fees from the vaults.
+fn mock_claim_position_fee(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let [pool_authority, _pool, position, token_a_account, token_b_account, token_a_vault, token_b_vault, token_a_mint, token_b_mint, _position_nft_account, owner, token_a_program, token_b_program, ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let (authority, bump) = Pubkey::find_program_address(&[MOCK_POOL_AUTHORITY_SEED], program_id);
+    if *pool_authority.key != authority {
+        return Err(ProgramError::InvalidSeeds);
+    }
+    let (fee_a, fee_b) = {
+        let data = position.data.borrow();
+        (
+            u64::from_le_bytes(data[..8].try_into().unwrap()),
+            u64::from_le_bytes(data[8..16].try_into().unwrap()),
+        )
+    };
+    for (amount, vault, mint, destination, token_program) in [
+        (
+            fee_a,
+            token_a_vault,
+            token_a_mint,
+            token_a_account,
+            token_a_program,
+        ),
+        (
+            fee_b,
+            token_b_vault,
+            token_b_mint,
+            token_b_account,
+            token_b_program,
+        ),
+    ] {
+        if amount == 0 {
+            continue;
+        }
+        let decimals =
+            StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint.data.borrow())?
+                .base
+                .decimals;
+        invoke_signed(
+            &spl_token_2022::instruction::transfer_checked(
+                token_program.key,
+                vault.key,
+                mint.key,
+                destination.key,
+                &authority,
+                &[],
+                amount,
+                decimals,
+            )?,
+            &[
+                vault.clone(),
+                mint.clone(),
+                destination.clone(),
+                pool_authority.clone(),
+                token_program.clone(),
+            ],
+            &[&[MOCK_POOL_AUTHORITY_SEED, &[bump]]],
+        )?;
+    }
+    position.data.borrow_mut()[..16].fill(0);
+    Ok(())
+}
+
+/// Accounts of one honorary position registered under the harness policy.
+#[derive(Clone, Copy)]
+struct PositionAccounts {
+    index: u8,
+    cp_pool: Pubkey,
+    cp_position: Pubkey,
+    base_mint: Pubkey,
+    base_vault: Pubkey,
+    treasury_base_ata: Pubkey,
+    honorary_position: Pubkey,
+    owner_pda: Pubkey,
+}
+
+struct Harness {
+    context: ProgramTestContext,
+    authority: Keypair,
+    dlmm_program: Pubkey,
+    cp_pool: Pubkey,
+    cp_position: Pubkey,
+    pool_authority: Pubkey,
+    base_mint: Pubkey,
+    quote_mint: Pubkey,
+    /// Token program of the quote mint.
+    quote_token_program: Pubkey,
+    base_vault: Pubkey,
+    quote_vault: Pubkey,
+    policy: Pubkey,
+    progress: Pubkey,
+    payout_bitmap: Pubkey,
+    history: Pubkey,
+    vault_authority: Pubkey,
+    owner_pda: Pubkey,
+    honorary_position: Pubkey,
+    treasury_ata: Pubkey,
+    treasury_base_ata: Pubkey,
+    creator_ata: Pubkey,
+    /// Crank payer's quote account passed for the crank reward, if any.
+    payer_quote_ata: Option<Pubkey>,
+    /// Remainder split quote ATAs appended to last pages and forced closes.
+    remainder_atas: Vec<Pubkey>,
+    /// Claim mode of the policy; pull-mode pages carry checkpoints instead of dust
+    /// ledgers.
+    claim_mode: ClaimMode,
+    /// Honorary positions registered after position 0, in index order.
+    extra_positions: Vec<PositionAccounts>,
+    /// Investor set entries `(quote ATA, stream)` in index order.
+    investors: Vec<(Pubkey, Pubkey)>,
+    /// Investor wallets (ATA owners and stream recipients) in index order.
+    investor_wallets: Vec<Keypair>,
+    /// Investor set Merkle tree levels, leaves first.
+    investor_tree: Vec<Vec<[u8; 32]>>,
+}
+
+impl Harness {
+    /// Boots program-test with the fee router and the mock cp-amm, creates one
+    /// investor per `locked` amount, then runs `init_policy` (committing the
+    /// investor set) and `init_honorary_position` for a base/quote pool.
+    async fn setup(policy_args: InitPolicyArgs, locked: &[u64]) -> Self {
+        match Self::try_setup(policy_args, locked).await {
+            Ok(harness) => harness,
+            Err(err) => panic!("setup failed: {err:?}"),
+        }
+    }
+
+    /// Like `setup`, returning the init transaction error instead of panicking.
+    async fn try_setup(
+        policy_args: InitPolicyArgs,
+        locked: &[u64],
+    ) -> std::result::Result<Self, TransactionError> {
+        let harness = Self::boot(locked).await;
+        let investor_set = harness.investor_set();
+        harness
+            .init(InitPolicyArgs {
+                investor_set,
+                ..policy_args
+            })
+            .await
+    }
+
+    /// Like `setup`, with a Token-2022 quote mint withholding `fee_bps` (at most
+    /// `max_fee`) of every transfer.
+    async fn setup_with_transfer_fee(
+        policy_args: InitPolicyArgs,
+        locked: &[u64],
+        fee_bps: u16,
+        max_fee: u64,
+    ) -> Self {
+        let harness = Self::boot_with_quote_fee(locked, Some((fee_bps, max_fee))).await;
+        let investor_set = harness.investor_set();
+        match harness
+            .init(InitPolicyArgs {
+                investor_set,
+                ..policy_args
+            })
+            .await
+        {
+            Ok(harness) => harness,
+            Err(err) => panic!("setup failed: {err:?}"),
+        }
+    }
+
+    /// Everything `setup` does before `init`: program-test, mints, an `OnlyB` pool
+    /// with quote as token B, token accounts and investors.
+    async fn boot(locked: &[u64]) -> Self {
+        Self::boot_with_quote_fee(locked, None).await
+    }
+
+    /// Like `boot`; with `quote_fee = Some((fee_bps, max_fee))` the quote mint is a
+    /// Token-2022 mint with the transfer fee extension.
+    async fn boot_with_quote_fee(locked: &[u64], quote_fee: Option<(u16, u64)>) -> Self {
+        let dlmm_program = Pubkey::from_str(DEFAULT_DLMM_PROGRAM_ID).unwrap();
+        let mut test = ProgramTest::new(
+            "keystone_fee_router",
+            crate::id(),
+            processor!(fee_router_entry),
+        );
+        test.add_program("mock_cp_amm", dlmm_program, processor!(mock_cp_amm));
+        let mut context = test.start_with_context().await;
+
+        let authority = Keypair::new();
+        let creator = Keypair::new();
+        let cp_pool = Keypair::new();
+        let cp_position = Keypair::new();
+        let base_mint = Keypair::new();
+        let quote_mint = Keypair::new();
+
+        fund_accounts(&mut context, &[authority.pubkey()]).await;
+        let rent = context.banks_client.get_rent().await.unwrap();
+        set_pool(
+            &mut context,
+            &cp_pool.pubkey(),
+            &base_mint.pubkey(),
+            &quote_mint.pubkey(),
+            COLLECT_FEE_MODE_ONLY_B,
+        )
+        .await;
+        create_owned_account(
+            &mut context,
+            &cp_position,
+            &dlmm_program,
+            rent.minimum_balance(16),
+            16,
+        )
+        .await;
+        create_mint(
+            &mut context,
+            &base_mint,
+            &authority,
+            rent.minimum_balance(Mint::LEN),
+        )
+        .await;
+        let quote_token_program = match quote_fee {
+            Some((fee_bps, max_fee)) => {
+                create_transfer_fee_mint(&mut context, &quote_mint, &authority, fee_bps, max_fee)
+                    .await;
+                spl_token_2022::ID
+            }
+            None => {
+                create_mint(
+                    &mut context,
+                    &quote_mint,
+                    &authority,
+                    rent.minimum_balance(Mint::LEN),
+                )
+                .await;
+                spl_token::ID
+            }
+        };
+        let quote_ata = |owner: &Pubkey| {
+            get_associated_token_address_with_program_id(
+                owner,
+                &quote_mint.pubkey(),
+                &quote_token_program,
+            )
+        };
+
+        let (pool_authority, _) =
+            Pubkey::find_program_address(&[MOCK_POOL_AUTHORITY_SEED], &dlmm_program);
+        let (policy, _) = Pubkey::find_program_address(
+            &[crate::POLICY_SEED, cp_pool.pubkey().as_ref()],
+            &crate::id(),
+        );
+        let (vault_authority, _) =
+            Pubkey::find_program_address(&[crate::VAULT_SEED, policy.as_ref()], &crate::id());
+        let (payout_bitmap, _) = Pubkey::find_program_address(
+            &[crate::PAYOUT_BITMAP_SEED, policy.as_ref()],
+            &crate::id(),
+        );
+        let (history, _) =
+            Pubkey::find_program_address(&[crate::HISTORY_SEED, policy.as_ref()], &crate::id());
+        let (progress, _) = Pubkey::find_program_address(
+            &[crate::PROGRESS_SEED, cp_pool.pubkey().as_ref()],
+            &crate::id(),
+        );
+        let (honorary_position, owner_pda) = position_pdas(&policy, 0);
+
+        let payer = context.payer.pubkey();
+        let mut ata_ixs = Vec::new();
+        for (owner, mint, token_program) in [
+            (vault_authority, quote_mint.pubkey(), quote_token_program),
+            (vault_authority, base_mint.pubkey(), spl_token::ID),
+            (creator.pubkey(), quote_mint.pubkey(), quote_token_program),
+            (pool_authority, base_mint.pubkey(), spl_token::ID),
+            (pool_authority, quote_mint.pubkey(), quote_token_program),
+        ] {
+            ata_ixs.push(create_associated_token_account(
+                &payer,
+                &owner,
+                &mint,
+                &token_program,
+            ));
+        }
+        process_tx(&mut context, ata_ixs, &[]).await.unwrap();
+
+        let treasury_ata = quote_ata(&vault_authority);
+        let mut harness = Self {
+            dlmm_program,
+            cp_pool: cp_pool.pubkey(),
+            cp_position: cp_position.pubkey(),
+            pool_authority,
+            base_mint: base_mint.pubkey(),
+            quote_mint: quote_mint.pubkey(),
+            quote_token_program,
+            base_vault: get_associated_token_address(&pool_authority, &base_mint.pubkey()),
+            quote_vault: quote_ata(&pool_authority),
+            policy,
+            progress,
+            payout_bitmap,
+            history,
+            vault_authority,
+            owner_pda,
+            honorary_position,
+            treasury_ata,
+            treasury_base_ata: get_associated_token_address(&vault_authority, &base_mint.pubkey()),
+            creator_ata: quote_ata(&creator.pubkey()),
+            payer_quote_ata: None,
+            remainder_atas: Vec::new(),
+            claim_mode: ClaimMode::Push,
+            extra_positions: Vec::new(),
+            investors: Vec::new(),
+            investor_wallets: Vec::new(),
+            investor_tree: Vec::new(),
+            context,
+            authority,
+        };
+        for &amount in locked {
+            let ata = harness.investor().await;
+            let recipient = harness.investor_wallets.last().unwrap().pubkey();
+            let stream = harness.stream(&recipient, amount).await;
+            harness.investors.push((ata, stream));
+        }
+        harness.investor_tree = merkle_levels(
+            harness
+                .investors
+                .iter()
+                .enumerate()
+                .map(|(index, (ata, stream))| crate::investor_leaf(index as u32, ata, stream))
+                .collect(),
+        );
+        harness
+    }
+
+    /// Investor set committing the harness investors.
+    fn investor_set(&self) -> InvestorSet {
+        InvestorSet {
+            root: self.investor_tree.last().unwrap()[0],
+            count: self.investors.len() as u32,
+        }
+    }
+
+    async fn init(mut self, args: InitPolicyArgs) -> std::result::Result<Self, TransactionError> {
+        self.claim_mode = args.claim_mode;
+        let recipient_metas = recipient_metas(&args.remainder_recipients);
+        let mut init_policy_ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::InitPolicy {
+                authority: self.authority.pubkey(),
+                policy: self.policy,
+                cp_pool: self.cp_pool,
+                quote_mint: self.quote_mint,
+                creator_quote_ata: self.creator_ata,
+                treasury_quote_ata: self.treasury_ata,
+                vault_authority: self.vault_authority,
+                token_program: self.quote_token_program,
+                system_program: system_program::ID,
+                rent: sysvar::rent::ID,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::InitPolicy { args }.data(),
+        };
+        init_policy_ix.accounts.extend(recipient_metas);
+        let init_position_ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::InitHonoraryPosition {
+                authority: self.authority.pubkey(),
+                policy: self.policy,
+                cp_pool: self.cp_pool,
+                quote_mint: self.quote_mint,
+                owner_pda: self.owner_pda,
+                cp_position: self.cp_position,
+                honorary_position: self.honorary_position,
+                system_program: system_program::ID,
+                rent: sysvar::rent::ID,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::InitHonoraryPosition {}.data(),
+        };
+        let authority = self.authority.insecure_clone();
+        process_tx(
+            &mut self.context,
+            vec![init_policy_ix, init_position_ix],
+            &[&authority],
+        )
+        .await?;
+        Ok(self)
+    }
+
+    /// Accounts of honorary position `index`.
+    fn position(&self, index: u8) -> PositionAccounts {
+        match index {
+            0 => PositionAccounts {
+                index,
+                cp_pool: self.cp_pool,
+                cp_position: self.cp_position,
+                base_mint: self.base_mint,
+                base_vault: self.base_vault,
+                treasury_base_ata: self.treasury_base_ata,
+                honorary_position: self.honorary_position,
+                owner_pda: self.owner_pda,
+            },
+            _ => self.extra_positions[index as usize - 1],
+        }
+    }
+
+    /// Registers the next honorary position, in a fresh `OnlyB` pool pairing a new
+    /// base mint with the policy quote mint.
+    async fn add_position(&mut self) -> std::result::Result<PositionAccounts, TransactionError> {
+        self.add_position_in_pool(COLLECT_FEE_MODE_ONLY_B).await
+    }
 
-const DLMM_ID: &str = "cpamdpZCGKUy5JxQXB4dcpGPiikHawvSWAd6mEn1sGG";
+    /// Like `add_position`, with the pool's `collect_fee_mode` set to `collect_fee_mode`.
+    async fn add_position_in_pool(
+        &mut self,
+        collect_fee_mode: u8,
+    ) -> std::result::Result<PositionAccounts, TransactionError> {
+        let index = 1 + self.extra_positions.len() as u8;
+        let cp_pool = Keypair::new();
+        let cp_position = Keypair::new();
+        let base_mint = Keypair::new();
+        let authority = self.authority.insecure_clone();
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        set_pool(
+            &mut self.context,
+            &cp_pool.pubkey(),
+            &base_mint.pubkey(),
+            &self.quote_mint,
+            collect_fee_mode,
+        )
+        .await;
+        create_owned_account(
+            &mut self.context,
+            &cp_position,
+            &self.dlmm_program,
+            rent.minimum_balance(16),
+            16,
+        )
+        .await;
+        create_mint(
+            &mut self.context,
+            &base_mint,
+            &authority,
+            rent.minimum_balance(Mint::LEN),
+        )
+        .await;
+        let payer = self.context.payer.pubkey();
+        let ata_ixs = [self.pool_authority, self.vault_authority]
+            .iter()
+            .map(|owner| {
+                create_associated_token_account(&payer, owner, &base_mint.pubkey(), &spl_token::ID)
+            })
+            .collect();
+        process_tx(&mut self.context, ata_ixs, &[]).await.unwrap();
+
+        let (honorary_position, owner_pda) = position_pdas(&self.policy, index);
+        let position = PositionAccounts {
+            index,
+            cp_pool: cp_pool.pubkey(),
+            cp_position: cp_position.pubkey(),
+            base_mint: base_mint.pubkey(),
+            base_vault: get_associated_token_address(&self.pool_authority, &base_mint.pubkey()),
+            treasury_base_ata: get_associated_token_address(
+                &self.vault_authority,
+                &base_mint.pubkey(),
+            ),
+            honorary_position,
+            owner_pda,
+        };
+        let ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::InitHonoraryPosition {
+                authority: authority.pubkey(),
+                policy: self.policy,
+                cp_pool: position.cp_pool,
+                quote_mint: self.quote_mint,
+                owner_pda,
+                cp_position: position.cp_position,
+                honorary_position,
+                system_program: system_program::ID,
+                rent: sysvar::rent::ID,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::InitHonoraryPosition {}.data(),
+        };
+        process_tx(&mut self.context, vec![ix], &[&authority]).await?;
+        self.extra_positions.push(position);
+        Ok(position)
+    }
+
+    /// Funds the pool vaults and records pending (base, quote) fees on position 0.
+    async fn accrue_fees(&mut self, base_fee: u64, quote_fee: u64) {
+        self.accrue_position_fees(0, base_fee, quote_fee).await;
+    }
+
+    /// Funds the pool vaults and records pending (base, quote) fees on position `index`.
+    async fn accrue_position_fees(&mut self, index: u8, base_fee: u64, quote_fee: u64) {
+        let position = self.position(index);
+        let authority = self.authority.insecure_clone();
+        let mut ixs = Vec::new();
+        for (mint, vault, amount, token_program) in [
+            (
+                position.base_mint,
+                position.base_vault,
+                base_fee,
+                spl_token::ID,
+            ),
+            (
+                self.quote_mint,
+                self.quote_vault,
+                quote_fee,
+                self.quote_token_program,
+            ),
+        ] {
+            if amount > 0 {
+                ixs.push(
+                    spl_token_2022::instruction::mint_to(
+                        &token_program,
+                        &mint,
+                        &vault,
+                        &authority.pubkey(),
+                        &[],
+                        amount,
+                    )
+                    .unwrap(),
+                );
+            }
+        }
+        if !ixs.is_empty() {
+            process_tx(&mut self.context, ixs, &[&authority])
+                .await
+                .unwrap();
+        }
+        // Quote is token B in this pool, base is token A.
+        let mut data = base_fee.to_le_bytes().to_vec();
+        data.extend_from_slice(&quote_fee.to_le_bytes());
+        let mut account = AccountSharedData::new(
+            self.context
+                .banks_client
+                .get_rent()
+                .await
+                .unwrap()
+                .minimum_balance(16),
+            16,
+            &self.dlmm_program,
+        );
+        account.set_data_from_slice(&data);
+        self.context.set_account(&position.cp_position, &account);
+    }
+
+    /// Creates a mock Streamflow stream locking `locked_amount` for `recipient`.
+    async fn stream(&mut self, recipient: &Pubkey, locked_amount: u64) -> Pubkey {
+        let stream = Pubkey::new_unique();
+        self.set_stream(stream, &streamflow_program(), recipient, locked_amount)
+            .await;
+        stream
+    }
+
+    /// Writes stream data locking `locked_amount` for `recipient` to `stream`: the
+    /// mock layout (`locked_amount` LE, then `recipient`), or with the `streamflow`
+    /// feature a Streamflow `Contract` whose schedule has not started.
+    async fn set_stream(
+        &mut self,
+        stream: Pubkey,
+        owner: &Pubkey,
+        recipient: &Pubkey,
+        locked_amount: u64,
+    ) {
+        #[cfg(not(feature = "streamflow"))]
+        let data = {
+            let mut data = locked_amount.to_le_bytes().to_vec();
+            data.extend_from_slice(recipient.as_ref());
+            data
+        };
+        #[cfg(feature = "streamflow")]
+        let data = {
+            // Unlocks in one period starting 2100-01-01, long after any test clock.
+            const START: u64 = 4_102_444_800;
+            crate::stream_adapter::streamflow::StreamflowContract {
+                amount_withdrawn: 0,
+                canceled_at: 0,
+                end_time: START + 86_400,
+                recipient: *recipient,
+                mint: Pubkey::default(),
+                start_time: START,
+                net_amount_deposited: locked_amount,
+                period: 86_400,
+                amount_per_period: locked_amount,
+                cliff: 0,
+                cliff_amount: 0,
+                closed: false,
+                current_pause_start: 0,
+                pause_cumulative: 0,
+                last_rate_change_time: 0,
+                funds_unlocked_at_last_rate_change: 0,
+            }
+            .to_bytes()
+        };
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let mut account =
+            AccountSharedData::new(rent.minimum_balance(data.len()), data.len(), owner);
+        account.set_data_from_slice(&data);
+        self.context.set_account(&stream, &account);
+    }
+
+    /// Replaces the lock account of investor `index` with a `keystone_vesting`
+    /// schedule for `beneficiary` vesting `total` linearly over `[start, end]`.
+    async fn set_vesting_schedule(
+        &mut self,
+        index: usize,
+        beneficiary: Pubkey,
+        total: u64,
+        (start, cliff, end): (i64, i64, i64),
+    ) {
+        let schedule = keystone_vesting::VestingSchedule {
+            authority: self.authority.pubkey(),
+            beneficiary,
+            start,
+            cliff,
+            end,
+            total,
+            claimed: 0,
+            revocable: true,
+            merkle_root: None,
+            bump: 0,
+            initialized: true,
+        };
+        let mut data = keystone_vesting::VestingSchedule::DISCRIMINATOR.to_vec();
+        schedule.serialize(&mut data).unwrap();
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let mut account = AccountSharedData::new(
+            rent.minimum_balance(data.len()),
+            data.len(),
+            &keystone_vesting::ID,
+        );
+        account.set_data_from_slice(&data);
+        let (_, stream) = self.entry(index);
+        self.context.set_account(&stream, &account);
+    }
+
+    /// Creates a funded investor wallet and returns its quote ATA.
+    async fn investor(&mut self) -> Pubkey {
+        let investor = Keypair::new();
+        fund_accounts(&mut self.context, &[investor.pubkey()]).await;
+        let ata = self.quote_account(&investor.pubkey()).await;
+        self.investor_wallets.push(investor);
+        ata
+    }
+
+    /// Creates the quote ATA of `owner`.
+    async fn quote_account(&mut self, owner: &Pubkey) -> Pubkey {
+        let payer = self.context.payer.pubkey();
+        let ix = create_associated_token_account(
+            &payer,
+            owner,
+            &self.quote_mint,
+            &self.quote_token_program,
+        );
+        process_tx(&mut self.context, vec![ix], &[]).await.unwrap();
+        get_associated_token_address_with_program_id(
+            owner,
+            &self.quote_mint,
+            &self.quote_token_program,
+        )
+    }
+
+    fn crank_ix(&self, investor_ata: Pubkey, stream: Pubkey, args: CrankArgs) -> Instruction {
+        let (event_authority, _) =
+            Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], &self.dlmm_program);
+        let remainder_atas = if args.is_last_page {
+            self.remainder_metas()
+        } else {
+            Vec::new()
+        };
+        let position = self.position(args.position_index);
+        // Only payout pages take the payout bitmap; push-mode pages the dust ledger.
+        let payout = args.phase == DistributionPhase::Payout;
+        let push = self.claim_mode == ClaimMode::Push;
+        let mut ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::CrankDistribute {
+                cp_program: self.dlmm_program,
+                cp_pool: position.cp_pool,
+                cp_pool_authority: self.pool_authority,
+                policy: self.policy,
+                honorary_position: position.honorary_position,
+                owner_pda: position.owner_pda,
+                cp_position: position.cp_position,
+                position_nft_account: Pubkey::new_unique(),
+                token_a_vault: position.base_vault,
+                token_b_vault: self.quote_vault,
+                token_a_mint: position.base_mint,
+                token_b_mint: self.quote_mint,
+                event_authority,
+                progress: self.progress,
+                payout_bitmap: payout.then_some(self.payout_bitmap),
+                history: self.history,
+                payer: self.authority.pubkey(),
+                payer_quote_ata: self.payer_quote_ata,
+                vault_authority: self.vault_authority,
+                treasury_quote_ata: self.treasury_ata,
+                treasury_base_ata: position.treasury_base_ata,
+                creator_quote_ata: self.creator_ata,
+                investor_quote_ata: investor_ata,
+                stream,
+                dust_ledger: push.then(|| self.dust_ledger(investor_ata)),
+                checkpoint: Some(self.checkpoint(investor_ata)),
+                quote_mint: self.quote_mint,
+                token_program: self.quote_token_program,
+                base_token_program: spl_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::CrankDistribute { args }.data(),
+        };
+        ix.accounts.extend(remainder_atas);
+        ix
+    }
+
+    fn remainder_metas(&self) -> Vec<AccountMeta> {
+        self.remainder_atas
+            .iter()
+            .map(|ata| AccountMeta::new(*ata, false))
+            .collect()
+    }
+
+    fn dust_ledger(&self, investor_ata: Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                crate::DUST_LEDGER_SEED,
+                self.policy.as_ref(),
+                investor_ata.as_ref(),
+            ],
+            &crate::id(),
+        )
+        .0
+    }
+
+    fn checkpoint(&self, investor_ata: Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                crate::CHECKPOINT_SEED,
+                self.policy.as_ref(),
+                investor_ata.as_ref(),
+            ],
+            &crate::id(),
+        )
+        .0
+    }
+
+    /// Per-entry PDA of a remaining-accounts page entry for the policy's claim mode.
+    fn entry_pda(&self, investor_ata: Pubkey) -> Pubkey {
+        match self.claim_mode {
+            ClaimMode::Push => self.dust_ledger(investor_ata),
+            ClaimMode::Pull => self.checkpoint(investor_ata),
+        }
+    }
+
+    async fn checkpoint_state(&mut self, investor_ata: Pubkey) -> crate::InvestorCheckpoint {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.checkpoint(investor_ata))
+            .await
+            .unwrap()
+            .unwrap();
+        crate::InvestorCheckpoint::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    async fn dust_ledger_state(&mut self, investor_ata: Pubkey) -> crate::DustLedger {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.dust_ledger(investor_ata))
+            .await
+            .unwrap()
+            .expect("dust ledger account");
+        crate::DustLedger::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    /// Returns the `(quote ATA, stream)` entry of investor `index`.
+    fn entry(&self, index: usize) -> (Pubkey, Pubkey) {
+        self.investors[index]
+    }
+
+    /// Runs one crank page holding investor `index` with its Merkle proof; snapshot
+    /// pages collect position 0.
+    async fn crank(
+        &mut self,
+        phase: DistributionPhase,
+        index: usize,
+        is_last_page: bool,
+    ) -> std::result::Result<(), TransactionError> {
+        self.crank_position(phase, index, is_last_page, 0).await
+    }
+
+    /// Like `crank`, collecting honorary position `position_index` on snapshot pages.
+    async fn crank_position(
+        &mut self,
+        phase: DistributionPhase,
+        index: usize,
+        is_last_page: bool,
+        position_index: u8,
+    ) -> std::result::Result<(), TransactionError> {
+        let (investor_ata, stream) = self.entry(index);
+        let args = CrankArgs {
+            page_cursor: index as u64,
+            is_last_page,
+            phase,
+            position_index,
+            proofs: vec![merkle_proof(&self.investor_tree, index)],
+        };
+        self.crank_raw(investor_ata, stream, args).await
+    }
+
+    /// Runs one crank page holding investors `cursor..cursor + len`; entries after the
+    /// first go to `remaining_accounts` with their dust ledgers (checkpoints in pull
+    /// mode). Snapshot pages collect
+    /// position 0. Returns the compute units consumed.
+    async fn crank_page(
+        &mut self,
+        phase: DistributionPhase,
+        cursor: usize,
+        len: usize,
+        is_last_page: bool,
+    ) -> std::result::Result<u64, TransactionError> {
+        let (investor_ata, stream) = self.entry(cursor);
+        let args = CrankArgs {
+            page_cursor: cursor as u64,
+            is_last_page,
+            phase,
+            position_index: 0,
+            proofs: (cursor..cursor + len)
+                .map(|index| merkle_proof(&self.investor_tree, index))
+                .collect(),
+        };
+        let mut ix = self.crank_ix(investor_ata, stream, args);
+        let extra_entries = self.investors[cursor + 1..cursor + len]
+            .iter()
+            .flat_map(|&(ata, stream)| {
+                [
+                    AccountMeta::new(ata, false),
+                    AccountMeta::new_readonly(stream, false),
+                    AccountMeta::new(self.entry_pda(ata), false),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let entries_end = ix.accounts.len() - self.remainder_metas().len() * is_last_page as usize;
+        ix.accounts.splice(entries_end..entries_end, extra_entries);
+        let authority = self.authority.insecure_clone();
+        process_tx_with_units(&mut self.context, vec![ix], &[&authority]).await
+    }
+
+    /// Claims honorary position `index` into today's snapshot via `collect_position_fees`.
+    async fn collect_position_fees(
+        &mut self,
+        index: u8,
+    ) -> std::result::Result<(), TransactionError> {
+        let (event_authority, _) =
+            Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], &self.dlmm_program);
+        let position = self.position(index);
+        let ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::CollectPositionFees {
+                cp_program: self.dlmm_program,
+                cp_pool: position.cp_pool,
+                cp_pool_authority: self.pool_authority,
+                policy: self.policy,
+                honorary_position: position.honorary_position,
+                owner_pda: position.owner_pda,
+                cp_position: position.cp_position,
+                position_nft_account: Pubkey::new_unique(),
+                token_a_vault: position.base_vault,
+                token_b_vault: self.quote_vault,
+                token_a_mint: position.base_mint,
+                token_b_mint: self.quote_mint,
+                event_authority,
+                progress: self.progress,
+                payer: self.context.payer.pubkey(),
+                vault_authority: self.vault_authority,
+                treasury_quote_ata: self.treasury_ata,
+                treasury_base_ata: position.treasury_base_ata,
+                token_program: self.quote_token_program,
+                base_token_program: spl_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::CollectPositionFees {
+                args: CollectPositionFeesArgs {
+                    position_index: index,
+                },
+            }
+            .data(),
+        };
+        process_tx(&mut self.context, vec![ix], &[]).await
+    }
+
+    /// Runs one crank page with caller-supplied entry and arguments.
+    async fn crank_raw(
+        &mut self,
+        investor_ata: Pubkey,
+        stream: Pubkey,
+        args: CrankArgs,
+    ) -> std::result::Result<(), TransactionError> {
+        let ix = self.crank_ix(investor_ata, stream, args);
+        let authority = self.authority.insecure_clone();
+        process_tx(&mut self.context, vec![ix], &[&authority]).await
+    }
+
+    /// Pull-mode claim by investor `index`, signed by its wallet.
+    async fn claim_fees(&mut self, index: usize) -> std::result::Result<(), TransactionError> {
+        let (investor_ata, stream) = self.entry(index);
+        let investor = self.investor_wallets[index].insecure_clone();
+        let checkpoint = self.checkpoint(investor_ata);
+        let ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::ClaimFees {
+                investor: investor.pubkey(),
+                policy: self.policy,
+                progress: self.progress,
+                checkpoint,
+                vault_authority: self.vault_authority,
+                treasury_quote_ata: self.treasury_ata,
+                investor_quote_ata: investor_ata,
+                stream,
+                quote_mint: self.quote_mint,
+                token_program: self.quote_token_program,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::ClaimFees {
+                args: ClaimFeesArgs {
+                    index: index as u32,
+                    proof: merkle_proof(&self.investor_tree, index),
+                },
+            }
+            .data(),
+        };
+        process_tx(&mut self.context, vec![ix], &[&investor]).await
+    }
+
+    async fn token_balance(&mut self, address: Pubkey) -> u64 {
+        let account = self
+            .context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .expect("token account");
+        StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+            .unwrap()
+            .base
+            .amount
+    }
+
+    async fn progress_state(&mut self) -> crate::Progress {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.progress)
+            .await
+            .unwrap()
+            .expect("progress account");
+        crate::Progress::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    async fn propose_policy_update(
+        &mut self,
+        args: PolicyUpdateArgs,
+    ) -> std::result::Result<(), TransactionError> {
+        let recipient_metas = recipient_metas(&args.remainder_recipients);
+        let mut ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::ProposePolicyUpdate {
+                authority: self.authority.pubkey(),
+                policy: self.policy,
+                creator_quote_ata: self.creator_ata,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::ProposePolicyUpdate { args }.data(),
+        };
+        ix.accounts.extend(recipient_metas);
+        let authority = self.authority.insecure_clone();
+        process_tx(&mut self.context, vec![ix], &[&authority]).await
+    }
+
+    async fn apply_policy_update(&mut self) -> std::result::Result<(), TransactionError> {
+        let ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::ApplyPolicyUpdate {
+                authority: self.authority.pubkey(),
+                policy: self.policy,
+                progress: self.progress,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::ApplyPolicyUpdate {}.data(),
+        };
+        let authority = self.authority.insecure_clone();
+        process_tx(&mut self.context, vec![ix], &[&authority]).await
+    }
+
+    /// Sends `pause_policy` (or `unpause_policy` when `paused` is false) signed by `signer`.
+    async fn set_paused(
+        &mut self,
+        signer: &Keypair,
+        paused: bool,
+    ) -> std::result::Result<(), TransactionError> {
+        let data = if paused {
+            crate::instruction::PausePolicy {}.data()
+        } else {
+            crate::instruction::UnpausePolicy {}.data()
+        };
+        let ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::SetPaused {
+                signer: signer.pubkey(),
+                policy: self.policy,
+            }
+            .to_account_metas(None),
+            data,
+        };
+        process_tx(&mut self.context, vec![ix], &[signer]).await
+    }
+
+    async fn force_close_day(&mut self) -> std::result::Result<(), TransactionError> {
+        let mut ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::ForceCloseDay {
+                policy: self.policy,
+                progress: self.progress,
+                history: self.history,
+                vault_authority: self.vault_authority,
+                treasury_quote_ata: self.treasury_ata,
+                creator_quote_ata: self.creator_ata,
+                quote_mint: self.quote_mint,
+                token_program: self.quote_token_program,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::ForceCloseDay {}.data(),
+        };
+        ix.accounts.extend(self.remainder_metas());
+        process_tx(&mut self.context, vec![ix], &[]).await
+    }
+
+    async fn close_policy(
+        &mut self,
+        signer: &Keypair,
+        close_position: bool,
+    ) -> std::result::Result<(), TransactionError> {
+        let (event_authority, _) =
+            Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], &self.dlmm_program);
+        let ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::ClosePolicy {
+                authority: signer.pubkey(),
+                policy: self.policy,
+                progress: self.progress,
+                payout_bitmap: self.payout_bitmap,
+                history: self.history,
+                honorary_position: self.honorary_position,
+                owner_pda: self.owner_pda,
+                vault_authority: self.vault_authority,
+                treasury_quote_ata: self.treasury_ata,
+                creator_quote_ata: self.creator_ata,
+                quote_mint: self.quote_mint,
+                cp_program: self.dlmm_program,
+                cp_pool: self.cp_pool,
+                cp_pool_authority: self.pool_authority,
+                cp_position: self.cp_position,
+                position_nft_mint: Pubkey::new_unique(),
+                position_nft_account: Pubkey::new_unique(),
+                nft_token_program: spl_token::ID,
+                event_authority,
+                token_program: self.quote_token_program,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::ClosePolicy {
+                args: ClosePolicyArgs { close_position },
+            }
+            .data(),
+        };
+        process_tx(&mut self.context, vec![ix], &[signer]).await
+    }
+
+    /// Pays out and closes the dust ledger of investor `index` via `release_dust`.
+    async fn release_dust(&mut self, index: usize) -> std::result::Result<(), TransactionError> {
+        let (investor_ata, _) = self.entry(index);
+        let ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::ReleaseDust {
+                authority: self.authority.pubkey(),
+                policy: self.policy,
+                progress: self.progress,
+                dust_ledger: self.dust_ledger(investor_ata),
+                investor_quote_ata: investor_ata,
+                creator_quote_ata: self.creator_ata,
+                vault_authority: self.vault_authority,
+                treasury_quote_ata: self.treasury_ata,
+                quote_mint: self.quote_mint,
+                token_program: self.quote_token_program,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::ReleaseDust {}.data(),
+        };
+        let authority = self.authority.insecure_clone();
+        process_tx(&mut self.context, vec![ix], &[&authority]).await
+    }
+
+    /// Unregisters the last added honorary position and closes its cp-amm position.
+    async fn close_honorary_position(&mut self) -> std::result::Result<(), TransactionError> {
+        let (event_authority, _) =
+            Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], &self.dlmm_program);
+        let position = *self.extra_positions.last().expect("extra position");
+        let ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::CloseHonoraryPosition {
+                authority: self.authority.pubkey(),
+                policy: self.policy,
+                progress: self.progress,
+                honorary_position: position.honorary_position,
+                owner_pda: position.owner_pda,
+                cp_program: self.dlmm_program,
+                cp_pool: position.cp_pool,
+                cp_pool_authority: self.pool_authority,
+                cp_position: position.cp_position,
+                position_nft_mint: Pubkey::new_unique(),
+                position_nft_account: Pubkey::new_unique(),
+                nft_token_program: spl_token::ID,
+                event_authority,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::CloseHonoraryPosition {
+                args: CloseHonoraryPositionArgs {
+                    close_position: true,
+                },
+            }
+            .data(),
+        };
+        let authority = self.authority.insecure_clone();
+        process_tx(&mut self.context, vec![ix], &[&authority]).await?;
+        self.extra_positions.pop();
+        Ok(())
+    }
+
+    /// Moves the cluster clock forward by `seconds`.
+    async fn warp_seconds(&mut self, seconds: i64) {
+        let clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        self.warp_to(clock.unix_timestamp + seconds).await;
+    }
+
+    /// Sets the cluster clock to `unix_timestamp`.
+    async fn warp_to(&mut self, unix_timestamp: i64) {
+        let mut clock: Clock = self.context.banks_client.get_sysvar().await.unwrap();
+        clock.unix_timestamp = unix_timestamp;
+        self.context.set_sysvar(&clock);
+    }
+
+    async fn policy_state(&mut self) -> crate::Policy {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.policy)
+            .await
+            .unwrap()
+            .expect("policy account");
+        crate::Policy::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    async fn payout_bitmap_state(&mut self) -> crate::PayoutBitmap {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.payout_bitmap)
+            .await
+            .unwrap()
+            .expect("payout bitmap account");
+        crate::PayoutBitmap::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    async fn history_state(&mut self) -> crate::DistributionHistory {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.history)
+            .await
+            .unwrap()
+            .expect("history account");
+        crate::DistributionHistory::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+}
+
+/// Remainder recipients' quote ATAs as passed to `init_policy` and
+/// `propose_policy_update`.
+fn recipient_metas(recipients: &[RemainderRecipient]) -> Vec<AccountMeta> {
+    recipients
+        .iter()
+        .map(|recipient| AccountMeta::new_readonly(recipient.quote_ata, false))
+        .collect()
+}
+
+fn policy_args() -> InitPolicyArgs {
+    InitPolicyArgs {
+        guardian: Pubkey::new_unique(),
+        y0_total: 1_000_000,
+        investor_fee_share_bps: 2_000,
+        daily_cap_quote: 0,
+        min_payout_lamports: 100,
+        crank_reward_bps: 0,
+        crank_reward_cap_quote: 0,
+        investor_set: InvestorSet::default(),
+        force_close_grace_secs: 3_600,
+        roll_forced_carry: false,
+        claim_mode: ClaimMode::Push,
+        lock_source: LockSource::Streamflow,
+        remainder_recipients: Vec::new(),
+        epoch_seconds: 86_400,
+        epoch_offset_seconds: 0,
+        max_page_size: 8,
+    }
+}
+
+fn policy_update_args() -> PolicyUpdateArgs {
+    let args = policy_args();
+    PolicyUpdateArgs {
+        y0_total: args.y0_total,
+        investor_fee_share_bps: args.investor_fee_share_bps,
+        daily_cap_quote: args.daily_cap_quote,
+        min_payout_lamports: args.min_payout_lamports,
+        crank_reward_bps: args.crank_reward_bps,
+        crank_reward_cap_quote: args.crank_reward_cap_quote,
+        force_close_grace_secs: args.force_close_grace_secs,
+        roll_forced_carry: args.roll_forced_carry,
+        remainder_recipients: args.remainder_recipients,
+    }
+}
 
 #[tokio::test]
 async fn host_integration_flow() {
-    let mut test = ProgramTest::new(
-        "keystone_fee_router",
-        crate::id(),
-        processor!(crate::entry),
+    let mut h = Harness::setup(policy_args(), &[150_000, 50_000]).await;
+    h.accrue_fees(0, 5_000).await;
+    let (investor_a, _) = h.entry(0);
+    let (investor_b, _) = h.entry(1);
+
+    // Snapshot pass: 200_000 of Y0 locked -> 20% investor pool of 5_000 claimed.
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    assert_eq!(h.token_balance(h.quote_vault).await, 0);
+    assert_eq!(h.token_balance(investor_a).await, 0);
+    h.crank(DistributionPhase::Snapshot, 1, true).await.unwrap();
+    let progress = h.progress_state().await;
+    assert_eq!(progress.locked_total_today, 200_000);
+    assert_eq!(progress.investor_pool_today, 1_000);
+    assert_eq!(progress.phase, DistributionPhase::Payout);
+    assert_eq!(progress.page_cursor, 0);
+    // Snapshot pages record each locked amount in the dust ledger; the payout bitmap
+    // waits for the payout pass.
+    assert_eq!(
+        h.dust_ledger_state(investor_a).await.locked_snapshot,
+        150_000
     );
-    test.prefer_builtins = true;
-
-    let mut context = test.start_with_context().await;
-
-    let authority = Keypair::new();
-    let creator = Keypair::new();
-    let investor = Keypair::new();
-    let cp_pool = Keypair::new();
-    let cp_position = Keypair::new();
-    let quote_mint = Keypair::new();
-    let stream_locked = Keypair::new();
-    let stream_empty = Keypair::new();
-
-    fund_accounts(
-        &mut context,
-        &[authority.pubkey(), creator.pubkey(), investor.pubkey(), cp_pool.pubkey(), cp_position.pubkey()],
+    let bitmap = h.context.banks_client.get_account(h.payout_bitmap).await;
+    assert!(bitmap.unwrap().is_none());
+
+    // Payout pass: shares follow locked_i / locked_total.
+    h.crank(DistributionPhase::Payout, 0, false).await.unwrap();
+    assert_eq!(h.token_balance(investor_a).await, 750);
+    // Replaying the page does not pay twice.
+    let err = h
+        .crank(DistributionPhase::Payout, 0, false)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::InvalidInvestorPage);
+    assert_eq!(h.token_balance(investor_a).await, 750);
+    h.crank(DistributionPhase::Payout, 1, true).await.unwrap();
+    assert_eq!(h.token_balance(investor_b).await, 250);
+
+    let progress = h.progress_state().await;
+    assert_eq!(progress.claimed_quote_today, 5_000);
+    assert_eq!(progress.distributed_quote_today, 1_000);
+    assert_eq!(progress.carry_quote_today, 0);
+    assert!(progress.day_closed);
+    assert_eq!(h.token_balance(h.creator_ata).await, 4_000);
+    assert_eq!(h.token_balance(h.treasury_ata).await, 0);
+    let bitmap = h.payout_bitmap_state().await;
+    assert_eq!(bitmap.day, progress.current_day);
+    assert!(bitmap.is_set(0) && bitmap.is_set(1));
+    let record = h.history_state().await.latest().unwrap();
+    assert_eq!(record.day, progress.current_day);
+    assert_eq!(record.claimed_quote, 5_000);
+    assert_eq!(record.investor_quote, 1_000);
+    assert_eq!(record.creator_remainder, 4_000);
+    assert_eq!(record.investor_count, 2);
+    assert!(!record.force_closed);
+    assert!(record.closed_slot > 0);
+
+    // The day is settled; further pages wait for the next day.
+    let err = h
+        .crank(DistributionPhase::Payout, 1, true)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::DailyWindowNotReady);
+}
+
+#[tokio::test]
+async fn payout_page_requires_closed_snapshot() {
+    let mut h = Harness::setup(policy_args(), &[200_000, 100_000]).await;
+    h.accrue_fees(0, 5_000).await;
+
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    let err = h
+        .crank(DistributionPhase::Payout, 1, false)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::WrongPhase);
+    assert_eq!(h.token_balance(h.entry(0).0).await, 0);
+}
+
+#[tokio::test]
+async fn pages_follow_investor_set_order() {
+    let mut h = Harness::setup(policy_args(), &[100_000, 100_000, 100_000]).await;
+    h.accrue_fees(0, 3_000).await;
+
+    // Skipping ahead is rejected.
+    let err = h
+        .crank(DistributionPhase::Snapshot, 1, false)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::InvalidInvestorPage);
+
+    // A stranger's entry does not verify against the committed root.
+    let (_, stream) = h.entry(0);
+    let stranger = h.investor().await;
+    let err = h
+        .crank_raw(
+            stranger,
+            stream,
+            CrankArgs {
+                page_cursor: 0,
+                is_last_page: false,
+                phase: DistributionPhase::Snapshot,
+                position_index: 0,
+                proofs: vec![merkle_proof(&h.investor_tree, 0)],
+            },
+        )
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::InvalidInvestorProof);
+
+    // Closing the pass before the last index is rejected.
+    let err = h
+        .crank(DistributionPhase::Snapshot, 0, true)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::InvalidInvestorPage);
+
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    // Repeating a page is rejected.
+    let err = h
+        .crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::InvalidInvestorPage);
+
+    h.crank(DistributionPhase::Snapshot, 1, false)
+        .await
+        .unwrap();
+    // The final index must close the pass.
+    let err = h
+        .crank(DistributionPhase::Snapshot, 2, false)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::InvalidInvestorPage);
+    h.crank(DistributionPhase::Snapshot, 2, true).await.unwrap();
+
+    let progress = h.progress_state().await;
+    assert_eq!(progress.locked_total_today, 300_000);
+    assert_eq!(progress.phase, DistributionPhase::Payout);
+}
+
+#[tokio::test]
+async fn guardian_pause_holds_collected_fees_in_the_treasury() {
+    let guardian = Keypair::new();
+    let mut h = Harness::setup(
+        InitPolicyArgs {
+            guardian: guardian.pubkey(),
+            ..policy_args()
+        },
+        &[200_000],
     )
     .await;
+    h.accrue_fees(0, 5_000).await;
+    h.crank(DistributionPhase::Snapshot, 0, true).await.unwrap();
 
-    let rent = context.banks_client.get_rent().await.unwrap();
-    let dlmm_program = Pubkey::from_str(DLMM_ID).unwrap();
-
-    create_unchecked_account(
-        &mut context,
-        &cp_pool,
-        &dlmm_program,
-        rent.minimum_balance(0),
-        0,
+    let err = h.set_paused(&Keypair::new(), true).await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::Unauthorized);
+    // The authority may pause, but only the guardian lifts a pause.
+    let authority = h.authority.insecure_clone();
+    h.set_paused(&authority, true).await.unwrap();
+    assert!(h.policy_state().await.paused);
+    let err = h.set_paused(&guardian, true).await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::ConstraintViolation);
+
+    let err = h
+        .crank(DistributionPhase::Payout, 0, true)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::Paused);
+    let err = h
+        .propose_policy_update(policy_update_args())
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::Paused);
+    assert_eq!(h.token_balance(h.treasury_ata).await, 5_000);
+    assert_eq!(h.token_balance(h.entry(0).0).await, 0);
+
+    let err = h.set_paused(&authority, false).await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::Unauthorized);
+    h.set_paused(&guardian, false).await.unwrap();
+    h.crank(DistributionPhase::Payout, 0, true).await.unwrap();
+    assert_eq!(h.token_balance(h.entry(0).0).await, 1_000);
+    assert_eq!(h.token_balance(h.creator_ata).await, 4_000);
+}
+
+#[tokio::test]
+async fn policy_update_is_timelocked_to_a_closed_day() {
+    let mut h = Harness::setup(policy_args(), &[200_000]).await;
+    h.propose_policy_update(PolicyUpdateArgs {
+        y0_total: 400_000,
+        investor_fee_share_bps: 5_000,
+        min_payout_lamports: 0,
+        ..policy_update_args()
+    })
+    .await
+    .unwrap();
+    assert!(h.policy_state().await.pending_update.is_some());
+
+    // The minimum delay has not elapsed.
+    let err = h.apply_policy_update().await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::PolicyUpdateNotReady);
+
+    // Delay elapsed, but a distribution day is open.
+    h.warp_seconds(crate::POLICY_UPDATE_MIN_DELAY_SECS).await;
+    h.accrue_fees(0, 5_000).await;
+    h.crank(DistributionPhase::Snapshot, 0, true).await.unwrap();
+    let err = h.apply_policy_update().await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::DailyWindowNotReady);
+
+    // The open day settles under the old parameters.
+    h.crank(DistributionPhase::Payout, 0, true).await.unwrap();
+    assert_eq!(h.token_balance(h.entry(0).0).await, 1_000);
+
+    let before = h.policy_state().await.config_hash();
+    h.apply_policy_update().await.unwrap();
+    let policy = h.policy_state().await;
+    assert_eq!(policy.y0_total, 400_000);
+    assert_eq!(policy.investor_fee_share_bps, 5_000);
+    assert_eq!(policy.min_payout_lamports, 0);
+    assert_eq!(policy.pending_update, None);
+    assert_ne!(policy.config_hash(), before);
+
+    // Nothing left to apply.
+    let err = h.apply_policy_update().await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::PolicyUpdateNotReady);
+}
+
+#[tokio::test]
+async fn crank_reward_is_paid_before_the_split() {
+    let mut h = Harness::setup(
+        InitPolicyArgs {
+            crank_reward_bps: 100,
+            crank_reward_cap_quote: 30,
+            max_page_size: 1,
+            ..policy_args()
+        },
+        &[150_000, 50_000],
     )
     .await;
-    create_unchecked_account(
-        &mut context,
-        &cp_position,
-        &dlmm_program,
-        rent.minimum_balance(0),
-        0,
+    let authority = h.authority.pubkey();
+    let payer_ata = h.quote_account(&authority).await;
+    h.accrue_fees(0, 5_000).await;
+
+    // 1% of 5_000 capped at 30, split over four pages: 7 each, 28 off the top.
+    // Investors split 20% of 4_972.
+    h.payer_quote_ata = Some(payer_ata);
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    assert_eq!(h.token_balance(payer_ata).await, 7);
+    let progress = h.progress_state().await;
+    assert_eq!(progress.crank_reward_per_page, 7);
+    assert_eq!(progress.crank_reward_today, 28);
+
+    // A page without a payer quote account leaves its cut in the remainder.
+    h.payer_quote_ata = None;
+    h.crank(DistributionPhase::Snapshot, 1, true).await.unwrap();
+    assert_eq!(h.token_balance(payer_ata).await, 7);
+    assert_eq!(h.progress_state().await.investor_pool_today, 994);
+
+    h.payer_quote_ata = Some(payer_ata);
+    h.crank(DistributionPhase::Payout, 0, false).await.unwrap();
+    assert_eq!(h.token_balance(payer_ata).await, 14);
+    h.payer_quote_ata = None;
+    h.crank(DistributionPhase::Payout, 1, true).await.unwrap();
+    assert_eq!(h.token_balance(payer_ata).await, 14);
+
+    let progress = h.progress_state().await;
+    assert_eq!(progress.crank_reward_paid_today, 14);
+    assert_eq!(progress.distributed_quote_today, 745 + 248);
+    assert_eq!(h.token_balance(h.entry(0).0).await, 745);
+    assert_eq!(h.token_balance(h.entry(1).0).await, 248);
+    assert_eq!(h.token_balance(h.creator_ata).await, 5_000 - 993 - 14);
+    assert_eq!(h.token_balance(h.treasury_ata).await, 0);
+}
+
+#[tokio::test]
+async fn crank_reward_covers_every_position() {
+    let mut h = Harness::setup(
+        InitPolicyArgs {
+            crank_reward_bps: 100,
+            max_page_size: 1,
+            ..policy_args()
+        },
+        &[150_000, 50_000],
     )
     .await;
+    h.add_position().await.unwrap();
+    let authority = h.authority.pubkey();
+    let payer_ata = h.quote_account(&authority).await;
+    h.accrue_position_fees(0, 0, 5_000).await;
+    h.accrue_position_fees(1, 0, 3_000).await;
+    h.payer_quote_ata = Some(payer_ata);
 
-    create_mint(&mut context, &quote_mint, &authority, rent.minimum_balance(Mint::LEN)).await;
+    // 1% of both positions' 8_000 over four pages, not of the first page's 5_000.
+    h.collect_position_fees(1).await.unwrap();
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    let progress = h.progress_state().await;
+    assert_eq!(progress.crank_reward_per_page, 20);
+    assert_eq!(progress.crank_reward_today, 80);
+    assert_eq!(h.token_balance(payer_ata).await, 20);
 
-    let (policy_pda, _) = Pubkey::find_program_address(
-        &[crate::POLICY_SEED, cp_pool.pubkey().as_ref()],
-        &crate::id(),
-    );
-    let (vault_authority, _) = Pubkey::find_program_address(
-        &[crate::VAULT_SEED, policy_pda.as_ref()],
-        &crate::id(),
-    );
-    let (progress_pda, _) = Pubkey::find_program_address(
-        &[crate::PROGRESS_SEED, cp_pool.pubkey().as_ref()],
-        &crate::id(),
-    );
-    let (position_pda, _) = Pubkey::find_program_address(
-        &[crate::POSITION_SEED, policy_pda.as_ref()],
-        &crate::id(),
-    );
-    let (owner_pda, _) = Pubkey::find_program_address(
-        &[
-            crate::VAULT_SEED,
-            policy_pda.as_ref(),
-            crate::FEE_POS_OWNER_SEED,
-        ],
-        &crate::id(),
-    );
+    h.crank(DistributionPhase::Snapshot, 1, true).await.unwrap();
+    h.crank(DistributionPhase::Payout, 0, false).await.unwrap();
+    h.crank(DistributionPhase::Payout, 1, true).await.unwrap();
+    assert_eq!(h.token_balance(payer_ata).await, 80);
+    // Investors split 20% of 7_920.
+    assert_eq!(h.progress_state().await.investor_pool_today, 1_584);
+    assert_eq!(h.token_balance(h.treasury_ata).await, 0);
+}
+
+#[tokio::test]
+async fn pull_snapshot_pages_each_earn_the_crank_reward() {
+    let mut h = Harness::setup(
+        InitPolicyArgs {
+            claim_mode: ClaimMode::Pull,
+            crank_reward_bps: 100,
+            crank_reward_cap_quote: 30,
+            max_page_size: 1,
+            ..policy_args()
+        },
+        &[150_000, 50_000],
+    )
+    .await;
+    let authority = h.authority.pubkey();
+    let payer_ata = h.quote_account(&authority).await;
+    h.accrue_fees(0, 5_000).await;
+    h.payer_quote_ata = Some(payer_ata);
+
+    // One pass of two pages: 15 each, the closing page no more than the first.
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    assert_eq!(h.token_balance(payer_ata).await, 15);
+    h.crank(DistributionPhase::Snapshot, 1, true).await.unwrap();
+    assert_eq!(h.token_balance(payer_ata).await, 30);
 
-    let treasury_ata = spl_associated_token_account::get_associated_token_address(&vault_authority, &quote_mint.pubkey());
-    let creator_ata = spl_associated_token_account::get_associated_token_address(&creator.pubkey(), &quote_mint.pubkey());
-    let investor_ata = spl_associated_token_account::get_associated_token_address(&investor.pubkey(), &quote_mint.pubkey());
+    let progress = h.progress_state().await;
+    assert!(progress.day_closed);
+    assert_eq!(progress.crank_reward_paid_today, 30);
+    assert_eq!(progress.pull_reserve_quote(), 994);
+    assert_eq!(h.token_balance(h.creator_ata).await, 5_000 - 994 - 30);
+}
 
-    let ata_ixs = vec![
-        create_associated_token_account(&context.payer.pubkey(), &vault_authority, &quote_mint.pubkey(), &spl_token::ID),
-        create_associated_token_account(&context.payer.pubkey(), &creator.pubkey(), &quote_mint.pubkey(), &spl_token::ID),
-        create_associated_token_account(&context.payer.pubkey(), &investor.pubkey(), &quote_mint.pubkey(), &spl_token::ID),
+#[tokio::test]
+async fn remainder_is_split_across_recipients() {
+    let mut h = Harness::setup(policy_args(), &[200_000]).await;
+    let creator_ata = h.creator_ata;
+    let protocol_ata = h.quote_account(&Pubkey::new_unique()).await;
+    let marketing_ata = h.quote_account(&Pubkey::new_unique()).await;
+    let recipients = vec![
+        RemainderRecipient {
+            quote_ata: creator_ata,
+            bps: 5_000,
+        },
+        RemainderRecipient {
+            quote_ata: protocol_ata,
+            bps: 3_000,
+        },
+        RemainderRecipient {
+            quote_ata: marketing_ata,
+            bps: 2_000,
+        },
     ];
-    process_tx(&mut context, ata_ixs, &[&context.payer]).await;
 
-    init_stream_account(&mut context, &stream_locked, 200_000, rent.minimum_balance(8)).await;
-    init_stream_account(&mut context, &stream_empty, 0, rent.minimum_balance(8)).await;
+    // Splits must cover exactly 10_000 bps.
+    let mut short = recipients.clone();
+    short[2].bps = 1_999;
+    let err = h
+        .propose_policy_update(PolicyUpdateArgs {
+            remainder_recipients: short,
+            ..policy_update_args()
+        })
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::ConstraintViolation);
 
-    let init_policy_ix = Instruction {
-        program_id: crate::id(),
-        accounts: crate::accounts::InitPolicy {
-            authority: authority.pubkey(),
-            policy: policy_pda,
-            cp_pool: cp_pool.pubkey(),
-            quote_mint: quote_mint.pubkey(),
-            creator_quote_ata: creator_ata,
-            treasury_quote_ata: treasury_ata,
-            vault_authority,
-            token_program: spl_token::ID,
-            system_program: system_program::ID,
-            rent: sysvar::rent::ID,
-        }
-        .to_account_metas(None),
-        data: crate::instruction::InitPolicy {
-            args: InitPolicyArgs {
-                y0_total: 1_000_000,
-                investor_fee_share_bps: 2_000,
-                daily_cap_quote: 0,
-                min_payout_lamports: 100,
+    h.propose_policy_update(PolicyUpdateArgs {
+        remainder_recipients: recipients,
+        ..policy_update_args()
+    })
+    .await
+    .unwrap();
+    h.warp_seconds(crate::POLICY_UPDATE_MIN_DELAY_SECS).await;
+    h.apply_policy_update().await.unwrap();
+    assert_eq!(h.policy_state().await.remainder_split.count, 3);
+
+    // 5_001 claimed, 1_000 to the investor: 4_001 split 50/30/20, the last
+    // recipient taking the rounding dust.
+    h.accrue_fees(0, 5_001).await;
+    // Last pages carry the recipients, which must be in policy order when paid.
+    h.remainder_atas = vec![creator_ata, marketing_ata, protocol_ata];
+    h.crank(DistributionPhase::Snapshot, 0, true).await.unwrap();
+    let err = h
+        .crank(DistributionPhase::Payout, 0, true)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::ConstraintViolation);
+    h.remainder_atas = vec![creator_ata, protocol_ata, marketing_ata];
+    h.crank(DistributionPhase::Payout, 0, true).await.unwrap();
+    assert_eq!(h.token_balance(h.entry(0).0).await, 1_000);
+    assert_eq!(h.token_balance(creator_ata).await, 2_000);
+    assert_eq!(h.token_balance(protocol_ata).await, 1_200);
+    assert_eq!(h.token_balance(marketing_ata).await, 801);
+    assert_eq!(h.token_balance(h.treasury_ata).await, 0);
+}
+
+#[tokio::test]
+async fn remainder_reroute_unsticks_an_open_day() {
+    let mut h = Harness::setup(policy_args(), &[200_000]).await;
+    let creator_ata = h.creator_ata;
+    let partner = Keypair::new();
+    fund_accounts(&mut h.context, &[partner.pubkey()]).await;
+    let partner_ata = h.quote_account(&partner.pubkey()).await;
+    let split = |partner_ata| {
+        vec![
+            RemainderRecipient {
+                quote_ata: creator_ata,
+                bps: 5_000,
             },
-        }
-        .data(),
+            RemainderRecipient {
+                quote_ata: partner_ata,
+                bps: 5_000,
+            },
+        ]
     };
-    process_tx(&mut context, vec![init_policy_ix], &[&context.payer, &authority]).await;
 
-    let init_position_ix = Instruction {
-        program_id: crate::id(),
-        accounts: crate::accounts::InitHonoraryPosition {
-            authority: authority.pubkey(),
-            policy: policy_pda,
-            cp_pool: cp_pool.pubkey(),
-            quote_mint: quote_mint.pubkey(),
-            owner_pda,
-            cp_position: cp_position.pubkey(),
-            honorary_position: position_pda,
-            system_program: system_program::ID,
-            rent: sysvar::rent::ID,
-        }
-        .to_account_metas(None),
-        data: crate::instruction::InitHonoraryPosition {}.data(),
-    };
-    process_tx(&mut context, vec![init_position_ix], &[&context.payer, &authority]).await;
+    // Recipients must be quote token accounts: not a missing account, not another mint.
+    for bad in [Pubkey::new_unique(), h.treasury_base_ata] {
+        let err = h
+            .propose_policy_update(PolicyUpdateArgs {
+                remainder_recipients: split(bad),
+                ..policy_update_args()
+            })
+            .await
+            .unwrap_err();
+        assert_custom_error(err, FeeRouterError::ConstraintViolation);
+    }
+    h.propose_policy_update(PolicyUpdateArgs {
+        remainder_recipients: split(partner_ata),
+        ..policy_update_args()
+    })
+    .await
+    .unwrap();
+    h.warp_seconds(crate::POLICY_UPDATE_MIN_DELAY_SECS).await;
+    h.apply_policy_update().await.unwrap();
+    h.remainder_atas = vec![creator_ata, partner_ata];
+
+    // The partner closes its ATA mid-day; the last page can no longer pay it.
+    h.accrue_fees(0, 5_000).await;
+    h.crank(DistributionPhase::Snapshot, 0, true).await.unwrap();
+    let close_ix = token_instruction::close_account(
+        &spl_token::ID,
+        &partner_ata,
+        &partner.pubkey(),
+        &partner.pubkey(),
+        &[],
+    )
+    .unwrap();
+    process_tx(&mut h.context, vec![close_ix], &[&partner])
+        .await
+        .unwrap();
+    assert!(h.crank(DistributionPhase::Payout, 0, true).await.is_err());
+
+    // Any update beyond the remainder recipients, force-close settings included,
+    // still waits for a closed day; a pure reroute applies mid-day and the stuck day
+    // force-closes.
+    for update in [
+        PolicyUpdateArgs {
+            y0_total: 400_000,
+            ..policy_update_args()
+        },
+        PolicyUpdateArgs {
+            force_close_grace_secs: 0,
+            ..policy_update_args()
+        },
+        PolicyUpdateArgs {
+            roll_forced_carry: true,
+            ..policy_update_args()
+        },
+    ] {
+        h.propose_policy_update(update).await.unwrap();
+        h.warp_seconds(crate::POLICY_UPDATE_MIN_DELAY_SECS).await;
+        let err = h.apply_policy_update().await.unwrap_err();
+        assert_custom_error(err, FeeRouterError::DailyWindowNotReady);
+    }
+    h.propose_policy_update(policy_update_args()).await.unwrap();
+    h.warp_seconds(crate::POLICY_UPDATE_MIN_DELAY_SECS).await;
+    h.apply_policy_update().await.unwrap();
+    assert_eq!(h.policy_state().await.remainder_split.count, 0);
+    h.remainder_atas = Vec::new();
+    h.force_close_day().await.unwrap();
+    assert_eq!(h.token_balance(creator_ata).await, 5_000);
+    assert!(h.progress_state().await.day_closed);
+}
 
+#[tokio::test]
+async fn force_close_pays_carry_to_creator() {
+    let mut h = Harness::setup(policy_args(), &[150_000, 50_000]).await;
+    h.accrue_fees(0, 5_000).await;
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    h.crank(DistributionPhase::Snapshot, 1, true).await.unwrap();
+    h.crank(DistributionPhase::Payout, 0, false).await.unwrap();
+    assert_eq!(h.token_balance(h.entry(0).0).await, 750);
+
+    // The last page never arrives; the next day cannot start.
+    let day = h.progress_state().await.current_day;
+    let day_end = (day + 1) * 86_400;
+    h.warp_to(day_end).await;
+    let err = h
+        .crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::DailyWindowNotReady);
+
+    // Not before the grace period.
+    let err = h.force_close_day().await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::DailyWindowNotReady);
+
+    h.warp_to(day_end + 3_600).await;
+    h.force_close_day().await.unwrap();
+    assert_eq!(h.token_balance(h.creator_ata).await, 4_250);
+    assert_eq!(h.token_balance(h.treasury_ata).await, 0);
+    let progress = h.progress_state().await;
+    assert_eq!(progress.current_day, day);
+    assert!(progress.day_closed && progress.force_closed);
+    assert_eq!(progress.distributed_quote_today, 750);
+    assert_eq!(progress.rolled_over_quote, 0);
+    let history = h.history_state().await;
+    assert_eq!(history.len, 1);
+    let record = history.latest().unwrap();
+    assert_eq!(record.day, day);
+    assert_eq!(record.investor_quote, 750);
+    assert_eq!(record.creator_remainder, 4_250);
+    assert!(record.force_closed);
+
+    // Closing twice is rejected; the next day starts normally.
+    let err = h.force_close_day().await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::DailyWindowNotReady);
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    let progress = h.progress_state().await;
+    assert_eq!(progress.current_day, day + 1);
+    assert!(!progress.force_closed);
+}
+
+#[tokio::test]
+async fn close_policy_winds_down_a_closed_day() {
+    let mut h = Harness::setup(policy_args(), &[150_000, 50_000]).await;
+    h.accrue_fees(0, 5_000).await;
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    h.crank(DistributionPhase::Snapshot, 1, true).await.unwrap();
+
+    // Not while the day is still being paid out.
+    let authority = h.authority.insecure_clone();
+    let err = h.close_policy(&authority, true).await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::DailyWindowNotReady);
+
+    h.crank(DistributionPhase::Payout, 0, false).await.unwrap();
+    h.crank(DistributionPhase::Payout, 1, true).await.unwrap();
+    assert!(h.progress_state().await.day_closed);
+
+    // Only the policy authority may close.
+    let intruder = Keypair::new();
+    fund_accounts(&mut h.context, &[intruder.pubkey()]).await;
+    let err = h.close_policy(&intruder, true).await.unwrap_err();
+    assert!(matches!(
+        err,
+        TransactionError::InstructionError(0, InstructionError::Custom(code))
+            if code == anchor_lang::error::ErrorCode::ConstraintHasOne as u32
+    ));
+
+    // Funds left in the treasury go to the creator.
+    let creator_before = h.token_balance(h.creator_ata).await;
     let mint_ix = token_instruction::mint_to(
         &spl_token::ID,
-        &quote_mint.pubkey(),
-        &treasury_ata,
+        &h.quote_mint,
+        &h.treasury_ata,
         &authority.pubkey(),
         &[],
-        5_000,
+        321,
     )
     .unwrap();
-    process_tx(&mut context, vec![mint_ix], &[&context.payer, &authority]).await;
+    process_tx(&mut h.context, vec![mint_ix], &[&authority])
+        .await
+        .unwrap();
+    let authority_before = h
+        .context
+        .banks_client
+        .get_balance(authority.pubkey())
+        .await
+        .unwrap();
+    h.close_policy(&authority, true).await.unwrap();
+    assert_eq!(h.token_balance(h.creator_ata).await, creator_before + 321);
+    for closed in [
+        h.policy,
+        h.progress,
+        h.payout_bitmap,
+        h.history,
+        h.honorary_position,
+        h.cp_position,
+        h.treasury_ata,
+    ] {
+        let account = h.context.banks_client.get_account(closed).await.unwrap();
+        assert!(account.is_none(), "{closed} still open");
+    }
+    let authority_after = h
+        .context
+        .banks_client
+        .get_balance(authority.pubkey())
+        .await
+        .unwrap();
+    assert!(authority_after > authority_before);
+}
 
-    let first_crank_ix = Instruction {
-        program_id: crate::id(),
-        accounts: crate::accounts::CrankDistribute {
-            cp_program: Pubkey::from_str(DLMM_ID).unwrap(),
-            cp_pool: cp_pool.pubkey(),
-            policy: policy_pda,
-            progress: progress_pda,
-            payer: authority.pubkey(),
-            vault_authority,
-            treasury_quote_ata: treasury_ata,
-            creator_quote_ata: creator_ata,
-            investor_quote_ata: investor_ata,
-            stream: stream_locked.pubkey(),
-            token_program: spl_token::ID,
-            system_program: system_program::ID,
-        }
-        .to_account_metas(None),
-        data: crate::instruction::CrankDistribute {
-            args: CrankArgs {
-                page_cursor: 1,
-                is_last_page: false,
-            },
-        }
-        .data(),
-    };
-    let first_sig = process_tx(&mut context, vec![first_crank_ix], &[&context.payer, &authority]).await;
-    assert_event(&mut context, first_sig, "InvestorPayoutPage").await;
+#[tokio::test]
+async fn fees_are_collected_from_every_position() {
+    let mut h = Harness::setup(policy_args(), &[150_000, 50_000]).await;
+    let second = h.add_position().await.unwrap();
+    assert_eq!(second.index, 1);
+    assert_eq!(h.policy_state().await.position_count, 2);
+    h.accrue_position_fees(0, 0, 5_000).await;
+    h.accrue_position_fees(1, 0, 3_000).await;
 
-    let second_crank_ix = Instruction {
-        program_id: crate::id(),
-        accounts: crate::accounts::CrankDistribute {
-            cp_program: Pubkey::from_str(DLMM_ID).unwrap(),
-            cp_pool: cp_pool.pubkey(),
-            policy: policy_pda,
-            progress: progress_pda,
-            payer: authority.pubkey(),
-            vault_authority,
-            treasury_quote_ata: treasury_ata,
-            creator_quote_ata: creator_ata,
-            investor_quote_ata: investor_ata,
-            stream: stream_empty.pubkey(),
-            token_program: spl_token::ID,
-            system_program: system_program::ID,
+    // The first snapshot page cannot run before every position was collected
+    // today; `collect_position_fees` opens the day ahead of it.
+    let err = h
+        .crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::PositionNotCollected);
+    h.collect_position_fees(1).await.unwrap();
+    assert_eq!(h.progress_state().await.collected_positions, 0b10);
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    h.crank(DistributionPhase::Snapshot, 1, true).await.unwrap();
+    let progress = h.progress_state().await;
+    assert_eq!(progress.claimed_quote_today, 8_000);
+    assert_eq!(progress.collected_positions, 0b11);
+    assert_eq!(progress.investor_pool_today, 1_600);
+
+    // Collection is snapshot-only.
+    let err = h.collect_position_fees(0).await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::WrongPhase);
+    h.crank(DistributionPhase::Payout, 0, false).await.unwrap();
+    h.crank(DistributionPhase::Payout, 1, true).await.unwrap();
+    assert_eq!(h.token_balance(h.entry(0).0).await, 1_200);
+    assert_eq!(h.token_balance(h.entry(1).0).await, 400);
+    assert_eq!(h.token_balance(h.creator_ata).await, 6_400);
+
+    // Pages may collect any position; the mask resets each day.
+    h.warp_seconds(86_400).await;
+    h.accrue_position_fees(1, 0, 2_000).await;
+    h.collect_position_fees(0).await.unwrap();
+    assert_eq!(h.progress_state().await.collected_positions, 0b01);
+    h.crank_position(DistributionPhase::Snapshot, 0, false, 1)
+        .await
+        .unwrap();
+    assert_eq!(h.progress_state().await.collected_positions, 0b11);
+    h.crank(DistributionPhase::Snapshot, 1, true).await.unwrap();
+    assert_eq!(h.progress_state().await.claimed_quote_today, 2_000);
+    h.crank(DistributionPhase::Payout, 0, false).await.unwrap();
+    h.crank(DistributionPhase::Payout, 1, true).await.unwrap();
+
+    // Extra positions are unregistered before the policy can be closed.
+    let authority = h.authority.insecure_clone();
+    let err = h.close_policy(&authority, true).await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::ConstraintViolation);
+    h.close_honorary_position().await.unwrap();
+    assert_eq!(h.policy_state().await.position_count, 1);
+    for closed in [second.honorary_position, second.cp_position] {
+        let account = h.context.banks_client.get_account(closed).await.unwrap();
+        assert!(account.is_none(), "{closed} still open");
+    }
+    h.close_policy(&authority, true).await.unwrap();
+}
+
+#[tokio::test]
+async fn force_close_rolls_carry_into_next_day() {
+    let mut h = Harness::setup(
+        InitPolicyArgs {
+            roll_forced_carry: true,
+            ..policy_args()
+        },
+        &[200_000],
+    )
+    .await;
+    h.accrue_fees(0, 5_000).await;
+    h.crank(DistributionPhase::Snapshot, 0, true).await.unwrap();
+
+    let day = h.progress_state().await.current_day;
+    h.warp_to((day + 1) * 86_400 + 3_600).await;
+    h.force_close_day().await.unwrap();
+    assert_eq!(h.token_balance(h.creator_ata).await, 0);
+    assert_eq!(h.token_balance(h.treasury_ata).await, 5_000);
+    assert_eq!(h.progress_state().await.rolled_over_quote, 5_000);
+
+    // The rolled carry is distributed on the next day without new fees.
+    h.crank(DistributionPhase::Snapshot, 0, true).await.unwrap();
+    let progress = h.progress_state().await;
+    assert_eq!(progress.claimed_quote_today, 5_000);
+    assert_eq!(progress.rolled_over_quote, 0);
+    h.crank(DistributionPhase::Payout, 0, true).await.unwrap();
+    assert_eq!(h.token_balance(h.entry(0).0).await, 1_000);
+    assert_eq!(h.token_balance(h.creator_ata).await, 4_000);
+    assert_eq!(h.token_balance(h.treasury_ata).await, 0);
+
+    // The forced day records no remainder; the next day records the rolled quote.
+    let records = h.history_state().await.records();
+    assert_eq!(records.len(), 2);
+    assert_eq!((records[0].day, records[1].day), (day, day + 1));
+    assert!(records[0].force_closed && !records[1].force_closed);
+    assert_eq!(records[0].claimed_quote, 5_000);
+    assert_eq!(records[0].creator_remainder, 0);
+    assert_eq!(records[1].claimed_quote, 5_000);
+    assert_eq!(records[1].investor_quote, 1_000);
+    assert_eq!(records[1].creator_remainder, 4_000);
+}
+
+#[tokio::test]
+async fn token_2022_transfer_fees_are_booked_per_hop() {
+    // 1% transfer fee, rounded up by Token-2022.
+    let mut h =
+        Harness::setup_with_transfer_fee(policy_args(), &[150_000, 50_000], 100, u64::MAX).await;
+    let (investor_a, _) = h.entry(0);
+    let (investor_b, _) = h.entry(1);
+    let authority = h.authority.insecure_clone();
+
+    // The claim hop withholds 100 of 10_000; only what landed counts as claimed.
+    h.accrue_fees(0, 10_000).await;
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    h.crank(DistributionPhase::Snapshot, 1, true).await.unwrap();
+    let progress = h.progress_state().await;
+    assert_eq!(progress.claimed_quote_today, 9_900);
+    assert_eq!(progress.investor_pool_today, 1_980);
+
+    // Shares of 1_485 and 495 lose 15 and 5 on the way to the investors.
+    h.crank(DistributionPhase::Payout, 0, false).await.unwrap();
+    h.crank(DistributionPhase::Payout, 1, true).await.unwrap();
+    assert_eq!(h.token_balance(investor_a).await, 1_470);
+    assert_eq!(h.token_balance(investor_b).await, 490);
+    let progress = h.progress_state().await;
+    assert_eq!(progress.distributed_quote_today, 1_960);
+    assert_eq!(progress.transfer_fees_today, 20);
+    assert!(progress.day_closed);
+
+    // The remainder excludes investor fees; the creator bears the fee on its hop.
+    assert_eq!(h.token_balance(h.treasury_ata).await, 0);
+    assert_eq!(h.token_balance(h.creator_ata).await, 7_920 - 80);
+    let record = h.history_state().await.latest().unwrap();
+    assert_eq!(record.investor_quote, 1_960);
+    assert_eq!(record.creator_remainder, 7_920);
+
+    // Fees withheld in the treasury are harvested to the mint before it closes.
+    let creator_before = h.token_balance(h.creator_ata).await;
+    h.close_policy(&authority, true).await.unwrap();
+    assert_eq!(h.token_balance(h.creator_ata).await, creator_before);
+    let treasury = h.context.banks_client.get_account(h.treasury_ata).await;
+    assert!(treasury.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn sub_threshold_shares_accrue_in_dust_ledger() {
+    let mut h = Harness::setup(policy_args(), &[190_000, 10_000]).await;
+    let (investor_b, _) = h.entry(1);
+
+    // Investor B's 50 is below the 100 threshold: it waits in the ledger, not with
+    // the creator.
+    for day in 0..2 {
+        h.accrue_fees(0, 5_000).await;
+        h.crank(DistributionPhase::Snapshot, 0, false)
+            .await
+            .unwrap();
+        h.crank(DistributionPhase::Snapshot, 1, true).await.unwrap();
+        h.crank(DistributionPhase::Payout, 0, false).await.unwrap();
+        h.crank(DistributionPhase::Payout, 1, true).await.unwrap();
+        let progress = h.progress_state().await;
+        assert_eq!(progress.distributed_quote_today, 1_000);
+        assert_eq!(h.token_balance(h.creator_ata).await, 4_000 * (day + 1));
+        if day == 0 {
+            assert_eq!(h.token_balance(investor_b).await, 0);
+            assert_eq!(h.dust_ledger_state(investor_b).await.accrued_quote, 50);
+            assert_eq!(progress.dust_reserve_quote, 50);
+            assert_eq!(h.token_balance(h.treasury_ata).await, 50);
+            // The ledger balance is B's: the policy cannot close over it.
+            let authority = h.authority.insecure_clone();
+            let err = h.close_policy(&authority, true).await.unwrap_err();
+            assert_custom_error(err, FeeRouterError::ConstraintViolation);
+            h.warp_seconds(86_400).await;
         }
-        .to_account_metas(None),
-        data: crate::instruction::CrankDistribute {
-            args: CrankArgs {
-                page_cursor: 2,
-                is_last_page: true,
+    }
+
+    // The second day's 50 crosses the threshold and releases the whole 100.
+    assert_eq!(h.token_balance(investor_b).await, 100);
+    assert_eq!(h.token_balance(h.entry(0).0).await, 1_900);
+    assert_eq!(h.dust_ledger_state(investor_b).await.accrued_quote, 0);
+    assert_eq!(h.progress_state().await.dust_reserve_quote, 0);
+    assert_eq!(h.token_balance(h.treasury_ata).await, 0);
+}
+
+#[tokio::test]
+async fn dust_of_an_unlocked_investor_is_released_before_close() {
+    let mut h = Harness::setup(policy_args(), &[190_000, 10_000]).await;
+    let (investor_b, stream_b) = h.entry(1);
+    let wallet_b = h.investor_wallets[1].pubkey();
+    h.accrue_fees(0, 5_000).await;
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    h.crank(DistributionPhase::Snapshot, 1, true).await.unwrap();
+    h.crank(DistributionPhase::Payout, 0, false).await.unwrap();
+    h.crank(DistributionPhase::Payout, 1, true).await.unwrap();
+    assert_eq!(h.dust_ledger_state(investor_b).await.accrued_quote, 50);
+
+    // B unlocks to zero: its share is 0 from now on and the 50 never reaches the
+    // threshold.
+    h.set_stream(stream_b, &streamflow_program(), &wallet_b, 0)
+        .await;
+    h.warp_seconds(86_400).await;
+    h.accrue_fees(0, 5_000).await;
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    let err = h.release_dust(1).await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::DailyWindowNotReady);
+    h.crank(DistributionPhase::Snapshot, 1, true).await.unwrap();
+    h.crank(DistributionPhase::Payout, 0, false).await.unwrap();
+    h.crank(DistributionPhase::Payout, 1, true).await.unwrap();
+    assert_eq!(h.dust_ledger_state(investor_b).await.accrued_quote, 50);
+    let authority = h.authority.insecure_clone();
+    let err = h.close_policy(&authority, true).await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::ConstraintViolation);
+
+    // Releasing pays B below the threshold and closes the ledger; the policy closes.
+    h.release_dust(1).await.unwrap();
+    assert_eq!(h.token_balance(investor_b).await, 50);
+    assert_eq!(h.progress_state().await.dust_reserve_quote, 0);
+    let ledger = h
+        .context
+        .banks_client
+        .get_account(h.dust_ledger(investor_b))
+        .await
+        .unwrap();
+    assert!(ledger.is_none());
+    h.close_policy(&authority, true).await.unwrap();
+    assert_eq!(h.token_balance(h.creator_ata).await, 4_000 + 4_050);
+}
+
+#[tokio::test]
+async fn pull_mode_investors_claim_accrued_fees() {
+    let mut h = Harness::setup(
+        InitPolicyArgs {
+            claim_mode: ClaimMode::Pull,
+            ..policy_args()
+        },
+        &[150_000, 50_000],
+    )
+    .await;
+    h.accrue_fees(0, 5_000).await;
+    let (investor_a, _) = h.entry(0);
+    let (investor_b, _) = h.entry(1);
+
+    // The snapshot pass books the pool and closes the day; no payout pass.
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    h.crank(DistributionPhase::Snapshot, 1, true).await.unwrap();
+    let progress = h.progress_state().await;
+    assert!(progress.day_closed);
+    assert_eq!(progress.distributed_quote_today, 1_000);
+    assert_eq!(progress.pull_reserve_quote(), 1_000);
+    assert_eq!(h.token_balance(h.creator_ata).await, 4_000);
+    assert_eq!(h.token_balance(h.treasury_ata).await, 1_000);
+
+    h.claim_fees(0).await.unwrap();
+    assert_eq!(h.token_balance(investor_a).await, 750);
+    // A repeat claim owes nothing.
+    h.claim_fees(0).await.unwrap();
+    assert_eq!(h.token_balance(investor_a).await, 750);
+
+    // Unclaimed shares accumulate across days.
+    h.accrue_fees(0, 2_000).await;
+    h.warp_seconds(86_400).await;
+    h.crank(DistributionPhase::Snapshot, 0, false)
+        .await
+        .unwrap();
+    h.crank(DistributionPhase::Snapshot, 1, true).await.unwrap();
+    assert_eq!(h.token_balance(h.creator_ata).await, 5_600);
+    h.claim_fees(1).await.unwrap();
+    assert_eq!(h.token_balance(investor_b).await, 350);
+
+    // A's unclaimed share keeps the policy open.
+    let authority = h.authority.insecure_clone();
+    let err = h.close_policy(&authority, true).await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::ConstraintViolation);
+    h.claim_fees(0).await.unwrap();
+    assert_eq!(h.token_balance(investor_a).await, 1_050);
+    assert_eq!(h.progress_state().await.pull_reserve_quote(), 0);
+    assert_eq!(h.token_balance(h.treasury_ata).await, 0);
+    h.close_policy(&authority, true).await.unwrap();
+}
+
+#[tokio::test]
+async fn pull_claims_use_locked_amounts_recorded_at_snapshot() {
+    let mut h = Harness::setup(
+        InitPolicyArgs {
+            claim_mode: ClaimMode::Pull,
+            ..policy_args()
+        },
+        &[150_000, 50_000],
+    )
+    .await;
+    let (investor_a, stream_a) = h.entry(0);
+    let (investor_b, stream_b) = h.entry(1);
+    let wallet_a = h.investor_wallets[0].pubkey();
+    let wallet_b = h.investor_wallets[1].pubkey();
+    h.accrue_fees(0, 5_000).await;
+    h.crank_page(DistributionPhase::Snapshot, 0, 2, true)
+        .await
+        .unwrap();
+    assert_eq!(h.checkpoint_state(investor_a).await.locked_snapshot, 150_000);
+    assert_eq!(h.checkpoint_state(investor_b).await.locked_snapshot, 50_000);
+
+    // A's stream unlocks and B tops theirs up before either claims: the day still
+    // pays on the amounts its snapshot recorded.
+    h.set_stream(stream_a, &streamflow_program(), &wallet_a, 0)
+        .await;
+    h.set_stream(stream_b, &streamflow_program(), &wallet_b, 10_000_000)
+        .await;
+    h.claim_fees(1).await.unwrap();
+    assert_eq!(h.token_balance(investor_b).await, 250);
+    h.claim_fees(0).await.unwrap();
+    assert_eq!(h.token_balance(investor_a).await, 750);
+    let progress = h.progress_state().await;
+    assert_eq!(progress.pull_reserve_quote(), 0);
+    assert_eq!(progress.pull_unsettled_scaled, 0);
+
+    // The next snapshot records the new amounts: with nothing locked, A accrues
+    // nothing more.
+    h.accrue_fees(0, 2_000).await;
+    h.warp_seconds(86_400).await;
+    h.crank_page(DistributionPhase::Snapshot, 0, 2, true)
+        .await
+        .unwrap();
+    let pool = h.progress_state().await.distributed_quote_today;
+    assert!(pool > 0);
+    h.claim_fees(0).await.unwrap();
+    assert_eq!(h.token_balance(investor_a).await, 750);
+    h.claim_fees(1).await.unwrap();
+    assert_eq!(h.token_balance(investor_b).await, 250 + pool);
+    assert_eq!(h.progress_state().await.pull_reserve_quote(), 0);
+}
+
+#[tokio::test]
+async fn push_payouts_use_locked_amounts_recorded_at_snapshot() {
+    let mut h = Harness::setup(policy_args(), &[150_000, 50_000]).await;
+    let (investor_a, stream_a) = h.entry(0);
+    let (investor_b, stream_b) = h.entry(1);
+    let wallet_a = h.investor_wallets[0].pubkey();
+    let wallet_b = h.investor_wallets[1].pubkey();
+    h.accrue_fees(0, 5_000).await;
+    h.crank_page(DistributionPhase::Snapshot, 0, 2, true)
+        .await
+        .unwrap();
+    assert_eq!(
+        h.dust_ledger_state(investor_b).await.locked_snapshot,
+        50_000
+    );
+
+    // A's stream unlocks and B tops theirs up between the passes: the payout pass
+    // still pays on the amounts the snapshot recorded.
+    h.set_stream(stream_a, &streamflow_program(), &wallet_a, 0)
+        .await;
+    h.set_stream(stream_b, &streamflow_program(), &wallet_b, 10_000_000)
+        .await;
+    h.crank_page(DistributionPhase::Payout, 0, 2, true)
+        .await
+        .unwrap();
+    assert_eq!(h.token_balance(investor_a).await, 750);
+    assert_eq!(h.token_balance(investor_b).await, 250);
+    assert_eq!(h.token_balance(h.creator_ata).await, 4_000);
+    assert_eq!(h.token_balance(h.treasury_ata).await, 0);
+}
+
+#[tokio::test]
+async fn claim_fees_requires_pull_mode() {
+    let mut h = Harness::setup(policy_args(), &[200_000]).await;
+    h.accrue_fees(0, 5_000).await;
+    h.crank(DistributionPhase::Snapshot, 0, true).await.unwrap();
+
+    let err = h.claim_fees(0).await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::ClaimModeMismatch);
+}
+
+#[tokio::test]
+async fn hourly_epochs_with_negative_offset() {
+    let mut h = Harness::setup(
+        InitPolicyArgs {
+            epoch_seconds: 3_600,
+            epoch_offset_seconds: -1_800,
+            ..policy_args()
+        },
+        &[200_000],
+    )
+    .await;
+    // Periods start at :30; begin one second before a boundary.
+    let clock: Clock = h.context.banks_client.get_sysvar().await.unwrap();
+    let epoch = (clock.unix_timestamp + 1_800).div_euclid(3_600) + 1;
+    let boundary = epoch * 3_600 - 1_800;
+    h.warp_to(boundary - 1).await;
+    h.accrue_fees(0, 5_000).await;
+    h.crank(DistributionPhase::Snapshot, 0, true).await.unwrap();
+    h.crank(DistributionPhase::Payout, 0, true).await.unwrap();
+    assert_eq!(h.progress_state().await.current_day, epoch - 1);
+    assert_eq!(h.token_balance(h.entry(0).0).await, 1_000);
+
+    // The boundary second opens the next period.
+    h.accrue_fees(0, 5_000).await;
+    h.warp_to(boundary).await;
+    h.crank(DistributionPhase::Snapshot, 0, true).await.unwrap();
+    assert_eq!(h.progress_state().await.current_day, epoch);
+
+    // Force close keys off the period end, not the UTC day.
+    let closable = boundary + 3_600 + 3_600;
+    h.warp_to(closable - 1).await;
+    let err = h.force_close_day().await.unwrap_err();
+    assert_custom_error(err, FeeRouterError::DailyWindowNotReady);
+    h.warp_to(closable).await;
+    h.force_close_day().await.unwrap();
+    assert_eq!(h.token_balance(h.creator_ata).await, 4_000 + 5_000);
+}
+
+#[tokio::test]
+async fn period_index_zero_is_a_real_day() {
+    // A period longer than the clock's age leaves today at index 0.
+    let mut h = Harness::setup(
+        InitPolicyArgs {
+            epoch_seconds: 4_000_000_000,
+            ..policy_args()
+        },
+        &[200_000],
+    )
+    .await;
+    h.accrue_fees(0, 5_000).await;
+    h.crank(DistributionPhase::Snapshot, 0, true).await.unwrap();
+    h.crank(DistributionPhase::Payout, 0, true).await.unwrap();
+    let progress = h.progress_state().await;
+    assert_eq!(progress.current_day, 0);
+    assert!(progress.day_opened && progress.day_closed);
+    assert_eq!(h.token_balance(h.entry(0).0).await, 1_000);
+
+    // Day 0 stays closed; it does not reopen on the next crank.
+    h.accrue_fees(0, 5_000).await;
+    let err = h
+        .crank(DistributionPhase::Snapshot, 0, true)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::DailyWindowNotReady);
+    assert_eq!(h.token_balance(h.entry(0).0).await, 1_000);
+}
+
+#[tokio::test]
+async fn init_policy_rejects_bad_epochs() {
+    for (epoch_seconds, epoch_offset_seconds) in [(0, 0), (-3_600, 0), (3_600, -3_600)] {
+        let harness = Harness::try_setup(
+            InitPolicyArgs {
+                epoch_seconds,
+                epoch_offset_seconds,
+                ..policy_args()
             },
-        }
-        .data(),
+            &[200_000],
+        )
+        .await;
+        let err = harness.err().expect("init_policy accepted a bad epoch");
+        assert_custom_error(err, FeeRouterError::ConstraintViolation);
+    }
+}
+
+#[tokio::test]
+async fn pre_minted_treasury_funds_are_not_distributed() {
+    let mut h = Harness::setup(policy_args(), &[200_000]).await;
+    let authority = h.authority.insecure_clone();
+    let mint_ix = token_instruction::mint_to(
+        &spl_token::ID,
+        &h.quote_mint,
+        &h.treasury_ata,
+        &authority.pubkey(),
+        &[],
+        5_000,
+    )
+    .unwrap();
+    process_tx(&mut h.context, vec![mint_ix], &[&authority])
+        .await
+        .unwrap();
+
+    h.crank(DistributionPhase::Snapshot, 0, true).await.unwrap();
+    h.crank(DistributionPhase::Payout, 0, true).await.unwrap();
+    assert_eq!(h.token_balance(h.entry(0).0).await, 0);
+    assert_eq!(h.token_balance(h.creator_ata).await, 0);
+    assert_eq!(h.token_balance(h.treasury_ata).await, 5_000);
+}
+
+#[tokio::test]
+async fn base_fee_claim_fails_quote_only() {
+    let mut h = Harness::setup(policy_args(), &[200_000]).await;
+    h.accrue_fees(10, 5_000).await;
+
+    let err = h
+        .crank(DistributionPhase::Snapshot, 0, true)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::QuoteOnlyViolation);
+    assert_eq!(h.token_balance(h.treasury_base_ata).await, 0);
+    assert_eq!(h.token_balance(h.treasury_ata).await, 0);
+}
+
+#[tokio::test]
+async fn init_policy_requires_a_quote_only_pool() {
+    // Fees in both tokens, and quote as token A (no mode collects only token A).
+    for quote_is_a in [false, true] {
+        let mut h = Harness::boot(&[200_000]).await;
+        let (token_a, token_b, mode) = if quote_is_a {
+            (h.quote_mint, h.base_mint, COLLECT_FEE_MODE_ONLY_B)
+        } else {
+            (h.base_mint, h.quote_mint, COLLECT_FEE_MODE_BOTH_TOKEN)
+        };
+        set_pool(&mut h.context, &h.cp_pool, &token_a, &token_b, mode).await;
+        let investor_set = h.investor_set();
+        let err = h
+            .init(InitPolicyArgs {
+                investor_set,
+                ..policy_args()
+            })
+            .await
+            .err()
+            .expect("init_policy accepted a pool collecting non-quote fees");
+        assert_custom_error(err, FeeRouterError::QuoteOnlyViolation);
+    }
+}
+
+#[tokio::test]
+async fn honorary_position_requires_a_quote_only_pool() {
+    let mut h = Harness::setup(policy_args(), &[200_000]).await;
+    let err = h
+        .add_position_in_pool(COLLECT_FEE_MODE_BOTH_TOKEN)
+        .await
+        .err()
+        .expect("init_honorary_position accepted a pool collecting both tokens");
+    assert_custom_error(err, FeeRouterError::QuoteOnlyViolation);
+    h.add_position().await.unwrap();
+}
+
+#[tokio::test]
+async fn keystone_vesting_schedules_drive_locked_amounts() {
+    let mut h = Harness::boot(&[0, 0, 0]).await;
+    let clock: Clock = h.context.banks_client.get_sysvar().await.unwrap();
+    let now = clock.unix_timestamp;
+    // Before the cliff nothing is vested; past the end nothing is locked.
+    let unvested = (now - 1_000, now + 86_400, now + 10 * 86_400);
+    let vested = (now - 10 * 86_400, now - 9 * 86_400, now - 1_000);
+    for (index, total, window) in [
+        (0, 150_000, unvested),
+        (1, 50_000, unvested),
+        (2, 400_000, vested),
+    ] {
+        let beneficiary = h.investor_wallets[index].pubkey();
+        h.set_vesting_schedule(index, beneficiary, total, window)
+            .await;
+    }
+    let investor_set = h.investor_set();
+    let mut h = h
+        .init(InitPolicyArgs {
+            investor_set,
+            lock_source: LockSource::KeystoneVesting,
+            ..policy_args()
+        })
+        .await
+        .unwrap();
+    h.accrue_fees(0, 5_000).await;
+
+    for index in 0..3 {
+        h.crank(DistributionPhase::Snapshot, index, index == 2)
+            .await
+            .unwrap();
+    }
+    let progress = h.progress_state().await;
+    assert_eq!(progress.locked_total_today, 200_000);
+    assert_eq!(progress.investor_pool_today, 1_000);
+    for index in 0..3 {
+        h.crank(DistributionPhase::Payout, index, index == 2)
+            .await
+            .unwrap();
+    }
+    assert_eq!(h.token_balance(h.entry(0).0).await, 750);
+    assert_eq!(h.token_balance(h.entry(1).0).await, 250);
+    assert_eq!(h.token_balance(h.entry(2).0).await, 0);
+    assert_eq!(h.token_balance(h.creator_ata).await, 4_000);
+}
+
+#[tokio::test]
+async fn keystone_vesting_reader_validates_the_schedule() {
+    let args = || InitPolicyArgs {
+        lock_source: LockSource::KeystoneVesting,
+        ..policy_args()
     };
-    let second_sig = process_tx(&mut context, vec![second_crank_ix], &[&context.payer, &authority]).await;
-    assert_event(&mut context, second_sig, "CreatorPayoutDayClosed").await;
 
-    let progress_account = context
-        .banks_client
-        .get_account(progress_pda)
+    // Streamflow streams are not owned by the vesting program.
+    let mut h = Harness::setup(args(), &[200_000]).await;
+    h.accrue_fees(0, 5_000).await;
+    let err = h
+        .crank(DistributionPhase::Snapshot, 0, true)
         .await
-        .unwrap()
-        .expect("progress account");
-    let mut data: &[u8] = &progress_account.data;
-    let mut data_slice: &[u8] = &progress_account.data;
-    let progress_state = crate::Progress::try_deserialize(&mut data_slice).unwrap();
-    assert_eq!(progress_state.distributed_quote_today, 1_000);
-    assert_eq!(progress_state.carry_quote_today, 0);
-    assert!(progress_state.day_closed);
-
-    let creator_acc = context
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::InvalidStream);
+
+    // A schedule vesting to another wallet does not count for the investor.
+    let mut h = Harness::setup(args(), &[200_000]).await;
+    h.accrue_fees(0, 5_000).await;
+    let unvested = (0, i64::MAX - 1, i64::MAX);
+    h.set_vesting_schedule(0, Pubkey::new_unique(), 200_000, unvested)
+        .await;
+    let err = h
+        .crank(DistributionPhase::Snapshot, 0, true)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::Unauthorized);
+}
+
+#[tokio::test]
+async fn pages_hold_up_to_max_page_size_entries() {
+    let err = Harness::try_setup(
+        InitPolicyArgs {
+            max_page_size: 0,
+            ..policy_args()
+        },
+        &[200_000],
+    )
+    .await
+    .err()
+    .expect("init_policy accepted an empty page size");
+    assert_custom_error(err, FeeRouterError::ConstraintViolation);
+
+    let mut h = Harness::setup(
+        InitPolicyArgs {
+            max_page_size: 2,
+            ..policy_args()
+        },
+        &[100_000, 100_000, 200_000],
+    )
+    .await;
+    h.accrue_fees(0, 5_000).await;
+
+    let err = h
+        .crank_page(DistributionPhase::Snapshot, 0, 3, true)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::InvalidInvestorPage);
+    h.crank_page(DistributionPhase::Snapshot, 0, 2, false)
+        .await
+        .unwrap();
+    h.crank_page(DistributionPhase::Snapshot, 2, 1, true)
+        .await
+        .unwrap();
+    assert_eq!(h.progress_state().await.locked_total_today, 400_000);
+
+    h.crank_page(DistributionPhase::Payout, 0, 2, false)
+        .await
+        .unwrap();
+    h.crank_page(DistributionPhase::Payout, 2, 1, true)
+        .await
+        .unwrap();
+    for (index, paid) in [250, 250, 500].into_iter().enumerate() {
+        assert_eq!(h.token_balance(h.entry(index).0).await, paid);
+    }
+    assert_eq!(h.token_balance(h.creator_ata).await, 4_000);
+}
+
+#[tokio::test]
+async fn every_page_entry_is_validated() {
+    let mut h = Harness::setup(policy_args(), &[100_000, 100_000, 200_000]).await;
+    h.accrue_fees(0, 5_000).await;
+    let (investor_ata, stream) = h.entry(2);
+    let recipient = h.investor_wallets[2].pubkey();
+
+    // Stream recipient differs from the ATA owner.
+    h.set_stream(
+        stream,
+        &streamflow_program(),
+        &Pubkey::new_unique(),
+        200_000,
+    )
+    .await;
+    let err = h
+        .crank_page(DistributionPhase::Snapshot, 0, 3, true)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::Unauthorized);
+
+    // Stream owned by another program.
+    h.set_stream(stream, &crate::id(), &recipient, 200_000)
+        .await;
+    let err = h
+        .crank_page(DistributionPhase::Snapshot, 0, 3, true)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::InvalidStream);
+
+    // Investor ATA of another mint.
+    h.set_stream(stream, &streamflow_program(), &recipient, 200_000)
+        .await;
+    let mut account = h
+        .context
         .banks_client
-        .get_account(creator_ata)
+        .get_account(investor_ata)
         .await
         .unwrap()
-        .expect("creator ata");
-    let token_state = TokenAccount::unpack(&creator_acc.data).unwrap();
-    assert_eq!(token_state.amount, 4_000);
+        .unwrap();
+    let mut token_account = TokenAccount::unpack(&account.data).unwrap();
+    token_account.mint = h.base_mint;
+    TokenAccount::pack(token_account, &mut account.data).unwrap();
+    h.context
+        .set_account(&investor_ata, &account.clone().into());
+    let err = h
+        .crank_page(DistributionPhase::Snapshot, 0, 3, true)
+        .await
+        .unwrap_err();
+    assert_custom_error(err, FeeRouterError::ConstraintViolation);
+
+    token_account.mint = h.quote_mint;
+    TokenAccount::pack(token_account, &mut account.data).unwrap();
+    h.context.set_account(&investor_ata, &account.into());
+    h.crank_page(DistributionPhase::Snapshot, 0, 3, true)
+        .await
+        .unwrap();
 }
 
-async fn fund_accounts(context: &mut ProgramTestContext, recipients: &[Pubkey]) {
-    let mut ixs = Vec::with_capacity(recipients.len());
-    for recipient in recipients {
-        ixs.push(system_instruction::transfer(
-            &context.payer.pubkey(),
-            recipient,
-            5_000_000_000,
-        ));
+/// Compute units per crank page by page size, up to the largest page whose account
+/// keys fit a legacy transaction. The router runs natively here, so the figures
+/// cover its token and system CPIs; run under `cargo test-sbf` (with `SBF_OUT_DIR`
+/// set) to measure the deployed program.
+#[tokio::test]
+async fn crank_page_compute_units() {
+    const PAGE_SIZES: [usize; 4] = [1, 2, 3, 4];
+    let count = PAGE_SIZES.iter().sum::<usize>();
+    let mut h = Harness::setup(policy_args(), &vec![50_000; count]).await;
+    h.accrue_fees(0, 1_000_000).await;
+
+    let mut units = Vec::new();
+    for phase in [DistributionPhase::Snapshot, DistributionPhase::Payout] {
+        let mut cursor = 0;
+        for len in PAGE_SIZES {
+            let is_last_page = cursor + len == count;
+            let consumed = h
+                .crank_page(phase, cursor, len, is_last_page)
+                .await
+                .unwrap();
+            println!("{phase:?} page of {len}: {consumed} CU");
+            units.push(consumed);
+            cursor += len;
+        }
+    }
+    assert!(h.progress_state().await.day_closed);
+    // First-time payouts create a dust ledger and transfer per entry.
+    let payout_units = &units[PAGE_SIZES.len()..];
+    assert!(payout_units.windows(2).all(|pair| pair[0] < pair[1]));
+    assert!(units.iter().all(|&consumed| consumed < 1_400_000));
+}
+
+/// Honorary position registry and owner PDAs of position `index`; position 0 uses
+/// the single-position seeds without an index.
+fn position_pdas(policy: &Pubkey, index: u8) -> (Pubkey, Pubkey) {
+    let index_seed: &[&[u8]] = match index {
+        0 => &[],
+        _ => &[&[index]],
+    };
+    let pda = |seeds: &[&[u8]]| {
+        Pubkey::find_program_address(&[seeds, index_seed].concat(), &crate::id()).0
+    };
+    (
+        pda(&[crate::POSITION_SEED, policy.as_ref()]),
+        pda(&[crate::VAULT_SEED, policy.as_ref(), crate::FEE_POS_OWNER_SEED]),
+    )
+}
+
+/// Builds the levels of a sorted-pair keccak tree as in `keystone_common::merkle`,
+/// leaves first. An odd trailing node is promoted unchanged.
+fn merkle_levels(leaves: Vec<[u8; 32]>) -> Vec<Vec<[u8; 32]>> {
+    let mut levels = vec![leaves];
+    while levels.last().unwrap().len() > 1 {
+        let next = levels
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] if a <= b => hash_nodes(a, b),
+                [a, b] => hash_nodes(b, a),
+                [a] => *a,
+                _ => unreachable!(),
+            })
+            .collect();
+        levels.push(next);
+    }
+    levels
+}
+
+/// Sibling path of leaf `index` in `levels`.
+fn merkle_proof(levels: &[Vec<[u8; 32]>], mut index: usize) -> Vec<[u8; 32]> {
+    let mut proof = Vec::new();
+    for level in &levels[..levels.len() - 1] {
+        if let Some(sibling) = level.get(index ^ 1) {
+            proof.push(*sibling);
+        }
+        index /= 2;
+    }
+    proof
+}
+
+fn assert_custom_error(err: TransactionError, expected: FeeRouterError) {
+    let code = anchor_lang::error::ERROR_CODE_OFFSET + expected as u32;
+    match err {
+        TransactionError::InstructionError(_, InstructionError::Custom(actual)) => {
+            assert_eq!(actual, code, "unexpected custom error")
+        }
+        other => panic!("expected custom error {code}, got {other:?}"),
     }
-    process_tx(context, ixs, &[&context.payer]).await;
 }
 
-async fn create_unchecked_account(
+async fn fund_accounts(context: &mut ProgramTestContext, recipients: &[Pubkey]) {
+    let ixs = recipients
+        .iter()
+        .map(|recipient| {
+            system_instruction::transfer(&context.payer.pubkey(), recipient, 5_000_000_000)
+        })
+        .collect();
+    process_tx(context, ixs, &[]).await.unwrap();
+}
+
+/// Writes a cp-amm `Pool` account owned by the DAMM v2 program id with the fields
+/// the fee router decodes.
+async fn set_pool(
+    context: &mut ProgramTestContext,
+    pool: &Pubkey,
+    token_a_mint: &Pubkey,
+    token_b_mint: &Pubkey,
+    collect_fee_mode: u8,
+) {
+    let mut data = vec![0u8; POOL_COLLECT_FEE_MODE_OFFSET + 1];
+    data[..8].copy_from_slice(&POOL_DISCRIMINATOR);
+    data[POOL_TOKEN_A_MINT_OFFSET..POOL_TOKEN_A_MINT_OFFSET + 32]
+        .copy_from_slice(token_a_mint.as_ref());
+    data[POOL_TOKEN_B_MINT_OFFSET..POOL_TOKEN_B_MINT_OFFSET + 32]
+        .copy_from_slice(token_b_mint.as_ref());
+    data[POOL_COLLECT_FEE_MODE_OFFSET] = collect_fee_mode;
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let mut account = AccountSharedData::new(
+        rent.minimum_balance(data.len()),
+        data.len(),
+        &Pubkey::from_str(DEFAULT_DLMM_PROGRAM_ID).unwrap(),
+    );
+    account.set_data_from_slice(&data);
+    context.set_account(pool, &account);
+}
+
+async fn create_owned_account(
     context: &mut ProgramTestContext,
     keypair: &Keypair,
     owner: &Pubkey,
@@ -274,7 +2817,7 @@ async fn create_unchecked_account(
         space,
         owner,
     );
-    process_tx(context, vec![ix], &[&context.payer, keypair]).await;
+    process_tx(context, vec![ix], &[keypair]).await.unwrap();
 }
 
 async fn create_mint(
@@ -298,64 +2841,99 @@ async fn create_mint(
         6,
     )
     .unwrap();
-    process_tx(context, vec![create_ix, init_ix], &[&context.payer, mint]).await;
+    process_tx(context, vec![create_ix, init_ix], &[mint])
+        .await
+        .unwrap();
 }
 
-async fn init_stream_account(
+/// Creates a Token-2022 mint whose transfer fee extension withholds `fee_bps` of each
+/// transfer, capped at `max_fee`.
+async fn create_transfer_fee_mint(
     context: &mut ProgramTestContext,
-    stream: &Keypair,
-    locked_amount: u64,
-    lamports: u64,
+    mint: &Keypair,
+    authority: &Keypair,
+    fee_bps: u16,
+    max_fee: u64,
 ) {
-    let ix = system_instruction::create_account(
+    let space = ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[
+        ExtensionType::TransferFeeConfig,
+    ])
+    .unwrap();
+    let lamports = context
+        .banks_client
+        .get_rent()
+        .await
+        .unwrap()
+        .minimum_balance(space);
+    let create_ix = system_instruction::create_account(
         &context.payer.pubkey(),
-        &stream.pubkey(),
+        &mint.pubkey(),
         lamports,
-        8,
-        &crate::id(),
+        space as u64,
+        &spl_token_2022::ID,
     );
-    process_tx(context, vec![ix], &[&context.payer, stream]).await;
-
-    let mut data = locked_amount.to_le_bytes().to_vec();
-    let account = SolanaAccount {
-        lamports,
-        data,
-        owner: crate::id(),
-        executable: false,
-        rent_epoch: 0,
-    };
-    context.set_account(&stream.pubkey(), &account);
+    let fee_ix = transfer_fee::instruction::initialize_transfer_fee_config(
+        &spl_token_2022::ID,
+        &mint.pubkey(),
+        Some(&authority.pubkey()),
+        Some(&authority.pubkey()),
+        fee_bps,
+        max_fee,
+    )
+    .unwrap();
+    let init_ix = spl_token_2022::instruction::initialize_mint(
+        &spl_token_2022::ID,
+        &mint.pubkey(),
+        &authority.pubkey(),
+        None,
+        6,
+    )
+    .unwrap();
+    process_tx(context, vec![create_ix, fee_ix, init_ix], &[mint])
+        .await
+        .unwrap();
 }
 
-async fn assert_event(context: &mut ProgramTestContext, signature: Signature, needle: &str) {
-    let tx = context
-        .banks_client
-        .get_transaction_with_status_meta(signature)
+/// Sends a transaction paid by the context payer.
+async fn process_tx(
+    context: &mut ProgramTestContext,
+    instructions: Vec<Instruction>,
+    signers: &[&Keypair],
+) -> std::result::Result<(), TransactionError> {
+    process_tx_with_units(context, instructions, signers)
         .await
-        .unwrap()
-        .expect("transaction meta");
-    let meta = tx.meta.expect("meta");
-    let logs = meta.log_messages.expect("logs");
-    assert!(
-        logs.iter().any(|line| line.contains(needle)),
-        "missing event {needle} in logs"
-    );
+        .map(|_| ())
 }
 
-async fn process_tx(
+/// Like `process_tx`, returning the compute units the transaction consumed.
+async fn process_tx_with_units(
     context: &mut ProgramTestContext,
     instructions: Vec<Instruction>,
     signers: &[&Keypair],
-) -> Signature {
-    let mut all_signers: Vec<&Keypair> = vec![&context.payer];
+) -> std::result::Result<u64, TransactionError> {
+    let payer = context.payer.insecure_clone();
+    let mut all_signers: Vec<&Keypair> = vec![&payer];
     all_signers.extend_from_slice(signers);
-    let bh = context.banks_client.get_latest_blockhash().await.unwrap();
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
     let tx = Transaction::new_signed_with_payer(
         &instructions,
-        Some(&context.payer.pubkey()),
+        Some(&payer.pubkey()),
         &all_signers,
-        bh,
+        blockhash,
     );
-    context.banks_client.process_transaction(tx.clone()).await.unwrap();
-    tx.signatures[0]
+    let outcome = context
+        .banks_client
+        .process_transaction_with_metadata(tx)
+        .await
+        .unwrap();
+    outcome.result?;
+    Ok(outcome
+        .metadata
+        .map(|metadata| metadata.compute_units_consumed)
+        .unwrap_or_default())
+}
+
+/// Mainnet Streamflow program, owner of the mock streams.
+fn streamflow_program() -> Pubkey {
+    Pubkey::from_str(crate::stream_adapter::streamflow::STREAMFLOW_PROGRAM_ID).unwrap()
 }
@@ -0,0 +1,432 @@
+// Host integration tests for the launchpad on `solana-program-test`.
+// Included from `programs/launchpad/src/lib.rs` behind the `bankrun-test` feature:
+// `cargo test -p keystone-launchpad --features bankrun-test`.
+//
+// The sale mints tokens from a mint whose authority is the treasury PDA, and takes
+// payment in a separate quote mint into a quote vault owned by that PDA.
+
+use crate::state::{BuyerReceipt, BuyerTier, LaunchPricing, SaleState, RECEIPT_SEED};
+use crate::{InitLaunchArgs, LaunchError};
+use anchor_lang::solana_program::{entrypoint::ProgramResult, program_pack::Pack};
+use anchor_lang::{prelude::*, system_program, InstructionData, ToAccountMetas};
+use keystone_common::curve::{CurveConfig, CurveKind};
+use keystone_common::fees::FeeConfig;
+use solana_program_test::{processor, ProgramTest, ProgramTestContext};
+#[allow(deprecated)]
+use solana_sdk::system_instruction;
+use solana_sdk::{
+    instruction::{Instruction, InstructionError},
+    signature::{Keypair, Signer},
+    transaction::{Transaction, TransactionError},
+};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account,
+};
+use spl_token::{
+    instruction as token_instruction,
+    state::{Account as TokenAccount, Mint},
+};
+
+/// Quote each buyer wallet starts with.
+const BUYER_QUOTE: u64 = 1_000_000;
+
+/// Adapts Anchor's lifetime-bound entrypoint to the `processor!` signature.
+fn launchpad_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(accounts.to_vec().into_boxed_slice());
+    crate::entry(program_id, accounts, data)
+}
+
+struct Harness {
+    context: ProgramTestContext,
+    mint: Pubkey,
+    quote_mint: Pubkey,
+    launch_config: Pubkey,
+    sale_state: Pubkey,
+    treasury_authority: Pubkey,
+    quote_vault: Pubkey,
+    pricing: LaunchPricing,
+}
+
+impl Harness {
+    /// Boots the program and opens a linear bonding curve sale that is already live.
+    async fn setup() -> Self {
+        let test = ProgramTest::new(
+            "keystone_launchpad",
+            crate::id(),
+            processor!(launchpad_entry),
+        );
+        let mut context = test.start_with_context().await;
+        let mint = Keypair::new();
+        let quote_mint = Keypair::new();
+        let (launch_config, _) =
+            Pubkey::find_program_address(&[b"launch", mint.pubkey().as_ref()], &crate::id());
+        let (sale_state, _) =
+            Pubkey::find_program_address(&[b"sale", mint.pubkey().as_ref()], &crate::id());
+        let (treasury_authority, _) =
+            Pubkey::find_program_address(&[b"treasury", launch_config.as_ref()], &crate::id());
+
+        create_mint(&mut context, &mint, &treasury_authority).await;
+        let payer = context.payer.pubkey();
+        create_mint(&mut context, &quote_mint, &payer).await;
+        let treasury_vault = get_associated_token_address(&treasury_authority, &mint.pubkey());
+        let quote_vault = get_associated_token_address(&treasury_authority, &quote_mint.pubkey());
+        process_tx(
+            &mut context,
+            vec![
+                create_associated_token_account(
+                    &payer,
+                    &treasury_authority,
+                    &mint.pubkey(),
+                    &spl_token::ID,
+                ),
+                create_associated_token_account(
+                    &payer,
+                    &treasury_authority,
+                    &quote_mint.pubkey(),
+                    &spl_token::ID,
+                ),
+            ],
+            &[],
+        )
+        .await
+        .unwrap();
+
+        let pricing = LaunchPricing::BondingCurve {
+            curve: CurveConfig {
+                kind: CurveKind::Linear,
+                base_price: 1_000_000_000,
+                k: 1_000_000,
+                x0: 0,
+                max_supply: 10_000,
+                fee_config: FeeConfig {
+                    numerator: 0,
+                    denominator: 10_000,
+                    fee_vault: Pubkey::default(),
+                },
+            },
+        };
+        let ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::InitLaunch {
+                authority: payer,
+                launch_config,
+                treasury_vault,
+                treasury_authority,
+                mint: mint.pubkey(),
+                sale_state,
+                system_program: system_program::ID,
+                token_program: spl_token::ID,
+                rent: anchor_lang::solana_program::sysvar::rent::ID,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::InitLaunch {
+                args: InitLaunchArgs {
+                    pricing: pricing.clone(),
+                    global_cap: 10_000,
+                    wallet_cap: 1_000,
+                    start_time: 0,
+                    end_time: i64::MAX / 2,
+                    whitelist_root: None,
+                },
+            }
+            .data(),
+        };
+        process_tx(&mut context, vec![ix], &[]).await.unwrap();
+
+        Self {
+            context,
+            mint: mint.pubkey(),
+            quote_mint: quote_mint.pubkey(),
+            launch_config,
+            sale_state,
+            treasury_authority,
+            quote_vault,
+            pricing,
+        }
+    }
+
+    /// Creates a funded wallet holding `BUYER_QUOTE` quote and an empty sale token
+    /// account.
+    async fn buyer(&mut self) -> Keypair {
+        let wallet = Keypair::new();
+        let payer = self.context.payer.pubkey();
+        let ixs = vec![
+            system_instruction::transfer(&payer, &wallet.pubkey(), 1_000_000_000),
+            create_associated_token_account(
+                &payer,
+                &wallet.pubkey(),
+                &self.quote_mint,
+                &spl_token::ID,
+            ),
+            create_associated_token_account(&payer, &wallet.pubkey(), &self.mint, &spl_token::ID),
+            token_instruction::mint_to(
+                &spl_token::ID,
+                &self.quote_mint,
+                &self.quote_ata(&wallet.pubkey()),
+                &payer,
+                &[],
+                BUYER_QUOTE,
+            )
+            .unwrap(),
+        ];
+        process_tx(&mut self.context, ixs, &[]).await.unwrap();
+        wallet
+    }
+
+    fn quote_ata(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address(owner, &self.quote_mint)
+    }
+
+    fn token_ata(&self, owner: &Pubkey) -> Pubkey {
+        get_associated_token_address(owner, &self.mint)
+    }
+
+    fn receipt(&self, buyer: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[RECEIPT_SEED, self.launch_config.as_ref(), buyer.as_ref()],
+            &crate::id(),
+        )
+        .0
+    }
+
+    async fn buy(
+        &mut self,
+        buyer: &Keypair,
+        amount: u64,
+    ) -> std::result::Result<(), TransactionError> {
+        let ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::Buy {
+                buyer: buyer.pubkey(),
+                quote_account: self.quote_ata(&buyer.pubkey()),
+                buyer_receipt: self.token_ata(&buyer.pubkey()),
+                launch_config: self.launch_config,
+                treasury_authority: self.treasury_authority,
+                treasury_vault: self.quote_vault,
+                sale_state: self.sale_state,
+                receipt: self.receipt(&buyer.pubkey()),
+                mint: self.mint,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::Buy {
+                amount,
+                proof: None,
+                max_quote: u64::MAX,
+            }
+            .data(),
+        };
+        process_tx(&mut self.context, vec![ix], &[buyer]).await
+    }
+
+    async fn sell(
+        &mut self,
+        seller: &Keypair,
+        amount: u64,
+    ) -> std::result::Result<(), TransactionError> {
+        let ix = Instruction {
+            program_id: crate::id(),
+            accounts: crate::accounts::Sell {
+                seller: seller.pubkey(),
+                seller_receipt: self.token_ata(&seller.pubkey()),
+                seller_quote_account: self.quote_ata(&seller.pubkey()),
+                launch_config: self.launch_config,
+                treasury_authority: self.treasury_authority,
+                treasury_vault: self.quote_vault,
+                sale_state: self.sale_state,
+                receipt: self.receipt(&seller.pubkey()),
+                mint: self.mint,
+                token_program: spl_token::ID,
+            }
+            .to_account_metas(None),
+            data: crate::instruction::Sell {
+                amount,
+                min_quote: 0,
+            }
+            .data(),
+        };
+        process_tx(&mut self.context, vec![ix], &[seller]).await
+    }
+
+    async fn receipt_state(&mut self, buyer: &Pubkey) -> Option<BuyerReceipt> {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.receipt(buyer))
+            .await
+            .unwrap()?;
+        Some(BuyerReceipt::try_deserialize(&mut account.data.as_slice()).unwrap())
+    }
+
+    async fn sale_state(&mut self) -> SaleState {
+        let account = self
+            .context
+            .banks_client
+            .get_account(self.sale_state)
+            .await
+            .unwrap()
+            .unwrap();
+        bytemuck::pod_read_unaligned(&account.data[8..8 + SaleState::LEN])
+    }
+
+    async fn token_balance(&mut self, address: Pubkey) -> u64 {
+        let account = self
+            .context
+            .banks_client
+            .get_account(address)
+            .await
+            .unwrap()
+            .unwrap();
+        TokenAccount::unpack(&account.data).unwrap().amount
+    }
+}
+
+#[tokio::test]
+async fn buy_opens_a_receipt_and_sell_draws_it_down() {
+    let mut h = Harness::setup().await;
+    let alice = h.buyer().await;
+    assert!(h.receipt_state(&alice.pubkey()).await.is_none());
+
+    // The first buy creates the receipt and counts the buyer.
+    let first = h.pricing.quote_buy(0, 100).unwrap();
+    h.buy(&alice, 100).await.unwrap();
+    let receipt = h.receipt_state(&alice.pubkey()).await.unwrap();
+    assert_eq!(receipt.launch, h.launch_config);
+    assert_eq!(receipt.buyer, alice.pubkey());
+    assert_eq!(receipt.tier, BuyerTier::Public);
+    assert_eq!(receipt.purchased, 100);
+    assert_eq!(receipt.claimed, 100);
+    assert_eq!(receipt.quote_paid, first);
+    assert_eq!(h.token_balance(h.token_ata(&alice.pubkey())).await, 100);
+    assert_eq!(h.token_balance(h.quote_vault).await, first);
+
+    // Later buys reuse it.
+    let second = h.pricing.quote_buy(100, 50).unwrap();
+    h.buy(&alice, 50).await.unwrap();
+    let receipt = h.receipt_state(&alice.pubkey()).await.unwrap();
+    assert_eq!(receipt.purchased, 150);
+    assert_eq!(receipt.claimed, 150);
+    assert_eq!(receipt.quote_paid, first + second);
+    let state = h.sale_state().await;
+    assert_eq!(state.buyer_count, 1);
+    assert_eq!(state.sold, 150);
+
+    // A second wallet opens its own receipt.
+    let bob = h.buyer().await;
+    h.buy(&bob, 10).await.unwrap();
+    assert_eq!(h.receipt_state(&bob.pubkey()).await.unwrap().purchased, 10);
+    assert_eq!(h.sale_state().await.buyer_count, 2);
+
+    // Selling burns the tokens, pays out of the quote vault and frees wallet cap.
+    let proceeds = h.pricing.quote_sell(160, 40).unwrap();
+    h.sell(&alice, 40).await.unwrap();
+    let receipt = h.receipt_state(&alice.pubkey()).await.unwrap();
+    assert_eq!(receipt.purchased, 110);
+    assert_eq!(receipt.claimed, 110);
+    assert_eq!(receipt.quote_paid, first + second - proceeds);
+    assert_eq!(h.token_balance(h.token_ata(&alice.pubkey())).await, 110);
+    assert_eq!(
+        h.token_balance(h.quote_ata(&alice.pubkey())).await,
+        BUYER_QUOTE - first - second + proceeds
+    );
+    assert_eq!(h.sale_state().await.sold, 120);
+
+    // Tokens received from another wallet do not count as bought.
+    let transfer = token_instruction::transfer(
+        &spl_token::ID,
+        &h.token_ata(&bob.pubkey()),
+        &h.token_ata(&alice.pubkey()),
+        &bob.pubkey(),
+        &[],
+        10,
+    )
+    .unwrap();
+    process_tx(&mut h.context, vec![transfer], &[&bob])
+        .await
+        .unwrap();
+    let err = h.sell(&alice, 111).await.unwrap_err();
+    assert_custom_error(err, LaunchError::SellTooLarge);
+}
+
+#[tokio::test]
+async fn sell_without_a_receipt_is_rejected() {
+    let mut h = Harness::setup().await;
+    let alice = h.buyer().await;
+    let carol = h.buyer().await;
+    h.buy(&alice, 100).await.unwrap();
+
+    // Carol holds sale tokens she got from Alice but never bought any herself.
+    let transfer = token_instruction::transfer(
+        &spl_token::ID,
+        &h.token_ata(&alice.pubkey()),
+        &h.token_ata(&carol.pubkey()),
+        &alice.pubkey(),
+        &[],
+        30,
+    )
+    .unwrap();
+    process_tx(&mut h.context, vec![transfer], &[&alice])
+        .await
+        .unwrap();
+    let err = h.sell(&carol, 30).await.unwrap_err();
+    // The receipt PDA was never created, so Anchor rejects it before `sell` runs.
+    assert_eq!(
+        err,
+        TransactionError::InstructionError(
+            0,
+            InstructionError::Custom(anchor_lang::error::ErrorCode::AccountNotInitialized as u32)
+        )
+    );
+    assert!(h.receipt_state(&carol.pubkey()).await.is_none());
+    assert_eq!(h.sale_state().await.sold, 100);
+}
+
+fn assert_custom_error(err: TransactionError, expected: LaunchError) {
+    let code = anchor_lang::error::ERROR_CODE_OFFSET + expected as u32;
+    match err {
+        TransactionError::InstructionError(_, InstructionError::Custom(actual)) => {
+            assert_eq!(actual, code, "unexpected custom error")
+        }
+        other => panic!("expected custom error {code}, got {other:?}"),
+    }
+}
+
+async fn create_mint(context: &mut ProgramTestContext, mint: &Keypair, authority: &Pubkey) {
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let create_ix = system_instruction::create_account(
+        &context.payer.pubkey(),
+        &mint.pubkey(),
+        rent.minimum_balance(Mint::LEN),
+        Mint::LEN as u64,
+        &spl_token::ID,
+    );
+    let init_ix =
+        token_instruction::initialize_mint(&spl_token::ID, &mint.pubkey(), authority, None, 6)
+            .unwrap();
+    process_tx(context, vec![create_ix, init_ix], &[mint])
+        .await
+        .unwrap();
+}
+
+async fn process_tx(
+    context: &mut ProgramTestContext,
+    instructions: Vec<Instruction>,
+    signers: &[&Keypair],
+) -> std::result::Result<(), TransactionError> {
+    let payer = context.payer.insecure_clone();
+    let mut all_signers: Vec<&Keypair> = vec![&payer];
+    all_signers.extend_from_slice(signers);
+    let blockhash = context.get_new_latest_blockhash().await.unwrap();
+    let tx = Transaction::new_signed_with_payer(
+        &instructions,
+        Some(&payer.pubkey()),
+        &all_signers,
+        blockhash,
+    );
+    context
+        .banks_client
+        .process_transaction(tx)
+        .await
+        .map_err(|err| err.unwrap())
+}
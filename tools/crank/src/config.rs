@@ -0,0 +1,105 @@
+//! Keeper configuration and investor list files (JSON, base58 pubkeys).
+
+use std::{fs, path::Path, str::FromStr};
+
+use anyhow::{Context, Result};
+use keystone_fee_router::meteora_cpi::DEFAULT_DLMM_PROGRAM_ID;
+use serde::{de::Error as _, Deserialize, Deserializer};
+use solana_sdk::pubkey::Pubkey;
+
+/// Keeper configuration for one policy.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Config {
+    /// Policy account to crank.
+    #[serde(deserialize_with = "pubkey")]
+    pub policy: Pubkey,
+    /// Meteora cp-amm program; defaults to the DAMM v2 program id.
+    #[serde(default = "default_cp_program", deserialize_with = "pubkey")]
+    pub cp_program: Pubkey,
+    /// cp-amm pool authority PDA (owner of the pool vaults).
+    #[serde(deserialize_with = "pubkey")]
+    pub cp_pool_authority: Pubkey,
+    /// Claim accounts of each honorary position, by position index.
+    pub positions: Vec<PositionConfig>,
+    /// Payer quote account receiving the crank reward, if any.
+    #[serde(default, deserialize_with = "optional_pubkey")]
+    pub payer_quote_ata: Option<Pubkey>,
+    /// Most investors per page, capped by `Policy.max_page_size` (the default);
+    /// pages shrink further to fit a transaction.
+    #[serde(default = "default_max_page_size")]
+    pub max_page_size: u64,
+}
+
+/// Pool accounts needed to claim one honorary position.
+#[derive(Clone, Debug, Deserialize)]
+pub struct PositionConfig {
+    /// Pool token A vault.
+    #[serde(deserialize_with = "pubkey")]
+    pub token_a_vault: Pubkey,
+    /// Pool token B vault.
+    #[serde(deserialize_with = "pubkey")]
+    pub token_b_vault: Pubkey,
+    /// Pool token A mint.
+    #[serde(deserialize_with = "pubkey")]
+    pub token_a_mint: Pubkey,
+    /// Pool token B mint.
+    #[serde(deserialize_with = "pubkey")]
+    pub token_b_mint: Pubkey,
+    /// Token account holding the position NFT.
+    #[serde(deserialize_with = "pubkey")]
+    pub position_nft_account: Pubkey,
+    /// Vault-owned token account for the pool's base mint.
+    #[serde(deserialize_with = "pubkey")]
+    pub treasury_base_ata: Pubkey,
+    /// Token program of the pool's base mint; defaults to SPL Token.
+    #[serde(default = "default_token_program", deserialize_with = "pubkey")]
+    pub base_token_program: Pubkey,
+}
+
+/// One investor set entry, in investor set order.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+pub struct InvestorEntry {
+    /// Investor quote ATA.
+    #[serde(deserialize_with = "pubkey")]
+    pub quote_ata: Pubkey,
+    /// Stream account of the investor.
+    #[serde(deserialize_with = "pubkey")]
+    pub stream: Pubkey,
+}
+
+impl Config {
+    /// Reads a JSON config file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        serde_json::from_slice(&data).with_context(|| format!("parsing {}", path.display()))
+    }
+}
+
+/// Reads a JSON array of investor entries.
+pub fn load_investors(path: &Path) -> Result<Vec<InvestorEntry>> {
+    let data = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+    serde_json::from_slice(&data).with_context(|| format!("parsing {}", path.display()))
+}
+
+fn default_cp_program() -> Pubkey {
+    Pubkey::from_str(DEFAULT_DLMM_PROGRAM_ID).expect("valid program id")
+}
+
+fn default_token_program() -> Pubkey {
+    spl_token::ID
+}
+
+fn default_max_page_size() -> u64 {
+    u64::MAX
+}
+
+fn pubkey<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    Pubkey::from_str(&value).map_err(D::Error::custom)
+}
+
+fn optional_pubkey<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Pubkey>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| Pubkey::from_str(&value).map_err(D::Error::custom))
+        .transpose()
+}
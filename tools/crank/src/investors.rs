@@ -0,0 +1,99 @@
+//! Investor set Merkle tree, mirroring `keystone_fee_router::investor_leaf`.
+
+use keystone_common::merkle::hash_nodes;
+use keystone_fee_router::investor_leaf;
+
+use crate::config::InvestorEntry;
+
+/// Investor list with its Merkle tree (sorted-pair keccak, odd nodes promoted).
+pub struct InvestorTree {
+    entries: Vec<InvestorEntry>,
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl InvestorTree {
+    /// Builds the tree over `entries` in investor set order.
+    pub fn new(entries: Vec<InvestorEntry>) -> Self {
+        let leaves: Vec<[u8; 32]> = entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| investor_leaf(index as u32, &entry.quote_ata, &entry.stream))
+            .collect();
+        let mut levels = vec![leaves];
+        while levels.last().is_some_and(|level| level.len() > 1) {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] if a <= b => hash_nodes(a, b),
+                    [a, b] => hash_nodes(b, a),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        Self { entries, levels }
+    }
+
+    /// Merkle root; all zeros for an empty list.
+    pub fn root(&self) -> [u8; 32] {
+        self.levels
+            .last()
+            .and_then(|level| level.first())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Number of investors.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the list is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Entry of investor `index`.
+    pub fn entry(&self, index: usize) -> InvestorEntry {
+        self.entries[index]
+    }
+
+    /// Sibling path of investor `index`.
+    pub fn proof(&self, mut index: usize) -> Vec<[u8; 32]> {
+        let mut proof = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        proof
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keystone_common::merkle::verify_merkle_proof;
+    use solana_sdk::pubkey::Pubkey;
+
+    #[test]
+    fn proofs_verify_against_root() {
+        for count in 1..=7 {
+            let entries: Vec<_> = (0..count)
+                .map(|_| InvestorEntry {
+                    quote_ata: Pubkey::new_unique(),
+                    stream: Pubkey::new_unique(),
+                })
+                .collect();
+            let tree = InvestorTree::new(entries.clone());
+            for (index, entry) in entries.iter().enumerate() {
+                let leaf = investor_leaf(index as u32, &entry.quote_ata, &entry.stream);
+                assert!(verify_merkle_proof(leaf, &tree.proof(index), tree.root()));
+            }
+        }
+    }
+}
@@ -0,0 +1,663 @@
+//! Day planning and page submission.
+//!
+//! Every step re-reads `Progress` and derives the next page from
+//! `(phase, page_cursor)`, so a page that landed but was reported as failed is
+//! never sent twice, and `is_last_page` goes out once per pass.
+
+use std::{thread, time::Duration};
+
+use anchor_lang::{system_program, AccountDeserialize, InstructionData, ToAccountMetas};
+use anyhow::{anyhow, bail, Context, Result};
+use keystone_fee_router::{
+    accounts, instruction, meteora_cpi::EVENT_AUTHORITY_SEED, position_index_seed, ClaimMode,
+    CollectPositionFeesArgs, CrankArgs, DistributionPhase, HonoraryPosition, Policy, Progress,
+    CHECKPOINT_SEED, DUST_LEDGER_SEED, FEE_POS_OWNER_SEED, HISTORY_SEED, PAYOUT_BITMAP_SEED,
+    POSITION_SEED, PROGRESS_SEED, VAULT_SEED,
+};
+use solana_sdk::{
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+
+use crate::{config::Config, investors::InvestorTree};
+
+/// Max serialized transaction size (`solana_packet::PACKET_DATA_SIZE`).
+const PACKET_DATA_SIZE: usize = 1232;
+/// Compute units requested for a page, before per-entry and per-claim costs.
+const PAGE_BASE_CU: u32 = 120_000;
+/// Compute units requested per investor entry.
+const PAGE_ENTRY_CU: u32 = 40_000;
+/// Compute units requested per extra `collect_position_fees`.
+const COLLECT_CU: u32 = 60_000;
+/// Per-transaction compute ceiling.
+const MAX_CU: u32 = 1_400_000;
+
+/// Read and send access to a cluster.
+pub trait Cluster {
+    /// Data of `address`, or `None` if the account does not exist.
+    fn account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>>;
+    /// Unix timestamp of the cluster clock.
+    fn unix_timestamp(&self) -> Result<i64>;
+    /// Blockhash for new transactions.
+    fn latest_blockhash(&self) -> Result<Hash>;
+    /// Sends `transaction` and waits until it is confirmed or rejected.
+    fn send_and_confirm(&self, transaction: &Transaction) -> Result<Signature>;
+}
+
+impl<T: Cluster + ?Sized> Cluster for &T {
+    fn account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>> {
+        (**self).account_data(address)
+    }
+
+    fn unix_timestamp(&self) -> Result<i64> {
+        (**self).unix_timestamp()
+    }
+
+    fn latest_blockhash(&self) -> Result<Hash> {
+        (**self).latest_blockhash()
+    }
+
+    fn send_and_confirm(&self, transaction: &Transaction) -> Result<Signature> {
+        (**self).send_and_confirm(transaction)
+    }
+}
+
+/// Exponential retry schedule for failed pages.
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+    /// Delay after the first failure.
+    pub initial: Duration,
+    /// Upper bound on any delay.
+    pub max: Duration,
+    /// Consecutive failures tolerated before giving up.
+    pub max_attempts: u32,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_millis(500),
+            max: Duration::from_secs(30),
+            max_attempts: 6,
+        }
+    }
+}
+
+impl Backoff {
+    /// Delay before retry number `attempt` (1-based): `initial * 2^(attempt - 1)`, capped.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 1u32 << attempt.saturating_sub(1).min(16);
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// Where the open day stands, as recorded in `Progress`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DayState {
+    /// Day being distributed.
+    pub current_day: i64,
+    /// Current pass.
+    pub phase: DistributionPhase,
+    /// Next investor index of the pass.
+    pub page_cursor: u64,
+    /// Whether the day is settled.
+    pub day_closed: bool,
+    /// Honorary positions collected today (bit per index).
+    pub collected_positions: u8,
+}
+
+impl From<&Progress> for DayState {
+    fn from(progress: &Progress) -> Self {
+        Self {
+            current_day: progress.current_day,
+            phase: progress.phase,
+            page_cursor: progress.page_cursor,
+            day_closed: progress.day_closed,
+            collected_positions: progress.collected_positions,
+        }
+    }
+}
+
+/// What the keeper does next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Step {
+    /// Today is settled; nothing to send.
+    Idle,
+    /// Send up to `len` investors starting at `cursor`.
+    Page {
+        /// Pass of the page.
+        phase: DistributionPhase,
+        /// First investor index.
+        cursor: u64,
+        /// Planned entry count (may shrink to fit a transaction).
+        len: u64,
+        /// Positions already collected today.
+        collected_positions: u8,
+    },
+    /// `day` ended without its last page; only `force_close_day` can settle it.
+    Stalled {
+        /// The open day.
+        day: i64,
+    },
+}
+
+/// Plans the next page from the on-chain day state (`None` before the first crank).
+pub fn next_step(
+    state: Option<DayState>,
+    today: i64,
+    investor_count: u32,
+    max_page_size: u64,
+) -> Step {
+    let count = investor_count as u64;
+    let fresh_day = Step::Page {
+        phase: DistributionPhase::Snapshot,
+        cursor: 0,
+        len: max_page_size.clamp(1, count.max(1)),
+        collected_positions: 0,
+    };
+    let Some(state) = state else {
+        return fresh_day;
+    };
+    if today > state.current_day {
+        return if state.day_closed {
+            fresh_day
+        } else {
+            Step::Stalled {
+                day: state.current_day,
+            }
+        };
+    }
+    if state.day_closed {
+        return Step::Idle;
+    }
+    Step::Page {
+        phase: state.phase,
+        cursor: state.page_cursor,
+        len: max_page_size.clamp(1, count.saturating_sub(state.page_cursor).max(1)),
+        collected_positions: state.collected_positions,
+    }
+}
+
+/// One transaction that landed.
+#[derive(Clone, Debug)]
+pub struct PageReport {
+    /// Pass of the page.
+    pub phase: DistributionPhase,
+    /// First investor index.
+    pub cursor: u64,
+    /// Entries sent; 0 for a transaction that only collected position fees.
+    pub len: u64,
+    /// Honorary positions whose fees the transaction collected.
+    pub collected: Vec<u8>,
+    /// Whether the page closed its pass.
+    pub is_last_page: bool,
+    /// Transaction signature.
+    pub signature: Signature,
+}
+
+/// Shape of a planned transaction.
+struct PagePlan {
+    len: u64,
+    collected: Vec<u8>,
+    is_last_page: bool,
+}
+
+/// Drives one policy's distribution days.
+pub struct Keeper<C: Cluster> {
+    cluster: C,
+    payer: Keypair,
+    config: Config,
+    investors: InvestorTree,
+    backoff: Backoff,
+}
+
+impl<C: Cluster> Keeper<C> {
+    /// Creates a keeper paying fees and rent from `payer`.
+    pub fn new(cluster: C, payer: Keypair, config: Config, investors: InvestorTree) -> Self {
+        Self {
+            cluster,
+            payer,
+            config,
+            investors,
+            backoff: Backoff::default(),
+        }
+    }
+
+    /// Replaces the retry schedule.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Sends pages until the current day is settled. Fails if the day is stalled,
+    /// the investor file does not match the policy, or a page keeps failing.
+    pub fn run_day(&self) -> Result<Vec<PageReport>> {
+        let mut reports = Vec::new();
+        let mut failures = 0;
+        loop {
+            let policy = self.policy()?;
+            if policy.paused {
+                bail!("policy is paused; pages resume once the guardian unpauses it");
+            }
+            require_investor_set(&policy, &self.investors)?;
+            let state = self
+                .progress(&policy)?
+                .as_ref()
+                .filter(|progress| progress.day_opened)
+                .map(DayState::from);
+            let today = policy
+                .epoch_index(self.cluster.unix_timestamp()?)
+                .map_err(|err| anyhow!("{err}"))?;
+            let step = next_step(
+                state,
+                today,
+                policy.investor_set.count,
+                self.config.max_page_size.min(policy.max_page_size as u64),
+            );
+            let (phase, cursor, len, collected_positions) = match step {
+                Step::Idle => return Ok(reports),
+                Step::Stalled { day } => {
+                    bail!("day {day} is still open past its end; run force_close_day")
+                }
+                Step::Page {
+                    phase,
+                    cursor,
+                    len,
+                    collected_positions,
+                } => (phase, cursor, len, collected_positions),
+            };
+            let (transaction, page) =
+                self.page_transaction(&policy, phase, cursor, len, collected_positions)?;
+            match self.cluster.send_and_confirm(&transaction) {
+                Ok(signature) => {
+                    failures = 0;
+                    reports.push(PageReport {
+                        phase,
+                        cursor,
+                        len: page.len,
+                        collected: page.collected,
+                        is_last_page: page.is_last_page,
+                        signature,
+                    });
+                }
+                Err(err) => {
+                    failures += 1;
+                    if failures >= self.backoff.max_attempts {
+                        return Err(err.context(format!(
+                            "{phase:?} page at investor {cursor} failed {failures} times"
+                        )));
+                    }
+                    thread::sleep(self.backoff.delay(failures));
+                }
+            }
+        }
+    }
+
+    fn policy(&self) -> Result<Policy> {
+        let data = self
+            .cluster
+            .account_data(&self.config.policy)?
+            .context("policy account not found")?;
+        Policy::try_deserialize(&mut data.as_slice()).map_err(|err| anyhow!("policy: {err}"))
+    }
+
+    fn progress(&self, policy: &Policy) -> Result<Option<Progress>> {
+        let address = self.pda(&[PROGRESS_SEED, policy.cp_pool.as_ref()]);
+        self.cluster
+            .account_data(&address)?
+            .map(|data| {
+                Progress::try_deserialize(&mut data.as_slice())
+                    .map_err(|err| anyhow!("progress: {err}"))
+            })
+            .transpose()
+    }
+
+    fn honorary_position(&self, index: u8) -> Result<(Pubkey, HonoraryPosition)> {
+        let address = self.pda(&[
+            POSITION_SEED,
+            self.config.policy.as_ref(),
+            position_index_seed(&index),
+        ]);
+        let data = self
+            .cluster
+            .account_data(&address)?
+            .with_context(|| format!("honorary position {index} not found"))?;
+        let position = HonoraryPosition::try_deserialize(&mut data.as_slice())
+            .map_err(|err| anyhow!("honorary position {index}: {err}"))?;
+        Ok((address, position))
+    }
+
+    fn pda(&self, seeds: &[&[u8]]) -> Pubkey {
+        Pubkey::find_program_address(seeds, &keystone_fee_router::ID).0
+    }
+
+    /// Builds the largest page of at most `len` entries that fits in a transaction.
+    /// When the first snapshot page cannot also carry every outstanding
+    /// `collect_position_fees`, builds a collect-only transaction instead.
+    fn page_transaction(
+        &self,
+        policy: &Policy,
+        phase: DistributionPhase,
+        cursor: u64,
+        len: u64,
+        collected_positions: u8,
+    ) -> Result<(Transaction, PagePlan)> {
+        let blockhash = self.cluster.latest_blockhash()?;
+        let count = policy.investor_set.count as u64;
+        // The first snapshot page collects every position not yet collected today; the
+        // crank reward it fixes covers all of them.
+        let uncollected: Vec<u8> = (0..policy.position_count)
+            .filter(|index| collected_positions & (1 << index) == 0)
+            .collect();
+        let sign = |instructions: &[Instruction]| {
+            Transaction::new_signed_with_payer(
+                instructions,
+                Some(&self.payer.pubkey()),
+                &[&self.payer],
+                blockhash,
+            )
+        };
+        let fits = |transaction: &Transaction| -> Result<bool> {
+            Ok(bincode::serialized_size(transaction)? as usize <= PACKET_DATA_SIZE)
+        };
+        for len in (1..=len).rev() {
+            let is_last_page = cursor + len == count;
+            let collected = match phase {
+                DistributionPhase::Snapshot => uncollected.clone(),
+                DistributionPhase::Payout => Vec::new(),
+            };
+            let instructions =
+                self.page_instructions(policy, phase, cursor, len, is_last_page, &collected)?;
+            let transaction = sign(&instructions);
+            if fits(&transaction)? {
+                let page = PagePlan {
+                    len,
+                    collected,
+                    is_last_page,
+                };
+                return Ok((transaction, page));
+            }
+        }
+        // `collect_position_fees` opens the day itself. Collect from the last position
+        // so the page keeps its own.
+        let snapshot = phase == DistributionPhase::Snapshot;
+        if let (true, Some(&index)) = (snapshot, uncollected.last()) {
+            let transaction = sign(&[
+                ComputeBudgetInstruction::set_compute_unit_limit(PAGE_BASE_CU + COLLECT_CU),
+                self.collect_instruction(policy, index)?,
+            ]);
+            if fits(&transaction)? {
+                let page = PagePlan {
+                    len: 0,
+                    collected: vec![index],
+                    is_last_page: false,
+                };
+                return Ok((transaction, page));
+            }
+        }
+        bail!("a single-investor page does not fit in a transaction")
+    }
+
+    /// Page instructions. `collected[0]` (default 0) is the page's own position; the
+    /// rest go out as `collect_position_fees` ahead of the crank.
+    fn page_instructions(
+        &self,
+        policy: &Policy,
+        phase: DistributionPhase,
+        cursor: u64,
+        len: u64,
+        is_last_page: bool,
+        collected: &[u8],
+    ) -> Result<Vec<Instruction>> {
+        let policy_key = self.config.policy;
+        let (position_index, extra_collects) = match collected.split_first() {
+            Some((first, rest)) => (*first, rest),
+            None => (0, &[][..]),
+        };
+
+        let units =
+            PAGE_BASE_CU + PAGE_ENTRY_CU * len as u32 + COLLECT_CU * extra_collects.len() as u32;
+        let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(
+            units.min(MAX_CU),
+        )];
+        for &index in extra_collects {
+            instructions.push(self.collect_instruction(policy, index)?);
+        }
+        let first = self.investors.entry(cursor as usize);
+        let (honorary_position, position) = self.honorary_position(position_index)?;
+        let position_config = self.position_config(position_index)?;
+        let vault_authority = self.pda(&[VAULT_SEED, policy_key.as_ref()]);
+        let mut metas = accounts::CrankDistribute {
+            cp_program: self.config.cp_program,
+            cp_pool: position.cp_pool,
+            cp_pool_authority: self.config.cp_pool_authority,
+            policy: policy_key,
+            honorary_position,
+            owner_pda: position.owner_pda,
+            cp_position: position.position,
+            position_nft_account: position_config.position_nft_account,
+            token_a_vault: position_config.token_a_vault,
+            token_b_vault: position_config.token_b_vault,
+            token_a_mint: position_config.token_a_mint,
+            token_b_mint: position_config.token_b_mint,
+            event_authority: self.event_authority(),
+            progress: self.pda(&[PROGRESS_SEED, policy.cp_pool.as_ref()]),
+            payout_bitmap: (phase == DistributionPhase::Payout)
+                .then(|| self.pda(&[PAYOUT_BITMAP_SEED, policy_key.as_ref()])),
+            history: self.pda(&[HISTORY_SEED, policy_key.as_ref()]),
+            payer: self.payer.pubkey(),
+            payer_quote_ata: self.config.payer_quote_ata,
+            vault_authority,
+            treasury_quote_ata: policy.treasury_quote_ata,
+            treasury_base_ata: position_config.treasury_base_ata,
+            creator_quote_ata: policy.creator_quote_ata,
+            investor_quote_ata: first.quote_ata,
+            stream: first.stream,
+            dust_ledger: (policy.claim_mode == ClaimMode::Push)
+                .then(|| self.dust_ledger(&first.quote_ata)),
+            checkpoint: (policy.claim_mode == ClaimMode::Pull)
+                .then(|| self.checkpoint(&first.quote_ata)),
+            quote_mint: policy.quote_mint,
+            token_program: policy.quote_token_program,
+            base_token_program: position_config.base_token_program,
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None);
+        // Entries after the first: (quote ATA, stream, dust ledger) triples, with the
+        // checkpoint in place of the ledger in pull mode.
+        for index in cursor + 1..cursor + len {
+            let entry = self.investors.entry(index as usize);
+            let entry_pda = match policy.claim_mode {
+                ClaimMode::Push => self.dust_ledger(&entry.quote_ata),
+                ClaimMode::Pull => self.checkpoint(&entry.quote_ata),
+            };
+            metas.push(AccountMeta::new(entry.quote_ata, false));
+            metas.push(AccountMeta::new_readonly(entry.stream, false));
+            metas.push(AccountMeta::new(entry_pda, false));
+        }
+        if is_last_page {
+            metas.extend(
+                policy
+                    .remainder_split
+                    .recipients()
+                    .iter()
+                    .map(|recipient| AccountMeta::new(recipient.quote_ata, false)),
+            );
+        }
+        let args = CrankArgs {
+            page_cursor: cursor,
+            is_last_page,
+            phase,
+            position_index,
+            proofs: (cursor..cursor + len)
+                .map(|index| self.investors.proof(index as usize))
+                .collect(),
+        };
+        instructions.push(Instruction {
+            program_id: keystone_fee_router::ID,
+            accounts: metas,
+            data: instruction::CrankDistribute { args }.data(),
+        });
+        Ok(instructions)
+    }
+
+    fn collect_instruction(&self, policy: &Policy, index: u8) -> Result<Instruction> {
+        let policy_key = self.config.policy;
+        let (honorary_position, position) = self.honorary_position(index)?;
+        let position_config = self.position_config(index)?;
+        Ok(Instruction {
+            program_id: keystone_fee_router::ID,
+            accounts: accounts::CollectPositionFees {
+                cp_program: self.config.cp_program,
+                cp_pool: position.cp_pool,
+                cp_pool_authority: self.config.cp_pool_authority,
+                policy: policy_key,
+                honorary_position,
+                owner_pda: self.pda(&[
+                    VAULT_SEED,
+                    policy_key.as_ref(),
+                    FEE_POS_OWNER_SEED,
+                    position_index_seed(&index),
+                ]),
+                cp_position: position.position,
+                position_nft_account: position_config.position_nft_account,
+                token_a_vault: position_config.token_a_vault,
+                token_b_vault: position_config.token_b_vault,
+                token_a_mint: position_config.token_a_mint,
+                token_b_mint: position_config.token_b_mint,
+                event_authority: self.event_authority(),
+                progress: self.pda(&[PROGRESS_SEED, policy.cp_pool.as_ref()]),
+                vault_authority: self.pda(&[VAULT_SEED, policy_key.as_ref()]),
+                payer: self.payer.pubkey(),
+                treasury_quote_ata: policy.treasury_quote_ata,
+                treasury_base_ata: position_config.treasury_base_ata,
+                token_program: policy.quote_token_program,
+                base_token_program: position_config.base_token_program,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: instruction::CollectPositionFees {
+                args: CollectPositionFeesArgs {
+                    position_index: index,
+                },
+            }
+            .data(),
+        })
+    }
+
+    fn position_config(&self, index: u8) -> Result<&crate::config::PositionConfig> {
+        self.config
+            .positions
+            .get(index as usize)
+            .with_context(|| format!("no config for honorary position {index}"))
+    }
+
+    fn event_authority(&self) -> Pubkey {
+        Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], &self.config.cp_program).0
+    }
+
+    fn dust_ledger(&self, investor_quote_ata: &Pubkey) -> Pubkey {
+        self.pda(&[
+            DUST_LEDGER_SEED,
+            self.config.policy.as_ref(),
+            investor_quote_ata.as_ref(),
+        ])
+    }
+
+    fn checkpoint(&self, investor_quote_ata: &Pubkey) -> Pubkey {
+        self.pda(&[
+            CHECKPOINT_SEED,
+            self.config.policy.as_ref(),
+            investor_quote_ata.as_ref(),
+        ])
+    }
+}
+
+/// Refuses to crank when the investor file does not hash to the policy's set.
+fn require_investor_set(policy: &Policy, investors: &InvestorTree) -> Result<()> {
+    if investors.len() != policy.investor_set.count as usize
+        || investors.root() != policy.investor_set.root
+    {
+        bail!(
+            "investor file ({} entries) does not match the policy investor set ({} entries)",
+            investors.len(),
+            policy.investor_set.count
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(phase: DistributionPhase, page_cursor: u64) -> DayState {
+        DayState {
+            current_day: 10,
+            phase,
+            page_cursor,
+            day_closed: false,
+            collected_positions: 0b1,
+        }
+    }
+
+    #[test]
+    fn resumes_from_the_recorded_cursor() {
+        let page = |phase, cursor, len, collected_positions| Step::Page {
+            phase,
+            cursor,
+            len,
+            collected_positions,
+        };
+        // First crank ever, and a new day after a closed one, start the snapshot pass.
+        assert_eq!(
+            next_step(None, 10, 5, 2),
+            page(DistributionPhase::Snapshot, 0, 2, 0)
+        );
+        let closed = DayState {
+            day_closed: true,
+            ..open(DistributionPhase::Payout, 0)
+        };
+        assert_eq!(
+            next_step(Some(closed), 11, 5, 2),
+            page(DistributionPhase::Snapshot, 0, 2, 0)
+        );
+        assert_eq!(next_step(Some(closed), 10, 5, 2), Step::Idle);
+
+        // Mid-pass: continue at the cursor; the tail page shrinks to what is left.
+        assert_eq!(
+            next_step(Some(open(DistributionPhase::Snapshot, 2)), 10, 5, 2),
+            page(DistributionPhase::Snapshot, 2, 2, 0b1)
+        );
+        assert_eq!(
+            next_step(Some(open(DistributionPhase::Payout, 4)), 10, 5, 2),
+            page(DistributionPhase::Payout, 4, 1, 0b1)
+        );
+
+        // An open day that already ended cannot be cranked.
+        assert_eq!(
+            next_step(Some(open(DistributionPhase::Payout, 4)), 11, 5, 2),
+            Step::Stalled { day: 10 }
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let backoff = Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+            max_attempts: 5,
+        };
+        let delays: Vec<_> = (1..=5).map(|attempt| backoff.delay(attempt)).collect();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 500, 500]
+                .map(Duration::from_millis)
+                .to_vec()
+        );
+    }
+}
@@ -0,0 +1,16 @@
+#![deny(missing_docs)]
+//! Keeper for the Keystone fee router's daily distribution.
+//!
+//! Reads `Policy` and `Progress` from the cluster, pages the investor list through
+//! `crank_distribute` (snapshot pass, then payout pass), resumes from
+//! `Progress::page_cursor` after a crash and retries failed pages with backoff.
+
+pub mod config;
+pub mod investors;
+pub mod keeper;
+pub mod rpc;
+
+pub use config::{Config, InvestorEntry, PositionConfig};
+pub use investors::InvestorTree;
+pub use keeper::{next_step, Backoff, Cluster, DayState, Keeper, PageReport, Step};
+pub use rpc::RpcCluster;
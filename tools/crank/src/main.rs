@@ -0,0 +1,91 @@
+//! `keystone-crank`: runs the fee router's daily distribution for one policy.
+//!
+//! ```text
+//! keystone-crank --url <rpc> --keypair <payer.json> --config <config.json> \
+//!     --investors <investors.json> [--watch <seconds>]
+//! ```
+
+use std::{env, path::PathBuf, process, thread, time::Duration};
+
+use anyhow::{bail, Context, Result};
+use keystone_crank::{config, Config, InvestorTree, Keeper, RpcCluster};
+use solana_sdk::signature::read_keypair_file;
+
+struct Args {
+    url: String,
+    keypair: PathBuf,
+    config: PathBuf,
+    investors: PathBuf,
+    watch: Option<Duration>,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut url = None;
+    let mut keypair = None;
+    let mut config = None;
+    let mut investors = None;
+    let mut watch = None;
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().with_context(|| format!("{flag} needs a value"));
+        match flag.as_str() {
+            "--url" => url = Some(value()?),
+            "--keypair" => keypair = Some(PathBuf::from(value()?)),
+            "--config" => config = Some(PathBuf::from(value()?)),
+            "--investors" => investors = Some(PathBuf::from(value()?)),
+            "--watch" => watch = Some(Duration::from_secs(value()?.parse()?)),
+            _ => bail!("unknown argument {flag}"),
+        }
+    }
+    Ok(Args {
+        url: url.unwrap_or_else(|| "http://127.0.0.1:8899".to_string()),
+        keypair: keypair.context("--keypair is required")?,
+        config: config.context("--config is required")?,
+        investors: investors.context("--investors is required")?,
+        watch,
+    })
+}
+
+fn run() -> Result<()> {
+    let args = parse_args()?;
+    let payer = read_keypair_file(&args.keypair)
+        .map_err(|err| anyhow::anyhow!("reading {}: {err}", args.keypair.display()))?;
+    let keeper = Keeper::new(
+        RpcCluster::new(&args.url),
+        payer,
+        Config::load(&args.config)?,
+        InvestorTree::new(config::load_investors(&args.investors)?),
+    );
+    loop {
+        match keeper.run_day() {
+            Ok(pages) => {
+                for page in &pages {
+                    if page.len == 0 {
+                        println!(
+                            "collected positions {:?}: {}",
+                            page.collected, page.signature
+                        );
+                        continue;
+                    }
+                    println!(
+                        "{:?} page at investor {} ({} entries, last: {}): {}",
+                        page.phase, page.cursor, page.len, page.is_last_page, page.signature
+                    );
+                }
+            }
+            Err(err) if args.watch.is_some() => eprintln!("crank failed: {err:#}"),
+            Err(err) => return Err(err),
+        }
+        match args.watch {
+            Some(interval) => thread::sleep(interval),
+            None => return Ok(()),
+        }
+    }
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {err:#}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,47 @@
+//! `Cluster` over JSON-RPC (mainnet, devnet or `solana-test-validator`).
+
+use anyhow::{Context, Result};
+use solana_rpc_client::rpc_client::RpcClient;
+use solana_sdk::{
+    clock::Clock, commitment_config::CommitmentConfig, hash::Hash, pubkey::Pubkey,
+    signature::Signature, sysvar, transaction::Transaction,
+};
+
+use crate::keeper::Cluster;
+
+/// JSON-RPC cluster access at `confirmed` commitment.
+pub struct RpcCluster {
+    client: RpcClient,
+}
+
+impl RpcCluster {
+    /// Connects to the RPC endpoint at `url`.
+    pub fn new(url: impl ToString) -> Self {
+        Self {
+            client: RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed()),
+        }
+    }
+}
+
+impl Cluster for RpcCluster {
+    fn account_data(&self, address: &Pubkey) -> Result<Option<Vec<u8>>> {
+        let response = self
+            .client
+            .get_account_with_commitment(address, self.client.commitment())?;
+        Ok(response.value.map(|account| account.data))
+    }
+
+    fn unix_timestamp(&self) -> Result<i64> {
+        let account = self.client.get_account(&sysvar::clock::ID)?;
+        let clock: Clock = bincode::deserialize(&account.data).context("clock sysvar")?;
+        Ok(clock.unix_timestamp)
+    }
+
+    fn latest_blockhash(&self) -> Result<Hash> {
+        Ok(self.client.get_latest_blockhash()?)
+    }
+
+    fn send_and_confirm(&self, transaction: &Transaction) -> Result<Signature> {
+        Ok(self.client.send_and_confirm_transaction(transaction)?)
+    }
+}
@@ -0,0 +1,557 @@
+// Keeper tests on `solana-program-test`: the fee router plus a mock cp-amm at the
+// DAMM v2 program id, driven through the same `Cluster` trait as the RPC client.
+// `FlakyBanks` drops sends or confirmations on demand to exercise retries and resume.
+
+use std::{cell::Cell, str::FromStr, time::Duration};
+
+use anchor_lang::solana_program::{
+    entrypoint::ProgramResult, program::invoke_signed, program_error::ProgramError,
+    program_pack::Pack, sysvar,
+};
+use anchor_lang::{
+    prelude::*, system_program, AccountDeserialize, InstructionData, ToAccountMetas,
+};
+use anyhow::anyhow;
+use keystone_crank::{
+    Backoff, Cluster, Config, InvestorEntry, InvestorTree, Keeper, PositionConfig,
+};
+use keystone_fee_router::{
+    meteora_cpi::{
+        CLAIM_POSITION_FEE_DISCRIMINATOR, COLLECT_FEE_MODE_ONLY_B, DEFAULT_DLMM_PROGRAM_ID,
+        POOL_COLLECT_FEE_MODE_OFFSET, POOL_DISCRIMINATOR, POOL_TOKEN_A_MINT_OFFSET,
+        POOL_TOKEN_B_MINT_OFFSET,
+    },
+    stream_adapter::streamflow::STREAMFLOW_PROGRAM_ID,
+    ClaimMode, DistributionPhase, InitPolicyArgs, InvestorSet, LockSource, Progress,
+};
+use solana_program_test::{processor, BanksClient, ProgramTest};
+#[allow(deprecated)]
+use solana_sdk::system_instruction;
+use solana_sdk::{
+    account::AccountSharedData,
+    hash::Hash,
+    instruction::Instruction,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account,
+};
+use spl_token::{instruction as token_instruction, state::Mint};
+use tokio::runtime::Runtime;
+
+const MOCK_POOL_AUTHORITY_SEED: &[u8] = b"pool_authority";
+
+/// Adapts Anchor's lifetime-bound entrypoint to the `processor!` signature.
+fn fee_router_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(accounts.to_vec().into_boxed_slice());
+    keystone_fee_router::entry(program_id, accounts, data)
+}
+
+/// Mock cp-amm `claim_position_fee`: pays the position's pending
+/// `fee_a: u64 LE || fee_b: u64 LE` out of the pool vaults.
+fn mock_cp_amm(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if data.get(..8) != Some(&CLAIM_POSITION_FEE_DISCRIMINATOR[..]) {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let [pool_authority, _pool, position, token_a_account, token_b_account, token_a_vault, token_b_vault, _token_a_mint, _token_b_mint, _position_nft_account, owner, token_program, ..] =
+        accounts
+    else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    if !owner.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    let (authority, bump) = Pubkey::find_program_address(&[MOCK_POOL_AUTHORITY_SEED], program_id);
+    let fees = {
+        let data = position.data.borrow();
+        [
+            u64::from_le_bytes(data[..8].try_into().unwrap()),
+            u64::from_le_bytes(data[8..16].try_into().unwrap()),
+        ]
+    };
+    for (amount, vault, destination) in [
+        (fees[0], token_a_vault, token_a_account),
+        (fees[1], token_b_vault, token_b_account),
+    ] {
+        if amount > 0 {
+            invoke_signed(
+                &token_instruction::transfer(
+                    token_program.key,
+                    vault.key,
+                    destination.key,
+                    &authority,
+                    &[],
+                    amount,
+                )?,
+                &[
+                    vault.clone(),
+                    destination.clone(),
+                    pool_authority.clone(),
+                    token_program.clone(),
+                ],
+                &[&[MOCK_POOL_AUTHORITY_SEED, &[bump]]],
+            )?;
+        }
+    }
+    position.data.borrow_mut()[..16].fill(0);
+    Ok(())
+}
+
+/// cp-amm `Pool` data with quote as token B under `CollectFeeMode::OnlyB`.
+fn pool_data(token_a_mint: &Pubkey, token_b_mint: &Pubkey) -> Vec<u8> {
+    let mut data = vec![0u8; POOL_COLLECT_FEE_MODE_OFFSET + 1];
+    data[..8].copy_from_slice(&POOL_DISCRIMINATOR);
+    data[POOL_TOKEN_A_MINT_OFFSET..][..32].copy_from_slice(token_a_mint.as_ref());
+    data[POOL_TOKEN_B_MINT_OFFSET..][..32].copy_from_slice(token_b_mint.as_ref());
+    data[POOL_COLLECT_FEE_MODE_OFFSET] = COLLECT_FEE_MODE_ONLY_B;
+    data
+}
+
+/// Banks client `Cluster` that stops landing transactions once `landing_budget`
+/// is spent, and can land a transaction but report failure (`lose_confirmations`).
+struct FlakyBanks {
+    runtime: Runtime,
+    client: BanksClient,
+    landing_budget: Cell<u32>,
+    lose_confirmations: Cell<u32>,
+    sent: Cell<u32>,
+}
+
+impl Cluster for FlakyBanks {
+    fn account_data(&self, address: &Pubkey) -> anyhow::Result<Option<Vec<u8>>> {
+        let account = self.runtime.block_on(self.client.get_account(*address))?;
+        Ok(account.map(|account| account.data))
+    }
+
+    fn unix_timestamp(&self) -> anyhow::Result<i64> {
+        let clock: Clock = self.runtime.block_on(self.client.get_sysvar())?;
+        Ok(clock.unix_timestamp)
+    }
+
+    fn latest_blockhash(&self) -> anyhow::Result<Hash> {
+        Ok(self.runtime.block_on(self.client.get_latest_blockhash())?)
+    }
+
+    fn send_and_confirm(&self, transaction: &Transaction) -> anyhow::Result<Signature> {
+        if !take(&self.landing_budget) {
+            return Err(anyhow!("send rejected"));
+        }
+        self.runtime
+            .block_on(self.client.process_transaction(transaction.clone()))?;
+        self.sent.set(self.sent.get() + 1);
+        if take(&self.lose_confirmations) {
+            return Err(anyhow!("confirmation timed out"));
+        }
+        Ok(transaction.signatures[0])
+    }
+}
+
+/// Decrements `counter` if positive; returns whether it was.
+fn take(counter: &Cell<u32>) -> bool {
+    let left = counter.get();
+    counter.set(left.saturating_sub(1));
+    left > 0
+}
+
+struct Setup {
+    cluster: FlakyBanks,
+    payer: Keypair,
+    config: Config,
+    investors: Vec<InvestorEntry>,
+    creator_ata: Pubkey,
+    progress: Pubkey,
+}
+
+impl Setup {
+    /// Fee router policy over `locked.len()` investors with one honorary position
+    /// per `fees` entry, each holding that much pending quote.
+    fn new(locked: &[u64], fees: &[u64]) -> Self {
+        let runtime = Runtime::new().unwrap();
+        let dlmm_program = Pubkey::from_str(DEFAULT_DLMM_PROGRAM_ID).unwrap();
+        let mut test = ProgramTest::new(
+            "keystone_fee_router",
+            keystone_fee_router::ID,
+            processor!(fee_router_entry),
+        );
+        test.add_program("mock_cp_amm", dlmm_program, processor!(mock_cp_amm));
+        let mut context = runtime.block_on(test.start_with_context());
+        let payer = context.payer.insecure_clone();
+        let client = context.banks_client.clone();
+        let rent = runtime.block_on(client.get_rent()).unwrap();
+        let send = |instructions: &[Instruction], signers: &[&Keypair]| {
+            let blockhash = runtime.block_on(client.get_latest_blockhash()).unwrap();
+            let mut all = vec![&payer];
+            all.extend_from_slice(signers);
+            let tx = Transaction::new_signed_with_payer(
+                instructions,
+                Some(&payer.pubkey()),
+                &all,
+                blockhash,
+            );
+            runtime.block_on(client.process_transaction(tx)).unwrap();
+        };
+        let mint = |mint: &Keypair| {
+            send(
+                &[
+                    system_instruction::create_account(
+                        &payer.pubkey(),
+                        &mint.pubkey(),
+                        rent.minimum_balance(Mint::LEN),
+                        Mint::LEN as u64,
+                        &spl_token::ID,
+                    ),
+                    token_instruction::initialize_mint(
+                        &spl_token::ID,
+                        &mint.pubkey(),
+                        &payer.pubkey(),
+                        None,
+                        6,
+                    )
+                    .unwrap(),
+                ],
+                &[mint],
+            );
+        };
+        let ata = |owner: &Pubkey, mint: &Pubkey| {
+            send(
+                &[create_associated_token_account(
+                    &payer.pubkey(),
+                    owner,
+                    mint,
+                    &spl_token::ID,
+                )],
+                &[],
+            );
+            get_associated_token_address(owner, mint)
+        };
+
+        let quote_mint = Keypair::new();
+        mint(&quote_mint);
+        let quote_mint = quote_mint.pubkey();
+        let (pool_authority, _) =
+            Pubkey::find_program_address(&[MOCK_POOL_AUTHORITY_SEED], &dlmm_program);
+        let quote_vault = ata(&pool_authority, &quote_mint);
+        // One `OnlyB` pool per position, pairing a fresh base mint (token A) with quote.
+        let mut pools = Vec::new();
+        let mut base_mints = Vec::new();
+        for _ in fees {
+            let base_mint = Keypair::new();
+            mint(&base_mint);
+            let pool = Pubkey::new_unique();
+            let data = pool_data(&base_mint.pubkey(), &quote_mint);
+            let mut account =
+                AccountSharedData::new(rent.minimum_balance(data.len()), data.len(), &dlmm_program);
+            account.set_data_from_slice(&data);
+            context.set_account(&pool, &account);
+            pools.push(pool);
+            base_mints.push(base_mint.pubkey());
+        }
+        let policy = Pubkey::find_program_address(
+            &[keystone_fee_router::POLICY_SEED, pools[0].as_ref()],
+            &keystone_fee_router::ID,
+        )
+        .0;
+        let vault_authority = Pubkey::find_program_address(
+            &[keystone_fee_router::VAULT_SEED, policy.as_ref()],
+            &keystone_fee_router::ID,
+        )
+        .0;
+        let treasury_ata = ata(&vault_authority, &quote_mint);
+        let creator_ata = ata(&Pubkey::new_unique(), &quote_mint);
+
+        let streamflow = Pubkey::from_str(STREAMFLOW_PROGRAM_ID).unwrap();
+        let mut investors = Vec::new();
+        for &amount in locked {
+            // Mock stream: locked amount, then the recipient (the ATA owner).
+            let wallet = Pubkey::new_unique();
+            let stream = Pubkey::new_unique();
+            let mut data = amount.to_le_bytes().to_vec();
+            data.extend_from_slice(wallet.as_ref());
+            let mut account =
+                AccountSharedData::new(rent.minimum_balance(data.len()), data.len(), &streamflow);
+            account.set_data_from_slice(&data);
+            context.set_account(&stream, &account);
+            investors.push(InvestorEntry {
+                quote_ata: ata(&wallet, &quote_mint),
+                stream,
+            });
+        }
+        let tree = InvestorTree::new(investors.clone());
+        send(
+            &[Instruction {
+                program_id: keystone_fee_router::ID,
+                accounts: keystone_fee_router::accounts::InitPolicy {
+                    authority: payer.pubkey(),
+                    policy,
+                    cp_pool: pools[0],
+                    quote_mint,
+                    creator_quote_ata: creator_ata,
+                    treasury_quote_ata: treasury_ata,
+                    vault_authority,
+                    token_program: spl_token::ID,
+                    system_program: system_program::ID,
+                    rent: sysvar::rent::ID,
+                }
+                .to_account_metas(None),
+                data: keystone_fee_router::instruction::InitPolicy {
+                    args: InitPolicyArgs {
+                        guardian: Pubkey::new_unique(),
+                        y0_total: 1_000_000,
+                        investor_fee_share_bps: 2_000,
+                        daily_cap_quote: 0,
+                        min_payout_lamports: 0,
+                        crank_reward_bps: 0,
+                        crank_reward_cap_quote: 0,
+                        investor_set: InvestorSet {
+                            root: tree.root(),
+                            count: locked.len() as u32,
+                        },
+                        force_close_grace_secs: 3_600,
+                        roll_forced_carry: false,
+                        claim_mode: ClaimMode::Push,
+                        lock_source: LockSource::Streamflow,
+                        remainder_recipients: Vec::new(),
+                        epoch_seconds: 86_400,
+                        epoch_offset_seconds: 0,
+                        max_page_size: 4,
+                    },
+                }
+                .data(),
+            }],
+            &[],
+        );
+
+        let mut positions = Vec::new();
+        for (index, ((&pool, &base_mint), &fee)) in
+            pools.iter().zip(&base_mints).zip(fees).enumerate()
+        {
+            let cp_position = Pubkey::new_unique();
+            let mut account = AccountSharedData::new(rent.minimum_balance(16), 16, &dlmm_program);
+            let mut data = 0u64.to_le_bytes().to_vec();
+            data.extend_from_slice(&fee.to_le_bytes());
+            account.set_data_from_slice(&data);
+            context.set_account(&cp_position, &account);
+            send(
+                &[token_instruction::mint_to(
+                    &spl_token::ID,
+                    &quote_mint,
+                    &quote_vault,
+                    &payer.pubkey(),
+                    &[],
+                    fee,
+                )
+                .unwrap()],
+                &[],
+            );
+
+            let index = index as u8;
+            let seed = keystone_fee_router::position_index_seed(&index);
+            let pda =
+                |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &keystone_fee_router::ID).0;
+            let owner_pda = pda(&[
+                keystone_fee_router::VAULT_SEED,
+                policy.as_ref(),
+                keystone_fee_router::FEE_POS_OWNER_SEED,
+                seed,
+            ]);
+            send(
+                &[Instruction {
+                    program_id: keystone_fee_router::ID,
+                    accounts: keystone_fee_router::accounts::InitHonoraryPosition {
+                        authority: payer.pubkey(),
+                        policy,
+                        cp_pool: pool,
+                        quote_mint,
+                        owner_pda,
+                        cp_position,
+                        honorary_position: pda(&[
+                            keystone_fee_router::POSITION_SEED,
+                            policy.as_ref(),
+                            seed,
+                        ]),
+                        system_program: system_program::ID,
+                        rent: sysvar::rent::ID,
+                    }
+                    .to_account_metas(None),
+                    data: keystone_fee_router::instruction::InitHonoraryPosition {}.data(),
+                }],
+                &[],
+            );
+            positions.push(PositionConfig {
+                token_a_vault: ata(&pool_authority, &base_mint),
+                token_b_vault: quote_vault,
+                token_a_mint: base_mint,
+                token_b_mint: quote_mint,
+                position_nft_account: Pubkey::new_unique(),
+                treasury_base_ata: ata(&vault_authority, &base_mint),
+                base_token_program: spl_token::ID,
+            });
+        }
+
+        let progress = Pubkey::find_program_address(
+            &[keystone_fee_router::PROGRESS_SEED, pools[0].as_ref()],
+            &keystone_fee_router::ID,
+        )
+        .0;
+        let config = Config {
+            policy,
+            cp_program: dlmm_program,
+            cp_pool_authority: pool_authority,
+            positions,
+            payer_quote_ata: None,
+            max_page_size: 1,
+        };
+        Self {
+            cluster: FlakyBanks {
+                runtime,
+                client,
+                landing_budget: Cell::new(u32::MAX),
+                lose_confirmations: Cell::new(0),
+                sent: Cell::new(0),
+            },
+            payer,
+            config,
+            investors,
+            creator_ata,
+            progress,
+        }
+    }
+
+    fn keeper(&self, max_attempts: u32) -> Keeper<&FlakyBanks> {
+        Keeper::new(
+            &self.cluster,
+            self.payer.insecure_clone(),
+            self.config.clone(),
+            InvestorTree::new(self.investors.clone()),
+        )
+        .with_backoff(Backoff {
+            initial: Duration::ZERO,
+            max: Duration::ZERO,
+            max_attempts,
+        })
+    }
+
+    fn progress(&self) -> Progress {
+        let data = self.cluster.account_data(&self.progress).unwrap().unwrap();
+        Progress::try_deserialize(&mut data.as_slice()).unwrap()
+    }
+
+    fn balance(&self, address: &Pubkey) -> u64 {
+        let data = self.cluster.account_data(address).unwrap().unwrap();
+        spl_token::state::Account::unpack(&data).unwrap().amount
+    }
+}
+
+#[test]
+fn keeper_settles_a_day_across_positions() {
+    let setup = Setup::new(&[300_000, 100_000, 100_000], &[6_000, 4_000]);
+    let pages = setup.keeper(1).run_day().unwrap();
+    let sent: Vec<_> = pages
+        .iter()
+        .map(|page| (page.phase, page.cursor, page.len, page.is_last_page))
+        .collect();
+    // Position 1 is collected on its own ahead of the first snapshot page.
+    assert_eq!(
+        sent,
+        [
+            (DistributionPhase::Snapshot, 0, 0, false),
+            (DistributionPhase::Snapshot, 0, 1, false),
+            (DistributionPhase::Snapshot, 1, 1, false),
+            (DistributionPhase::Snapshot, 2, 1, true),
+            (DistributionPhase::Payout, 0, 1, false),
+            (DistributionPhase::Payout, 1, 1, false),
+            (DistributionPhase::Payout, 2, 1, true),
+        ]
+    );
+    let progress = setup.progress();
+    assert!(progress.day_closed);
+    assert_eq!(progress.claimed_quote_today, 10_000);
+    assert_eq!(progress.collected_positions, 0b11);
+    let paid: Vec<_> = setup
+        .investors
+        .iter()
+        .map(|entry| setup.balance(&entry.quote_ata))
+        .collect();
+    assert_eq!(paid, [1_200, 400, 400]);
+    assert_eq!(setup.balance(&setup.creator_ata), 8_000);
+
+    // The day is settled; a second run sends nothing.
+    assert!(setup.keeper(1).run_day().unwrap().is_empty());
+}
+
+#[test]
+fn keeper_collects_every_position_before_the_first_snapshot_page() {
+    let setup = Setup::new(&[300_000, 200_000], &[3_000, 2_000, 1_000]);
+    let pages = setup.keeper(1).run_day().unwrap();
+    let sent: Vec<_> = pages
+        .iter()
+        .map(|page| (page.phase, page.cursor, page.len, page.collected.clone()))
+        .collect();
+    // The first snapshot page cannot carry extra collects, so they go out alone.
+    assert_eq!(
+        sent,
+        [
+            (DistributionPhase::Snapshot, 0, 0, vec![2]),
+            (DistributionPhase::Snapshot, 0, 0, vec![1]),
+            (DistributionPhase::Snapshot, 0, 1, vec![0]),
+            (DistributionPhase::Snapshot, 1, 1, vec![]),
+            (DistributionPhase::Payout, 0, 1, vec![]),
+            (DistributionPhase::Payout, 1, 1, vec![]),
+        ]
+    );
+    let progress = setup.progress();
+    assert!(progress.day_closed);
+    assert_eq!(progress.claimed_quote_today, 6_000);
+    assert_eq!(progress.collected_positions, 0b111);
+    let paid: Vec<_> = setup
+        .investors
+        .iter()
+        .map(|entry| setup.balance(&entry.quote_ata))
+        .collect();
+    assert_eq!(paid, [720, 480]);
+}
+
+#[test]
+fn keeper_resumes_after_a_crash_and_retries_lost_confirmations() {
+    let setup = Setup::new(&[300_000, 100_000, 100_000], &[6_000, 4_000]);
+
+    // The first keeper lands the collect of position 1 and two snapshot pages, then
+    // every send fails.
+    setup.cluster.landing_budget.set(3);
+    assert!(setup.keeper(2).run_day().is_err());
+    let progress = setup.progress();
+    assert_eq!(progress.phase, DistributionPhase::Snapshot);
+    assert_eq!(progress.page_cursor, 2);
+    assert!(!progress.day_closed);
+
+    // A fresh keeper resumes at the cursor. The closing snapshot page lands but its
+    // confirmation is lost: the retry re-reads progress and moves on to the payout
+    // pass instead of sending `is_last_page` again.
+    setup.cluster.landing_budget.set(u32::MAX);
+    setup.cluster.lose_confirmations.set(1);
+    setup.cluster.sent.set(0);
+    let pages = setup.keeper(3).run_day().unwrap();
+    let sent: Vec<_> = pages
+        .iter()
+        .map(|page| (page.phase, page.cursor, page.is_last_page))
+        .collect();
+    assert_eq!(
+        sent,
+        [
+            (DistributionPhase::Payout, 0, false),
+            (DistributionPhase::Payout, 1, false),
+            (DistributionPhase::Payout, 2, true),
+        ]
+    );
+    assert_eq!(setup.cluster.sent.get(), 4);
+    let progress = setup.progress();
+    assert!(progress.day_closed);
+    assert_eq!(progress.claimed_quote_today, 10_000);
+    let paid: Vec<_> = setup
+        .investors
+        .iter()
+        .map(|entry| setup.balance(&entry.quote_ata))
+        .collect();
+    assert_eq!(paid, [1_200, 400, 400]);
+}
@@ -0,0 +1,557 @@
+#![deny(missing_docs)]
+//! Host-side simulator for fee router distribution days.
+//!
+//! Replays the crank's days through the program's own day bookkeeping
+//! (`Progress::open_day`, `fix_crank_reward`, `pay_page_crank_reward`,
+//! `close_snapshot`, `run_payout_page`, `accrue_pull_pool`, `split_remainder`): who gets paid what, the transfer fees withheld on payouts,
+//! the remainder recipients' share, the events the crank would emit, and the carry
+//! (dust ledgers and forced-close rollover) from one day into the next.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::spl_token_2022::extension::transfer_fee::TransferFee;
+use keystone_fee_router::{
+    accrue_pull_pool, close_snapshot, compute_investor_quote, fix_crank_reward,
+    pay_page_crank_reward, run_payout_page, split_remainder, ClaimMode, CreatorPayoutDayClosed,
+    DayForceClosed, FeeRouterError, InvestorPayoutPage, InvestorSnapshotClosed, PayoutEntry,
+    Policy, Progress, RemainderPaid, RemainderRecipient,
+};
+
+/// How a replayed day ends.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DayEnd {
+    /// The last page closes the day.
+    #[default]
+    Closed,
+    /// The day stalls before its snapshot closes and is settled by `force_close_day`.
+    StalledInSnapshot,
+    /// The day stalls after this many payout pages and is settled by `force_close_day`.
+    StalledInPayout(usize),
+}
+
+/// One distribution day to replay.
+#[derive(Clone, Debug, Default)]
+pub struct DayInput {
+    /// Quote claimed from the honorary positions during the snapshot pass.
+    pub claimed_quote: u64,
+    /// Still-locked amount of each investor at the snapshot, in investor set order.
+    pub locked: Vec<u64>,
+    /// How the day ends.
+    pub end: DayEnd,
+}
+
+/// Event the crank would emit, in emission order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    /// Last snapshot page.
+    InvestorSnapshotClosed(InvestorSnapshotClosed),
+    /// Each payout page.
+    InvestorPayoutPage(InvestorPayoutPage),
+    /// Each remainder recipient at day close.
+    RemainderPaid(RemainderPaid),
+    /// Normal day close.
+    CreatorPayoutDayClosed(CreatorPayoutDayClosed),
+    /// Forced day close.
+    DayForceClosed(DayForceClosed),
+}
+
+/// What one investor got for the day.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct InvestorOutcome {
+    /// Locked amount at the snapshot.
+    pub locked: u64,
+    /// Pro-rata share of the investor pool allocated today.
+    pub share: u64,
+    /// Quote transferred today (push mode), including released dust.
+    pub payout: u64,
+    /// Quote received after the quote mint's transfer fee.
+    pub received: u64,
+    /// Dust ledger balance after the day.
+    pub dust: u64,
+}
+
+/// Result of one replayed day.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DayOutcome {
+    /// Day key.
+    pub day: i64,
+    /// Distributable quote: claimed today plus carry rolled over by a forced close.
+    pub claimed_quote: u64,
+    /// Sum of locked amounts.
+    pub locked_total: u64,
+    /// Investor pool after the crank reward, share, locked ratio and cap.
+    pub investor_pool: u64,
+    /// Crank reward paid to page payers.
+    pub crank_reward: u64,
+    /// Quote withheld by the transfer fee on today's investor payouts.
+    pub transfer_fees: u64,
+    /// Per-investor results, in investor set order.
+    pub investors: Vec<InvestorOutcome>,
+    /// Remainder paid per recipient (the creator when the policy has no split).
+    pub remainder: Vec<(RemainderRecipient, u64)>,
+    /// Quote rolled into the next day by a forced close.
+    pub rolled_over: u64,
+    /// Events in emission order.
+    pub events: Vec<Event>,
+}
+
+impl DayOutcome {
+    /// Total remainder routed to the creator side.
+    pub fn remainder_total(&self) -> u64 {
+        self.remainder.iter().map(|(_, amount)| amount).sum()
+    }
+}
+
+/// Replays fee router days for one policy, carrying dust ledgers and forced-close
+/// rollover from day to day.
+pub struct Simulator {
+    policy: Policy,
+    progress: Progress,
+    next_day: i64,
+    page_size: usize,
+    crank_reward_claimed: bool,
+    transfer_fee: Option<TransferFee>,
+    dust: Vec<u64>,
+}
+
+impl Simulator {
+    /// Starts at day key `first_day` with empty dust ledgers for the policy's
+    /// investor set; one investor per page.
+    pub fn new(policy: Policy, first_day: i64) -> Self {
+        let count = policy.investor_set.count as usize;
+        Self {
+            policy,
+            progress: Progress::default(),
+            next_day: first_day,
+            page_size: 1,
+            crank_reward_claimed: true,
+            transfer_fee: None,
+            dust: vec![0; count],
+        }
+    }
+
+    /// Investors per payout page (affects page events and the crank reward split).
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Whether page payers pass a quote account for the crank reward; unclaimed
+    /// reward goes to the remainder.
+    pub fn with_crank_reward_claimed(mut self, claimed: bool) -> Self {
+        self.crank_reward_claimed = claimed;
+        self
+    }
+
+    /// Token-2022 transfer fee of the quote mint, withheld on investor payouts.
+    pub fn with_transfer_fee(mut self, transfer_fee: TransferFee) -> Self {
+        self.transfer_fee = Some(transfer_fee);
+        self
+    }
+
+    /// Dust ledger balances, in investor set order.
+    pub fn dust(&self) -> &[u64] {
+        &self.dust
+    }
+
+    /// Quote rolled over into the next day by a forced close.
+    pub fn rolled_over(&self) -> u64 {
+        self.progress.rolled_over_quote
+    }
+
+    /// Day bookkeeping after the last replayed day.
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    /// Replays `days` in order.
+    pub fn replay(&mut self, days: &[DayInput]) -> Result<Vec<DayOutcome>> {
+        days.iter().map(|day| self.run_day(day)).collect()
+    }
+
+    /// Replays the next day.
+    pub fn run_day(&mut self, input: &DayInput) -> Result<DayOutcome> {
+        require!(
+            input.locked.len() == self.dust.len(),
+            FeeRouterError::InvalidInvestorPage
+        );
+        let day = self.next_day;
+        self.next_day += 1;
+        let progress = &mut self.progress;
+        progress.open_day(day, 0);
+        progress.claimed_quote_today = progress
+            .claimed_quote_today
+            .checked_add(input.claimed_quote)
+            .ok_or(FeeRouterError::ArithmeticOverflow)?;
+        let mut outcome = DayOutcome {
+            day,
+            claimed_quote: progress.claimed_quote_today,
+            investors: input
+                .locked
+                .iter()
+                .zip(&self.dust)
+                .map(|(&locked, &dust)| InvestorOutcome {
+                    locked,
+                    dust,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        if input.end == DayEnd::StalledInSnapshot {
+            self.force_close(&mut outcome)?;
+            return Ok(outcome);
+        }
+
+        // Snapshot pass: the first page fixes the reward every page earns. The treasury
+        // holds at least what was claimed today.
+        fix_crank_reward(progress, &self.policy, u64::MAX)?;
+        let crank_reward_claimed = self.crank_reward_claimed;
+        let pay_reward = move |reward: u64| Ok(if crank_reward_claimed { reward } else { 0 });
+        let count = outcome.investors.len();
+        for _ in 0..count.div_ceil(self.page_size).max(1) {
+            pay_page_crank_reward(progress, pay_reward)?;
+        }
+        progress.locked_total_today = input.locked.iter().try_fold(0u64, |total, &locked| {
+            total
+                .checked_add(locked)
+                .ok_or(FeeRouterError::ArithmeticOverflow)
+        })?;
+        close_snapshot(progress, &self.policy, u64::MAX)?;
+        outcome.locked_total = progress.locked_total_today;
+        outcome.investor_pool = progress.investor_pool_today;
+        outcome
+            .events
+            .push(Event::InvestorSnapshotClosed(InvestorSnapshotClosed {
+                day,
+                locked_total: progress.locked_total_today,
+                investor_pool: progress.investor_pool_today,
+                crank_reward: progress.crank_reward_today,
+            }));
+
+        match self.policy.claim_mode {
+            ClaimMode::Pull => {
+                // No payout pass: shares are what each investor accrues for `claim_fees`.
+                accrue_pull_pool(progress)?;
+                for investor in &mut outcome.investors {
+                    investor.share = compute_investor_quote(
+                        progress.investor_pool_today,
+                        investor.locked,
+                        progress.locked_total_today,
+                    )?;
+                }
+            }
+            ClaimMode::Push => {
+                let mut cursor = 0;
+                let mut pages = 0;
+                while cursor < count {
+                    if input.end == DayEnd::StalledInPayout(pages) {
+                        self.force_close(&mut outcome)?;
+                        return Ok(outcome);
+                    }
+                    let end = (cursor + self.page_size).min(count);
+                    let mut entries: Vec<PayoutEntry> = (cursor..end)
+                        .map(|index| PayoutEntry {
+                            locked: input.locked[index],
+                            dust: self.dust[index],
+                            ..Default::default()
+                        })
+                        .collect();
+                    let transfer_fee = self.transfer_fee;
+                    let totals = run_payout_page(
+                        progress,
+                        &self.policy,
+                        &mut entries,
+                        |_, amount| {
+                            let fee = match &transfer_fee {
+                                Some(fee) => fee
+                                    .calculate_fee(amount)
+                                    .ok_or(FeeRouterError::ArithmeticOverflow)?,
+                                None => 0,
+                            };
+                            Ok(amount - fee)
+                        },
+                        pay_reward,
+                    )?;
+                    for (offset, entry) in entries.iter().enumerate() {
+                        let investor = &mut outcome.investors[cursor + offset];
+                        investor.share = entry.share;
+                        investor.payout = entry.payout;
+                        investor.received = entry.received;
+                        investor.dust = entry.dust;
+                        self.dust[cursor + offset] = entry.dust;
+                    }
+                    outcome
+                        .events
+                        .push(Event::InvestorPayoutPage(InvestorPayoutPage {
+                            day,
+                            page_cursor: cursor as u64,
+                            investors: (end - cursor) as u32,
+                            paid_total: totals.paid_total,
+                            dust_accrued: totals.dust_accrued,
+                            crank_reward: totals.crank_reward,
+                            carry_after: progress.carry_quote_today,
+                        }));
+                    cursor = end;
+                    pages += 1;
+                }
+            }
+        }
+
+        // Day close.
+        let progress = &mut self.progress;
+        let remainder = progress.undistributed_quote();
+        progress.carry_quote_today = 0;
+        progress.day_closed = true;
+        self.record_totals(&mut outcome);
+        outcome.remainder = self.pay_remainder(day, remainder, &mut outcome.events)?;
+        outcome
+            .events
+            .push(Event::CreatorPayoutDayClosed(CreatorPayoutDayClosed {
+                day,
+                remainder,
+            }));
+        Ok(outcome)
+    }
+
+    /// `force_close_day`: quote claimed but not paid out goes to the remainder, or
+    /// rolls into the next day when the policy says so.
+    fn force_close(&mut self, outcome: &mut DayOutcome) -> Result<()> {
+        let progress = &mut self.progress;
+        let day = progress.current_day;
+        let carry = progress.undistributed_quote();
+        let rolled_over = self.policy.roll_forced_carry;
+        if rolled_over {
+            progress.rolled_over_quote = carry;
+            outcome.rolled_over = carry;
+        }
+        progress.carry_quote_today = 0;
+        progress.day_closed = true;
+        progress.force_closed = true;
+        self.record_totals(outcome);
+        if !rolled_over {
+            outcome.remainder = self.pay_remainder(day, carry, &mut outcome.events)?;
+        }
+        outcome.events.push(Event::DayForceClosed(DayForceClosed {
+            day,
+            carry,
+            rolled_over,
+        }));
+        Ok(())
+    }
+
+    fn record_totals(&self, outcome: &mut DayOutcome) {
+        outcome.crank_reward = self.progress.crank_reward_paid_today;
+        outcome.transfer_fees = self.progress.transfer_fees_today;
+    }
+
+    fn pay_remainder(
+        &self,
+        day: i64,
+        amount: u64,
+        events: &mut Vec<Event>,
+    ) -> Result<Vec<(RemainderRecipient, u64)>> {
+        let shares = split_remainder(
+            &self.policy.remainder_split,
+            self.policy.creator_quote_ata,
+            amount,
+        )?;
+        events.extend(shares.iter().map(|(recipient, amount)| {
+            Event::RemainderPaid(RemainderPaid {
+                day,
+                recipient: recipient.quote_ata,
+                bps: recipient.bps,
+                amount: *amount,
+            })
+        }));
+        Ok(shares)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use keystone_fee_router::{InvestorSet, LockSource, RemainderSplit};
+
+    fn policy(investor_fee_share_bps: u16, min_payout_lamports: u64, count: u32) -> Policy {
+        Policy {
+            authority: Pubkey::default(),
+            guardian: Pubkey::default(),
+            cp_pool: Pubkey::default(),
+            quote_mint: Pubkey::default(),
+            quote_token_program: Pubkey::default(),
+            creator_quote_ata: Pubkey::new_unique(),
+            remainder_split: RemainderSplit::default(),
+            treasury_quote_ata: Pubkey::default(),
+            investor_fee_share_bps,
+            y0_total: 1_000_000,
+            daily_cap_quote: 0,
+            min_payout_lamports,
+            crank_reward_bps: 0,
+            crank_reward_cap_quote: 0,
+            investor_set: InvestorSet {
+                root: [0; 32],
+                count,
+            },
+            force_close_grace_secs: 0,
+            roll_forced_carry: false,
+            claim_mode: ClaimMode::Push,
+            lock_source: LockSource::Streamflow,
+            epoch_seconds: 86_400,
+            epoch_offset_seconds: 0,
+            max_page_size: 4,
+            position_count: 1,
+            pending_update: None,
+            paused: false,
+            bump: 0,
+            initialized: true,
+        }
+    }
+
+    #[test]
+    fn pays_pro_rata_and_routes_the_remainder() {
+        let policy = Policy {
+            crank_reward_bps: 100,
+            max_page_size: 2,
+            ..policy(2_000, 0, 3)
+        };
+        let creator = policy.creator_quote_ata;
+        let mut sim = Simulator::new(policy, 7).with_page_size(2);
+        let day = sim
+            .run_day(&DayInput {
+                claimed_quote: 10_100,
+                locked: vec![300_000, 100_000, 100_000],
+                end: DayEnd::Closed,
+            })
+            .unwrap();
+        // Reward 101 over four pages, 25 each (the residue stays with the split); 20%
+        // of the remaining 10_000 to investors.
+        assert_eq!(day.crank_reward, 100);
+        assert_eq!(day.investor_pool, 2_000);
+        let paid: Vec<u64> = day.investors.iter().map(|i| i.payout).collect();
+        assert_eq!(paid, [1_200, 400, 400]);
+        assert_eq!(day.remainder_total(), 10_100 - 100 - 2_000);
+        assert_eq!(day.remainder[0].0.quote_ata, creator);
+
+        let pages: Vec<(u64, u64)> = day
+            .events
+            .iter()
+            .filter_map(|event| match event {
+                Event::InvestorPayoutPage(page) => Some((page.page_cursor, page.crank_reward)),
+                _ => None,
+            })
+            .collect();
+        // Payout pages earn the same as the two snapshot pages before them.
+        assert_eq!(pages, [(0, 25), (2, 25)]);
+        assert!(matches!(
+            day.events.last(),
+            Some(Event::CreatorPayoutDayClosed(CreatorPayoutDayClosed { day: 7, remainder }))
+                if *remainder == day.remainder_total()
+        ));
+    }
+
+    #[test]
+    fn dust_carries_across_days() {
+        // 250 a day against a 500 threshold: paid every second day.
+        let mut sim = Simulator::new(policy(10_000, 500, 1), 1);
+        let day = DayInput {
+            claimed_quote: 1_000,
+            locked: vec![250_000],
+            end: DayEnd::Closed,
+        };
+        let days = sim.replay(&[day.clone(), day.clone(), day]).unwrap();
+        let paid: Vec<(u64, u64)> = days
+            .iter()
+            .map(|day| (day.investors[0].payout, day.investors[0].dust))
+            .collect();
+        assert_eq!(paid, [(0, 250), (500, 0), (0, 250)]);
+        // Dust stays allocated to the investor, not the creator.
+        assert!(days.iter().all(|day| day.remainder_total() == 750));
+        assert_eq!(sim.dust(), [250]);
+    }
+
+    #[test]
+    fn forced_close_rolls_the_claim_into_the_next_day() {
+        let policy = Policy {
+            roll_forced_carry: true,
+            ..policy(10_000, 0, 1)
+        };
+        let mut sim = Simulator::new(policy, 1);
+        let days = sim
+            .replay(&[
+                DayInput {
+                    claimed_quote: 1_000,
+                    locked: vec![500_000],
+                    end: DayEnd::StalledInSnapshot,
+                },
+                DayInput {
+                    claimed_quote: 600,
+                    locked: vec![500_000],
+                    end: DayEnd::Closed,
+                },
+            ])
+            .unwrap();
+        assert_eq!(days[0].rolled_over, 1_000);
+        assert!(days[0].remainder.is_empty());
+        assert_eq!(
+            days[0].events,
+            [Event::DayForceClosed(DayForceClosed {
+                day: 1,
+                carry: 1_000,
+                rolled_over: true
+            })]
+        );
+        assert_eq!(days[1].claimed_quote, 1_600);
+        assert_eq!(days[1].investors[0].payout, 800);
+        assert_eq!(sim.rolled_over(), 0);
+    }
+
+    #[test]
+    fn transfer_fees_come_out_of_the_investor_share() {
+        let mut sim = Simulator::new(policy(10_000, 0, 2), 1).with_transfer_fee(TransferFee {
+            epoch: 0.into(),
+            maximum_fee: u64::MAX.into(),
+            transfer_fee_basis_points: 100.into(),
+        });
+        let day = sim
+            .run_day(&DayInput {
+                claimed_quote: 10_000,
+                locked: vec![500_000, 500_000],
+                end: DayEnd::Closed,
+            })
+            .unwrap();
+        let received: Vec<u64> = day.investors.iter().map(|i| i.received).collect();
+        assert_eq!(received, [4_950, 4_950]);
+        assert_eq!(day.transfer_fees, 100);
+        // The withheld fee is not remainder: the treasury never held it.
+        assert_eq!(day.remainder_total(), 0);
+    }
+
+    #[test]
+    fn forced_close_after_the_snapshot_settles_the_unpaid_pool() {
+        let mut sim = Simulator::new(policy(10_000, 0, 2), 1);
+        let day = sim
+            .run_day(&DayInput {
+                claimed_quote: 10_000,
+                locked: vec![500_000, 500_000],
+                end: DayEnd::StalledInPayout(1),
+            })
+            .unwrap();
+        assert_eq!(day.investors[0].payout, 5_000);
+        assert_eq!(day.investors[1].payout, 0);
+        assert_eq!(day.remainder_total(), 5_000);
+        assert!(matches!(
+            day.events.as_slice(),
+            [
+                Event::InvestorSnapshotClosed(_),
+                Event::InvestorPayoutPage(_),
+                Event::RemainderPaid(_),
+                Event::DayForceClosed(DayForceClosed {
+                    day: 1,
+                    carry: 5_000,
+                    rolled_over: false
+                }),
+            ]
+        ));
+        assert!(sim.progress().force_closed);
+    }
+}